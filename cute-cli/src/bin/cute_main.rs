@@ -3,7 +3,7 @@ use std::time::Duration;
 use tokio_stream::StreamExt;
 use cute_cli::context::TestContext;
 use cute_cli::tasks::*;
-use cute_core::{bin_deserialize, CuteError, ProcManager};
+use cute_core::{CuteError, ProcManager, StreamSchedule};
 use log::{info, warn};
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
@@ -11,19 +11,16 @@ async fn main() -> Result<(), CuteError> {
     cute_log::init_logger();
 
     let ctx = Arc::new(tokio::sync::RwLock::new(TestContext::default()));
-    tokio::spawn({
-        let arc_ctx = ctx.clone();
-        async move {
-            let mut proc_map = ProcManager::new();
-            proc_map.insert(0, Box::new(EchoTaskConstructor::default()));
-            proc_map.insert(1, Box::new(TestTaskConstructor::default()));
-
-            match cute_network::Server::create_raw(cute_network::NetworkConfig::default()).start_server(Box::new(proc_map),arc_ctx).await {
-                Ok(_) => {}
-                Err(_) => {}
-            }
-        }
-    });
+    let proc_map = ProcManager::new();
+    proc_map.register::<EchoTask>()?;
+    proc_map.register::<TestTask>()?;
+    // 첫 인자로 받은 directory 의 plugin 을 등록.
+    if let Some(plugin_dir) = std::env::args().nth(1) {
+        let protocols = unsafe { proc_map.load_plugins(&plugin_dir)? };
+        info!("plugins in {} : {:?}", plugin_dir, protocols);
+    }
+    let server = cute_network::Server::create_raw(cute_network::NetworkConfig::default())
+        .start_server(Box::new(proc_map.clone()), ctx.clone()).await?;
 
     tokio::time::sleep(Duration::from_secs(1)).await;
 
@@ -33,20 +30,28 @@ async fn main() -> Result<(), CuteError> {
             let mut client = cute_network::Client::<TestContext>::create_raw(cute_network::NetworkConfig {
                 max_page_byte_size: 65536,
                 max_channel_size: 128,
+                max_message_size: 67_108_864,
+                max_pending_messages: 16,
                 request_limit_milli_second : 262_144,
                 host_address:  std::net::SocketAddr::from(([127,0,0,1], 7777)),
                 time_out: 30,
                 keep_alive_time_out: 60,
+                shutdown_time_out: 10,
+                compress_type: cute_network::CompressType::Lz4,
+                compress_threshold: 1024,
+                tls: None,
+                auth: Default::default(),
+                reconnect: None,
+                retry: None,
+                rate_limit: Default::default(),
+                backpressure: Default::default(),
             },arc_ctx).await.unwrap();
 
-            match client.get_stream(0,None).await {
+            match client.get_typed_stream::<EchoProtocol>(&(), StreamSchedule::Continuous).await {
                 Ok(mut stream) => {
                     loop {
                         if let Some(res_output) = stream.next().await {
-                            if let Ok(output) = res_output {
-                                let convert_result = bin_deserialize::<EchoData>(&*output);
-                                info!("{:?}", convert_result);
-                            }
+                            info!("{:?}", res_output);
                         }
                     }
                 }
@@ -63,24 +68,32 @@ async fn main() -> Result<(), CuteError> {
             let mut client = cute_network::Client::<TestContext>::create_raw(cute_network::NetworkConfig {
                 max_page_byte_size: 65536,
                 max_channel_size: 128,
+                max_message_size: 67_108_864,
+                max_pending_messages: 16,
                 request_limit_milli_second : 262_144,
                 host_address:  std::net::SocketAddr::from(([127,0,0,1], 7777)),
                 time_out: 30,
                 keep_alive_time_out: 60,
+                shutdown_time_out: 10,
+                compress_type: cute_network::CompressType::Lz4,
+                compress_threshold: 1024,
+                tls: None,
+                auth: Default::default(),
+                reconnect: None,
+                retry: None,
+                rate_limit: Default::default(),
+                backpressure: Default::default(),
             },arc_ctx).await.unwrap();
 
             let instant = tokio::time::Instant::now();
-            match client.get_stream(1,None).await {
+            match client.get_typed_stream::<TestProtocol>(&(), StreamSchedule::Continuous).await {
                 Ok(mut stream) => {
                     while let Some(res_output) = stream.next().await {
                         if instant.elapsed().as_millis() > 30_000 {
                             break;
                         } else {
-                            if let Ok(output) = res_output {
-                                let convert_result = bin_deserialize::<TestData>(&*output);
-                                if let Ok(data) = convert_result {
-                                    info!("{:?}", data.data);
-                                }
+                            if let Ok(data) = res_output {
+                                info!("{:?}", data.data);
                             }
                         }
                     }
@@ -96,9 +109,9 @@ async fn main() -> Result<(), CuteError> {
     });
 
 
-    loop {
-        tokio::time::sleep(Duration::from_secs(1)).await;
-    }
+    tokio::signal::ctrl_c().await?;
+    server.shutdown().await?;
+    info!("server closed");
 
     Ok(())
 }
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
-use cute_core::{bin_serialize, CuteError, Task};
+use cute_core::{create_protocol, CallInfo, CuteError, TypedTask};
 use cute_embadded::*;
 use crate::context::TestContext;
 
@@ -18,27 +18,33 @@ pub struct TestData {
 }
 
 
+create_protocol!(EchoProtocol, 0, (), EchoData);
+create_protocol!(TestProtocol, 1, (), TestData);
+
 #[async_trait::async_trait]
-impl Task<TestContext> for EchoTask {
-    fn new(_input: Option<Box<[u8]>>) -> Result<Box<dyn Task<TestContext> + Send>, CuteError>
+impl TypedTask<TestContext> for EchoTask {
+    type Protocol = EchoProtocol;
+
+    fn new(_input: ()) -> Result<Self, CuteError>
     where
         Self: Sized
     {
-        Ok(Box::new(Self {}))
+        Ok(Self {})
     }
 
-    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<TestContext>>) -> Result<Option<Vec<u8>>, CuteError> {
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<TestContext>>, call : &CallInfo) -> Result<Option<EchoData>, CuteError> {
         tokio::time::sleep(tokio::time::Duration::from_millis(600)).await;
 
         let mut writer = ctx.write().await;
         writer.test += 1;
         drop(writer);
+        call.context_watch.notify();
 
         let reader = ctx.read().await;
         let echo = EchoData { data : reader.test };
         drop(reader);
 
-        Ok(Some(bin_serialize(echo)?))
+        Ok(Some(echo))
     }
 
     async fn destroy(&mut self) {
@@ -50,25 +56,26 @@ pub struct TestTask {
 }
 
 #[async_trait::async_trait]
-impl Task<TestContext> for TestTask {
+impl TypedTask<TestContext> for TestTask {
+    type Protocol = TestProtocol;
 
-    fn new(_input: Option<Box<[u8]>>) -> Result<Box<dyn Task<TestContext> + Send>, CuteError>
+    fn new(_input: ()) -> Result<Self, CuteError>
     where
         Self: Sized
     {
-        Ok(Box::new(Self {
+        Ok(Self {
             test: Vec::new()
-        }))
+        })
     }
 
-    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<TestContext>>) -> Result<Option<Vec<u8>>, CuteError> {
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<TestContext>>, _call : &CallInfo) -> Result<Option<TestData>, CuteError> {
         tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
 
         let reader = ctx.read().await;
         let echo = TestData { data : reader.test * 2, empty_data : vec![0;100_000] };
         drop(reader);
 
-        Ok(Some(bin_serialize(echo)?))
+        Ok(Some(echo))
     }
 
     async fn destroy(&mut self) {
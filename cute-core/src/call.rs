@@ -0,0 +1,110 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use crate::errors::CuteError;
+use crate::schedule::ContextWatch;
+
+/// # Comment
+/// 인증된 요청자의 정보.
+///
+/// Server 에 등록된 인증 방식 (token, API key, mTLS 인증서 등) 을 통해 생성된다.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    /// 요청자를 구분하는 이름.
+    pub name : String,
+    /// 권한 확인시 사용하는 역할 목록.
+    pub roles : Vec<String>,
+}
+
+impl Identity {
+    pub fn new(name : impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            roles: Vec::new(),
+        }
+    }
+
+    pub fn with_role(mut self, role : impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    pub fn has_role(&self, role : &str) -> bool {
+        self.roles.iter().any(|item| item == role)
+    }
+}
+
+/// # Comment
+/// `Task::execute` 에 전달되는 요청 정보.
+///
+/// 인증을 사용하지 않는 경우 `identity` 는 `None` 이다.
+///
+/// 오래 걸리는 `execute` 는 `cancel` 을 확인하여 요청자가 떠난 경우 일찍 끝낼 수 있다.
+#[derive(Debug, Clone, Default)]
+pub struct CallInfo {
+    /// 요청된 protocol.
+    pub protocol : u32,
+    /// 요청한 peer 의 주소. 알 수 없는 경우 `None`.
+    pub peer_addr : Option<SocketAddr>,
+    /// 인증된 요청자.
+    pub identity : Option<Identity>,
+    /// Context 를 변경한 경우 `notify` 를 호출하여 `StreamSchedule::OnChange` stream 에 알린다.
+    pub context_watch : ContextWatch,
+    /// 요청 제한 시각. unary 요청에만 적용되며 지나면 server 는 `cancel` 후 `DeadlineExceeded` 를 응답한다.
+    pub deadline : Option<Instant>,
+    /// 연결 해제, stream 종료, 요청 취소 및 deadline 초과시 cancel 된다.
+    pub cancel : CancellationToken,
+}
+
+impl CallInfo {
+    pub fn new(protocol : u32, peer_addr : Option<SocketAddr>, identity : Option<Identity>) -> Self {
+        Self {
+            protocol,
+            peer_addr,
+            identity,
+            context_watch: ContextWatch::default(),
+            deadline: None,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_context_watch(mut self, context_watch : ContextWatch) -> Self {
+        self.context_watch = context_watch;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline : Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel : CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// deadline 까지 남은 시간. deadline 이 없으면 `None`.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// `future` 를 deadline 까지 실행. 지나면 `cancel` 후 중단하며 `DeadlineExceeded` 를 반환한다.
+    pub async fn until_deadline<T>(&self, future : impl Future<Output = Result<T, CuteError>>) -> Result<T, CuteError> {
+        match self.deadline {
+            None => future.await,
+            Some(deadline) => match tokio::time::timeout_at(deadline, future).await {
+                Ok(result) => result,
+                Err(_) => {
+                    self.cancel.cancel();
+                    Err(CuteError::deadline_exceeded(format!("protocol {} deadline exceeded", self.protocol)))
+                }
+            },
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{Display, Formatter};
 use std::io::{ErrorKind};
+use std::time::Duration;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum CuteErrorCode {
@@ -22,13 +23,21 @@ pub enum CuteErrorCode {
     /// `ProxyWorker` 와 같이 권한 대행을 실행 후 실패한 경우 사용.
     Unauthenticated = 8,
     /// Error 는 발생했지만 Error 로 처리하고 싶지 않는 경우 사용.
-    Ok = 9
+    Ok = 9,
+    /// 이미 등록된 key 등을 다시 등록하는 경우.
+    AlreadyExists = 10,
+    /// 연결이 끊기는 등 일시적으로 요청할 수 없는 경우. 다시 요청하면 성공할 수 있다.
+    Unavailable = 11,
+    /// 요청 수 제한 등으로 server 가 요청을 거부한 경우. `CuteError::retry_after` 만큼 기다린 후 다시 요청한다.
+    ResourceExhausted = 12,
 }
 
 #[derive(Debug, Clone)]
 pub struct CuteError {
     pub code : CuteErrorCode,
     pub message : String,
+    /// 다시 요청하기 전에 기다려야 하는 시간. server 가 알려준 경우에만 `Some`.
+    pub retry_after : Option<Duration>,
 }
 
 impl Default for CuteError {
@@ -36,17 +45,46 @@ impl Default for CuteError {
         Self {
             code : CuteErrorCode::Ok,
             message: "".to_string(),
+            retry_after: None,
+        }
+    }
+}
+
+impl TryFrom<u32> for CuteErrorCode {
+    type Error = CuteError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(CuteErrorCode::SerializeInvalid),
+            2 => Ok(CuteErrorCode::DeSerializeInvalid),
+            3 => Ok(CuteErrorCode::DeadlineExceeded),
+            4 => Ok(CuteErrorCode::PermissionDenied),
+            5 => Ok(CuteErrorCode::NotFound),
+            6 => Ok(CuteErrorCode::Internal),
+            7 => Ok(CuteErrorCode::Cancelled),
+            8 => Ok(CuteErrorCode::Unauthenticated),
+            9 => Ok(CuteErrorCode::Ok),
+            10 => Ok(CuteErrorCode::AlreadyExists),
+            11 => Ok(CuteErrorCode::Unavailable),
+            12 => Ok(CuteErrorCode::ResourceExhausted),
+            _ => Err(CuteError::deserialize_invalid(format!("unknown error code {}", value))),
         }
     }
 }
 
 impl CuteError {
-    fn new(code : CuteErrorCode, msg : impl Into<String>) -> Self {
+    pub fn new(code : CuteErrorCode, msg : impl Into<String>) -> Self {
         Self {
             code,
-            message: msg.into()
+            message: msg.into(),
+            retry_after: None,
         }
     }
+
+    pub fn with_retry_after(mut self, retry_after : Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
     pub fn serialize(&self) -> Vec<u8> {
         format!("{:?} : {}",self.code,self.message).as_bytes().to_vec()
     }
@@ -86,6 +124,18 @@ impl CuteError {
     pub fn ok(msg : impl Into<String>) -> CuteError {
         CuteError::new(CuteErrorCode::Ok, msg)
     }
+
+    pub fn already_exists(msg : impl Into<String>) -> CuteError {
+        CuteError::new(CuteErrorCode::AlreadyExists, msg)
+    }
+
+    pub fn unavailable(msg : impl Into<String>) -> CuteError {
+        CuteError::new(CuteErrorCode::Unavailable, msg)
+    }
+
+    pub fn resource_exhausted(msg : impl Into<String>) -> CuteError {
+        CuteError::new(CuteErrorCode::ResourceExhausted, msg)
+    }
 }
 
 impl Display for CuteError {
@@ -121,8 +171,19 @@ impl From<std::io::Error> for CuteError {
             ErrorKind::Unsupported => {
                 Self::unauthenticated(value.to_string())
             }
+            ErrorKind::AlreadyExists => {
+                Self::already_exists(value.to_string())
+            }
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof => {
+                Self::unavailable(value.to_string())
+            }
             _ => {
-                Self::internal(format!("{}. {}",value.kind(),value.to_string()))
+                Self::internal(format!("{}. {}",value.kind(),value))
             }
         }
     }
@@ -140,6 +201,9 @@ impl From<CuteError> for std::io::Error {
             CuteErrorCode::Cancelled => ErrorKind::Interrupted,
             CuteErrorCode::Unauthenticated => ErrorKind::Unsupported,
             CuteErrorCode::Internal => ErrorKind::Other,
+            CuteErrorCode::AlreadyExists => ErrorKind::AlreadyExists,
+            CuteErrorCode::Unavailable => ErrorKind::NotConnected,
+            CuteErrorCode::ResourceExhausted => ErrorKind::Other,
         };
 
         std::io::Error::new(kind, value.message)
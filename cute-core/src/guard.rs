@@ -0,0 +1,169 @@
+use std::ops::{Deref, DerefMut};
+use tokio_util::task::TaskTracker;
+use crate::{BidiTask, StreamTask, Task};
+
+/// # Comment
+/// `TaskGuard` 에서 종료시 호출할 `destroy`.
+///
+/// `Task`, `StreamTask` 및 `BidiTask` 에 구현되어 있다.
+#[async_trait::async_trait]
+pub trait TaskLifecycle : Send {
+    async fn destroy(&mut self);
+}
+
+#[async_trait::async_trait]
+impl<C : 'static> TaskLifecycle for dyn Task<C> + Send {
+    async fn destroy(&mut self) {
+        Task::destroy(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<C : 'static> TaskLifecycle for dyn StreamTask<C> + Send {
+    async fn destroy(&mut self) {
+        StreamTask::destroy(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<C : 'static> TaskLifecycle for dyn BidiTask<C> + Send {
+    async fn destroy(&mut self) {
+        BidiTask::destroy(self).await
+    }
+}
+
+/// # Comment
+/// `Task` 의 생존주기를 보장하기 위한 Guard.
+///
+/// 정상 종료, 오류시에는 `destroy` 를 직접 호출하여 종료한다.
+///
+/// client 연결 해제, server 종료 등으로 작업중인 future 가 drop 되는 경우
+/// `destroy` 가 호출되지 않았다면 Drop 에서 tokio runtime 에 `destroy` 를 spawn 한다.
+pub struct TaskGuard<T : TaskLifecycle + ?Sized + 'static> {
+    task : Option<Box<T>>,
+    tracker : Option<TaskTracker>,
+}
+
+impl<T : TaskLifecycle + ?Sized + 'static> TaskGuard<T> {
+    pub fn new(task : Box<T>) -> Self {
+        Self {
+            task: Some(task),
+            tracker: None,
+        }
+    }
+
+    /// Drop 에서 spawn 하는 `destroy` 를 `tracker` 로 추적함. server 는 종료시 `tracker` 를 기다린다.
+    pub fn with_tracker(task : Box<T>, tracker : TaskTracker) -> Self {
+        Self {
+            task: Some(task),
+            tracker: Some(tracker),
+        }
+    }
+
+    /// `destroy` 를 호출 후 Guard 를 종료한다.
+    pub async fn destroy(mut self) {
+        if let Some(mut task) = self.task.take() {
+            task.destroy().await;
+        }
+    }
+}
+
+impl<T : TaskLifecycle + ?Sized + 'static> Deref for TaskGuard<T> {
+    type Target = Box<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.task.as_ref().expect("task already destroyed")
+    }
+}
+
+impl<T : TaskLifecycle + ?Sized + 'static> DerefMut for TaskGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.task.as_mut().expect("task already destroyed")
+    }
+}
+
+impl<T : TaskLifecycle + ?Sized + 'static> Drop for TaskGuard<T> {
+    fn drop(&mut self) {
+        if let Some(mut task) = self.task.take() {
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => {
+                    let destroy = async move {
+                        task.destroy().await;
+                    };
+                    match &self.tracker {
+                        Some(tracker) => handle.spawn(tracker.track_future(destroy)),
+                        None => handle.spawn(destroy),
+                    };
+                }
+                Err(_) => {
+                    log::warn!("tokio runtime not found. task dropped without destroy");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::{CallInfo, CuteError, Task};
+    use super::*;
+
+    struct Counting {
+        destroyed : Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Task<()> for Counting {
+        fn new(_input : Option<Box<[u8]>>) -> Result<Box<dyn Task<()> + Send>, CuteError> {
+            Err(CuteError::internal("use counting()"))
+        }
+
+        async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<()>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+            Ok(None)
+        }
+
+        async fn destroy(&mut self) {
+            self.destroyed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counting() -> (TaskGuard<dyn Task<()> + Send>, Arc<AtomicUsize>) {
+        let destroyed = Arc::new(AtomicUsize::new(0));
+        let task : Box<dyn Task<()> + Send> = Box::new(Counting { destroyed: destroyed.clone() });
+        (TaskGuard::new(task), destroyed)
+    }
+
+    #[tokio::test]
+    async fn drop_without_destroy_destroys_once() {
+        let (guard, destroyed) = counting();
+        drop(guard);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn destroy_then_drop_does_not_destroy_twice() {
+        let (guard, destroyed) = counting();
+        guard.destroy().await;
+        assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dropped_future_destroys_once() {
+        let (mut guard, destroyed) = counting();
+        let running = tokio::spawn(async move {
+            guard.execute(Arc::new(tokio::sync::RwLock::new(())), &CallInfo::new(0, None, None)).await.ok();
+            std::future::pending::<()>().await;
+            guard.destroy().await;
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        running.abort();
+        let _ = running.await;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(destroyed.load(Ordering::SeqCst), 1);
+    }
+}
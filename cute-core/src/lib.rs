@@ -3,6 +3,20 @@ use std::sync::Arc;
 pub use self::procs::*;
 pub use self::serdes::*;
 pub use self::errors::{CuteError, CuteErrorCode};
+pub use self::call::{CallInfo, Identity};
+pub use self::guard::{TaskGuard, TaskLifecycle};
+pub use self::schedule::{ContextWatch, StreamSchedule, StreamTicker};
+pub use self::typed::{CuteProtocol, TypedTask, TypedTaskConstructor};
+pub use self::reflection::{ProtocolInfo, ProtocolMeta, TaskKind};
+pub use self::plugin::{PluginDeclaration, PluginRegistrar, PluginStr, CUTE_PLUGIN_ABI_VERSION};
+pub use cute_macro::cute_task;
+
+/// `cute_task` 에서 생성한 code 가 사용함.
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+    pub use tokio::sync::RwLock;
+}
 pub type DataStream<T> = Pin<Box<dyn tokio_stream::Stream<Item = Result<T, CuteError>> + Send>>;
 
 /// # Comment
@@ -16,18 +30,65 @@ pub trait Task<C> {
     /// 생성자.
     ///
     /// 입력받은 input 을 `serde::Deserialize` 해도 되게 안해도 되도록 구현.
+    #[allow(clippy::new_ret_no_self)]
     fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn Task<C> + Send>, CuteError>
     where Self: Sized;
 
     /// 생성자에서 생성한 후에 동작을 수행후 결과를 반환한다.
     ///
     /// mut 가능하도록 한 것은 자기 자신 내부에서 Task 를 생성해 그 Task 결과가 동적 프로그래밍과 같이 작동할 수 있기에 mutable 하도록 함.
-    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<C>>) -> Result<Option<Vec<u8>>, CuteError>;
+    ///
+    /// `call` 에는 요청한 peer 및 인증된 `Identity` 가 기록되어 있다.
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<C>>, call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError>;
 
     /// 경우에 따라 생성자에서 생성시에 나온 Member 변수를 할당해제등을 수행하거나 생존주기를 종료시킬떄 사용.
     async fn destroy(&mut self);
 }
 
+/// # Comment
+/// 한번의 `execute` 로 여러 결과를 만드는 작업을 정의.
+///
+/// sensor buffer 읽기, DB query paging 등 결과가 순차적으로 나오는 경우 사용한다.
+///
+/// 반환한 stream 이 끝나면 client 의 stream 도 종료되며 이후 `destroy` 가 호출된다.
+#[async_trait::async_trait]
+pub trait StreamTask<C> {
+    /// 생성자. `Task::new` 와 동일.
+    #[allow(clippy::new_ret_no_self)]
+    fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn StreamTask<C> + Send>, CuteError>
+    where Self: Sized;
+
+    /// 결과를 stream 으로 반환한다.
+    ///
+    /// stream 은 `self` 를 빌릴 수 없으므로 필요한 상태는 stream 으로 옮겨서 사용한다.
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<C>>, call : &CallInfo) -> Result<DataStream<Vec<u8>>, CuteError>;
+
+    /// stream 종료 후 호출됨. `Task::destroy` 와 동일.
+    async fn destroy(&mut self);
+}
+
+/// # Comment
+/// 입력을 stream 으로 받는 작업을 정의.
+///
+/// embedded board 에 setpoint 를 계속 보내며 결과를 받는 경우 등에 사용한다.
+///
+/// client streaming 요청은 반환한 stream 의 마지막 결과를 응답으로 사용하며 bidi streaming 요청은 모든 결과를 전달한다.
+#[async_trait::async_trait]
+pub trait BidiTask<C> {
+    /// 생성자. stream 을 열때 받은 input 을 사용하며 `Task::new` 와 동일.
+    #[allow(clippy::new_ret_no_self)]
+    fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn BidiTask<C> + Send>, CuteError>
+    where Self: Sized;
+
+    /// client 가 보내는 `input` stream 을 받아 결과 stream 을 반환한다.
+    ///
+    /// client 가 입력을 종료하거나 연결이 끊어지면 `input` 은 종료된다.
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<C>>, call : &CallInfo, input : DataStream<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError>;
+
+    /// 결과 stream 종료 후 호출됨. `Task::destroy` 와 동일.
+    async fn destroy(&mut self);
+}
+
 /// # Comment
 /// Task 생성자.
 ///
@@ -43,6 +104,35 @@ where C : Send + Sync + 'static,
     ///
     /// Impl 하지 않고 dynamic 하게 하여 std::collection 등에 추가하기 쉽게 하였다.
     fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn Task<C> + Send>, CuteError>;
+
+    /// reflection 에 사용할 이름, 설명 및 schema.
+    fn metadata(&self) -> ProtocolMeta {
+        ProtocolMeta::default()
+    }
+}
+
+/// # Comment
+/// StreamTask 생성자. `TaskConstructor` 와 동일하며 macro 를 통해 만들 수 있다.
+pub trait StreamTaskConstructor<C> : Send + Sync
+where C : Send + Sync + 'static,
+{
+    fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn StreamTask<C> + Send>, CuteError>;
+
+    fn metadata(&self) -> ProtocolMeta {
+        ProtocolMeta::default()
+    }
+}
+
+/// # Comment
+/// BidiTask 생성자. `TaskConstructor` 와 동일하며 macro 를 통해 만들 수 있다.
+pub trait BidiTaskConstructor<C> : Send + Sync
+where C : Send + Sync + 'static,
+{
+    fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn BidiTask<C> + Send>, CuteError>;
+
+    fn metadata(&self) -> ProtocolMeta {
+        ProtocolMeta::default()
+    }
 }
 
 /// # Comment
@@ -55,13 +145,41 @@ where C : Send + Sync + 'static,
 pub trait Procedure<C> {
     /// 현재 std::collection 에 기록된 모든 작업 내용을 반환.
     async fn get_service_protocols(&self) -> Result<Vec<u32>, CuteError>;
+    /// 기록된 작업의 종류 및 설명을 반환. 기본은 `get_service_protocols` 의 key 만 기록한다.
+    async fn get_service_infos(&self) -> Result<Vec<ProtocolInfo>, CuteError> {
+        Ok(self.get_service_protocols().await?
+            .into_iter()
+            .map(|protocol| ProtocolInfo::new(protocol, TaskKind::Task, ProtocolMeta::default()))
+            .collect())
+    }
     /// 해당 이름을 가진 작업을 반환.
     async fn get_task(&self, key : u32, input : Option<Box<[u8]>>) -> Result<Box<dyn Task<C> + Send>, CuteError>;
+    /// 해당 이름을 가진 StreamTask 를 반환. 없는 경우 `NotFound` 이며 server 는 `get_task` 로 처리한다.
+    async fn get_stream_task(&self, key : u32, _input : Option<Box<[u8]>>) -> Result<Box<dyn StreamTask<C> + Send>, CuteError> {
+        Err(CuteError::not_found(format!("StreamTask \"{}\" not found", key)))
+    }
+    /// 해당 이름을 가진 BidiTask 를 반환. client streaming 및 bidi streaming 요청에 사용된다.
+    async fn get_bidi_task(&self, key : u32, _input : Option<Box<[u8]>>) -> Result<Box<dyn BidiTask<C> + Send>, CuteError> {
+        Err(CuteError::not_found(format!("BidiTask \"{}\" not found", key)))
+    }
+    /// 등록 변경 알림. 지원하지 않는 경우 `None` 이며 server 는 client 에 알리지 않는다.
+    fn subscribe_changes(&self) -> Option<tokio::sync::broadcast::Receiver<ProcEvent>> {
+        None
+    }
 }
 
 #[macro_export]
 macro_rules! create_task_constructor {
     ($task : ident,$constructor : ident,$context: ident) => {
+        $crate::create_task_constructor!($task, $constructor, $context, "");
+    };
+    ($task : ident,$constructor : ident,$context: ident,$description : expr) => {
+        $crate::create_task_constructor!(@define $task, $constructor, $context, $description, false);
+    };
+    ($task : ident,$constructor : ident,$context: ident,$description : expr, idempotent) => {
+        $crate::create_task_constructor!(@define $task, $constructor, $context, $description, true);
+    };
+    (@define $task : ident,$constructor : ident,$context: ident,$description : expr,$idempotent : expr) => {
         #[derive(Debug, Clone, Default)]
         pub struct $constructor;
 
@@ -69,10 +187,62 @@ macro_rules! create_task_constructor {
             fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn Task<$context> + Send>, CuteError> {
                 $task::new(input)
             }
+
+            fn metadata(&self) -> $crate::ProtocolMeta {
+                $crate::ProtocolMeta::new(stringify!($task)).with_description($description).with_idempotent($idempotent)
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! create_stream_task_constructor {
+    ($task : ident,$constructor : ident,$context: ident) => {
+        $crate::create_stream_task_constructor!($task, $constructor, $context, "");
+    };
+    ($task : ident,$constructor : ident,$context: ident,$description : expr) => {
+        #[derive(Debug, Clone, Default)]
+        pub struct $constructor;
+
+        impl StreamTaskConstructor<$context> for $constructor {
+            fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn StreamTask<$context> + Send>, CuteError> {
+                $task::new(input)
+            }
+
+            fn metadata(&self) -> $crate::ProtocolMeta {
+                $crate::ProtocolMeta::new(stringify!($task)).with_description($description)
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! create_bidi_task_constructor {
+    ($task : ident,$constructor : ident,$context: ident) => {
+        $crate::create_bidi_task_constructor!($task, $constructor, $context, "");
+    };
+    ($task : ident,$constructor : ident,$context: ident,$description : expr) => {
+        #[derive(Debug, Clone, Default)]
+        pub struct $constructor;
+
+        impl BidiTaskConstructor<$context> for $constructor {
+            fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn BidiTask<$context> + Send>, CuteError> {
+                $task::new(input)
+            }
+
+            fn metadata(&self) -> $crate::ProtocolMeta {
+                $crate::ProtocolMeta::new(stringify!($task)).with_description($description)
+            }
         }
     };
 }
 
 mod serdes;
 mod procs;
-mod errors;
\ No newline at end of file
+mod errors;
+mod call;
+mod guard;
+mod schedule;
+mod typed;
+mod reflection;
+mod plugin;
\ No newline at end of file
@@ -0,0 +1,252 @@
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use libloading::{Library, Symbol};
+use log::{info, warn};
+use crate::{BidiTaskConstructor, CuteProtocol, ProcManager, StreamTaskConstructor, TaskConstructor, TypedTask, TypedTaskConstructor};
+use crate::errors::CuteError;
+use crate::procs::ProcConstructor;
+
+/// `PluginDeclaration` 및 등록 fn 의 형태가 바뀌면 올린다.
+pub const CUTE_PLUGIN_ABI_VERSION : u32 = 1;
+
+const DECLARATION_SYMBOL : &[u8] = b"cute_plugin_declaration\0";
+const REGISTER_SYMBOL : &[u8] = b"cute_plugin_register\0";
+
+type DeclarationFn = extern "C" fn() -> PluginDeclaration;
+type RegisterFn = unsafe extern "C" fn(*mut c_void) -> bool;
+
+/// FFI 경계를 넘기 위한 문자열. library 가 불러와진 동안만 유효하다.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginStr {
+    ptr : *const u8,
+    len : usize,
+}
+
+impl PluginStr {
+    pub const fn new(value : &'static str) -> Self {
+        Self {
+            ptr: value.as_ptr(),
+            len: value.len(),
+        }
+    }
+
+    /// # Safety
+    /// 가리키는 library 가 불러와진 상태여야 한다.
+    unsafe fn to_string(self) -> String {
+        String::from_utf8_lossy(std::slice::from_raw_parts(self.ptr, self.len)).to_string()
+    }
+}
+
+/// # Comment
+/// plugin 이 compile 된 환경.
+///
+/// trait object 는 ABI 가 고정되어 있지 않으므로 같은 cute-core, rustc 및 context 로 compile 된 plugin 만 불러온다.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginDeclaration {
+    pub abi_version : u32,
+    pub core_version : PluginStr,
+    pub rustc_version : PluginStr,
+    pub context_type : PluginStr,
+}
+
+impl PluginDeclaration {
+    /// 현재 compile 환경으로 생성. `create_plugin!` 에서 사용한다.
+    pub fn new<C>() -> Self {
+        Self {
+            abi_version: CUTE_PLUGIN_ABI_VERSION,
+            core_version: PluginStr::new(env!("CARGO_PKG_VERSION")),
+            rustc_version: PluginStr::new(env!("CUTE_RUSTC_VERSION")),
+            context_type: PluginStr::new(std::any::type_name::<C>()),
+        }
+    }
+
+    /// # Safety
+    /// 선언을 반환한 library 가 불러와진 상태여야 한다.
+    unsafe fn check<C>(&self) -> Result<(), CuteError> {
+        // abi 가 다르면 이후 field 의 형태를 알 수 없으므로 먼저 확인.
+        if self.abi_version != CUTE_PLUGIN_ABI_VERSION {
+            return Err(Self::incompatible("abi version", self.abi_version, CUTE_PLUGIN_ABI_VERSION));
+        }
+        let host = Self::new::<C>();
+        let fields = [
+            ("cute-core version", self.core_version, host.core_version),
+            ("rustc version", self.rustc_version, host.rustc_version),
+            ("context type", self.context_type, host.context_type),
+        ];
+        for (name, plugin, host) in fields {
+            let (plugin, host) = (plugin.to_string(), host.to_string());
+            if plugin != host {
+                return Err(Self::incompatible(name, format!("\"{}\"", plugin), format!("\"{}\"", host)));
+            }
+        }
+        Ok(())
+    }
+
+    /// host 와 다른 환경으로 compile 된 plugin. 다시 compile 해야 하므로 `Internal` 로 반환한다.
+    fn incompatible(name : &str, actual : impl std::fmt::Display, expected : impl std::fmt::Display) -> CuteError {
+        CuteError::internal(format!("incompatible plugin {} : expected {}, actual {}", name, expected, actual))
+    }
+}
+
+/// # Comment
+/// plugin 의 등록 fn 에 전달되어 생성자를 모음.
+///
+/// 등록 fn 이 끝난 후 `ProcManager` 에 한번에 등록하며 이미 등록된 key 가 있으면 모두 등록하지 않는다.
+pub struct PluginRegistrar<C> {
+    constructors : Vec<(u32, ProcConstructor<C>)>,
+    error : Option<CuteError>,
+}
+
+impl<C> PluginRegistrar<C>
+where C : Send + Sync + 'static
+{
+    fn new() -> Self {
+        Self {
+            constructors: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn insert(&mut self, key : u32, task_constructor : Box<dyn TaskConstructor<C> + Send + Sync + 'static>) {
+        self.constructors.push((key, ProcConstructor::Task(task_constructor)));
+    }
+
+    pub fn insert_stream(&mut self, key : u32, task_constructor : Box<dyn StreamTaskConstructor<C> + Send + Sync + 'static>) {
+        self.constructors.push((key, ProcConstructor::Stream(task_constructor)));
+    }
+
+    pub fn insert_bidi(&mut self, key : u32, task_constructor : Box<dyn BidiTaskConstructor<C> + Send + Sync + 'static>) {
+        self.constructors.push((key, ProcConstructor::Bidi(task_constructor)));
+    }
+
+    /// `TypedTask` 를 `CuteProtocol::ID` 로 등록.
+    pub fn register<T>(&mut self)
+    where T : TypedTask<C> + 'static,
+    {
+        self.insert(T::Protocol::ID, Box::new(TypedTaskConstructor::<C, T>::new()));
+    }
+
+    /// plugin 의 등록 fn 을 실행. panic 은 FFI 경계를 넘지 않도록 여기서 멈춘다.
+    #[doc(hidden)]
+    pub fn run(&mut self, register : fn(&mut PluginRegistrar<C>) -> Result<(), CuteError>) -> bool {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| register(self))) {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                self.error = Some(e);
+                false
+            }
+            Err(_) => {
+                self.error = Some(CuteError::internal("plugin register panicked"));
+                false
+            }
+        }
+    }
+}
+
+impl<C> ProcManager<C>
+where C : Send + Sync + 'static
+{
+    /// `create_plugin!` 으로 만든 cdylib 를 불러와 등록하고 등록된 protocol 을 반환.
+    ///
+    /// 생성자 및 실행중인 Task 가 library 의 code 를 사용하므로 불러온 library 는 해제하지 않는다.
+    ///
+    /// plugin 은 자신의 tokio 를 포함하므로 plugin 의 Task 에서는 `tokio::spawn`, `tokio::time` 등 runtime 이 필요한 API 를 사용할 수 없다.
+    ///
+    /// # Safety
+    /// library 의 초기화 code 가 실행되므로 신뢰할 수 있는 plugin 만 불러와야 한다.
+    /// 선언의 version 이 같더라도 다른 dependency 로 compile 된 경우는 확인할 수 없다.
+    pub unsafe fn load_plugin(&self, path : impl AsRef<Path>) -> Result<Vec<u32>, CuteError> {
+        let path = path.as_ref();
+        let library = Library::new(path)
+            .map_err(|e| CuteError::internal(format!("failed to load plugin {} : {}", path.display(), e)))?;
+        let declaration : Symbol<DeclarationFn> = library.get(DECLARATION_SYMBOL)
+            .map_err(|e| CuteError::deserialize_invalid(format!("{} is not a cute plugin : {}", path.display(), e)))?;
+        declaration().check::<C>()?;
+        let register : Symbol<RegisterFn> = library.get(REGISTER_SYMBOL)
+            .map_err(|e| CuteError::deserialize_invalid(format!("{} is not a cute plugin : {}", path.display(), e)))?;
+
+        let mut registrar = PluginRegistrar::<C>::new();
+        if !register(&mut registrar as *mut PluginRegistrar<C> as *mut c_void) {
+            return Err(registrar.error.take().unwrap_or_else(|| CuteError::internal("plugin register failed")));
+        }
+        let keys = self.insert_all(std::mem::take(&mut registrar.constructors))?;
+        info!("plugin {} loaded : {:?}", path.display(), keys);
+        std::mem::forget(library);
+        Ok(keys)
+    }
+
+    /// directory 의 cdylib 를 이름 순서로 불러옴. 실패한 plugin 은 건너뛰며 등록된 protocol 을 반환.
+    ///
+    /// # Safety
+    /// `load_plugin` 과 동일.
+    pub unsafe fn load_plugins(&self, dir : impl AsRef<Path>) -> Result<Vec<u32>, CuteError> {
+        let mut paths : Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION))
+            .collect();
+        paths.sort();
+
+        let mut keys = Vec::new();
+        for path in paths {
+            match self.load_plugin(&path) {
+                Ok(loaded) => keys.extend(loaded),
+                Err(e) => warn!("plugin {} skipped : {}", path.display(), e),
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// # Comment
+/// cdylib 에서 `ProcManager::load_plugin` 이 찾는 C ABI entry point 를 생성.
+/// ```ignore
+/// fn register(registrar : &mut PluginRegistrar<TestContext>) -> Result<(), CuteError> {
+///     registrar.register::<EchoTask>();
+///     Ok(())
+/// }
+///
+/// create_plugin!(TestContext, register);
+/// ```
+#[macro_export]
+macro_rules! create_plugin {
+    ($context : ty, $register : path) => {
+        #[no_mangle]
+        pub extern "C" fn cute_plugin_declaration() -> $crate::PluginDeclaration {
+            $crate::PluginDeclaration::new::<$context>()
+        }
+
+        /// # Safety
+        /// `registrar` 는 `ProcManager::load_plugin` 이 넘긴 `PluginRegistrar` 이어야 한다.
+        #[no_mangle]
+        pub unsafe extern "C" fn cute_plugin_register(registrar : *mut ::core::ffi::c_void) -> bool {
+            let registrar = &mut *(registrar as *mut $crate::PluginRegistrar<$context>);
+            registrar.run($register)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CuteErrorCode;
+
+    #[test]
+    fn version_mismatch_names_both_versions() {
+        let mut declaration = PluginDeclaration::new::<()>();
+        declaration.abi_version = CUTE_PLUGIN_ABI_VERSION + 1;
+        let error = unsafe { declaration.check::<()>() }.unwrap_err();
+        assert_eq!(error.code, CuteErrorCode::Internal);
+        assert_eq!(error.message, format!("incompatible plugin abi version : expected {}, actual {}", CUTE_PLUGIN_ABI_VERSION, CUTE_PLUGIN_ABI_VERSION + 1));
+
+        let mut declaration = PluginDeclaration::new::<()>();
+        declaration.rustc_version = PluginStr::new("rustc 0.0.0");
+        let error = unsafe { declaration.check::<()>() }.unwrap_err();
+        assert_eq!(error.code, CuteErrorCode::Internal);
+        assert_eq!(error.message, format!("incompatible plugin rustc version : expected \"{}\", actual \"rustc 0.0.0\"", env!("CUTE_RUSTC_VERSION")));
+
+        assert!(unsafe { PluginDeclaration::new::<()>().check::<()>() }.is_ok());
+    }
+}
@@ -1,21 +1,217 @@
-use crate::{Procedure, Task, TaskConstructor};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::broadcast;
+use crate::{BidiTask, BidiTaskConstructor, CuteProtocol, Procedure, ProtocolInfo, ProtocolMeta, StreamTask, StreamTaskConstructor, Task, TaskConstructor, TaskKind, TypedTask, TypedTaskConstructor};
 use crate::errors::CuteError;
 
+/// 변경 알림을 받지 못한 client 가 밀리는 경우 유지할 event 수.
+const PROC_EVENT_CAPACITY : usize = 64;
+
+/// # Comment
+/// `ProcManager` 의 등록 변경 알림.
+///
+/// server 는 연결된 client 에 전달하며 `Removed` 인 protocol 의 stream 을 종료한다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcEvent {
+    Added(u32),
+    Replaced(u32),
+    Removed(u32),
+}
+
+impl ProcEvent {
+    pub fn protocol(&self) -> u32 {
+        match self {
+            ProcEvent::Added(protocol) | ProcEvent::Replaced(protocol) | ProcEvent::Removed(protocol) => *protocol,
+        }
+    }
+
+    /// 전송시 사용하는 종류 값.
+    pub fn kind(&self) -> u32 {
+        match self {
+            ProcEvent::Added(_) => 0,
+            ProcEvent::Replaced(_) => 1,
+            ProcEvent::Removed(_) => 2,
+        }
+    }
+
+    /// `kind` 및 protocol 로 다시 생성.
+    pub fn from_parts(kind : u32, protocol : u32) -> Result<Self, CuteError> {
+        match kind {
+            0 => Ok(ProcEvent::Added(protocol)),
+            1 => Ok(ProcEvent::Replaced(protocol)),
+            2 => Ok(ProcEvent::Removed(protocol)),
+            _ => Err(CuteError::deserialize_invalid(format!("unknown proc event : {}", kind))),
+        }
+    }
+}
+
+/// 등록된 생성자. Task, StreamTask 및 BidiTask 는 같은 key 를 공유한다.
+pub(crate) enum ProcConstructor<C> {
+    Task(Box<dyn TaskConstructor<C> + Send + Sync>),
+    Stream(Box<dyn StreamTaskConstructor<C> + Send + Sync>),
+    Bidi(Box<dyn BidiTaskConstructor<C> + Send + Sync>),
+}
+
+impl<C> ProcConstructor<C>
+where C : Send + Sync + 'static
+{
+    fn kind(&self) -> TaskKind {
+        match self {
+            ProcConstructor::Task(_) => TaskKind::Task,
+            ProcConstructor::Stream(_) => TaskKind::Stream,
+            ProcConstructor::Bidi(_) => TaskKind::Bidi,
+        }
+    }
+
+    fn metadata(&self) -> ProtocolMeta {
+        match self {
+            ProcConstructor::Task(constructor) => constructor.metadata(),
+            ProcConstructor::Stream(constructor) => constructor.metadata(),
+            ProcConstructor::Bidi(constructor) => constructor.metadata(),
+        }
+    }
+}
+
+struct ProcInner<C> {
+    constructor_map : RwLock<HashMap<u32, ProcConstructor<C>>>,
+    event_tx : broadcast::Sender<ProcEvent>,
+}
+
+/// # Comment
+/// 작업 생성자를 key 로 관리.
+///
+/// clone 은 같은 등록 정보를 공유하므로 `Server::start_server` 에 넘긴 후에도 등록, 교체 및 삭제할 수 있다.
 pub struct ProcManager<C> {
-    constructor_map : std::collections::HashMap<u32, Box<dyn TaskConstructor<C> + Send + Sync>>,
-    _phantom_c : std::marker::PhantomData<fn() -> C>,
+    inner : Arc<ProcInner<C>>,
+}
+
+impl<C> Clone for ProcManager<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C> Default for ProcManager<C> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<C> ProcManager<C> {
     pub fn new() -> Self {
+        let (event_tx, _) = broadcast::channel(PROC_EVENT_CAPACITY);
         Self {
-            constructor_map: std::collections::HashMap::new(),
-            _phantom_c: Default::default(),
+            inner: Arc::new(ProcInner {
+                constructor_map: RwLock::new(HashMap::new()),
+                event_tx,
+            }),
+        }
+    }
+
+    /// Task, StreamTask 및 BidiTask 는 같은 key 를 공유하며 이미 등록된 key 는 `AlreadyExists` 를 반환한다.
+    pub fn insert(&self, key : u32, task_constructor : Box<dyn TaskConstructor<C> + Send + Sync + 'static>) -> Result<(), CuteError> {
+        self.add(key, ProcConstructor::Task(task_constructor))
+    }
+
+    /// `StreamTask` 를 등록. stream 요청시에만 사용된다.
+    pub fn insert_stream(&self, key : u32, task_constructor : Box<dyn StreamTaskConstructor<C> + Send + Sync + 'static>) -> Result<(), CuteError> {
+        self.add(key, ProcConstructor::Stream(task_constructor))
+    }
+
+    /// `BidiTask` 를 등록. client streaming 및 bidi streaming 요청시에만 사용된다.
+    pub fn insert_bidi(&self, key : u32, task_constructor : Box<dyn BidiTaskConstructor<C> + Send + Sync + 'static>) -> Result<(), CuteError> {
+        self.add(key, ProcConstructor::Bidi(task_constructor))
+    }
+
+    /// `TypedTask` 를 `CuteProtocol::ID` 로 등록. 입력 및 출력은 `bin_serialize` 로 변환된다.
+    pub fn register<T>(&self) -> Result<(), CuteError>
+    where C : Send + Sync + 'static,
+          T : TypedTask<C> + 'static,
+    {
+        self.insert(T::Protocol::ID, Box::new(TypedTaskConstructor::<C, T>::new()))
+    }
+
+    /// 등록 또는 교체. 실행중인 작업은 이전 생성자로 만든 Task 를 계속 사용한다.
+    pub fn replace(&self, key : u32, task_constructor : Box<dyn TaskConstructor<C> + Send + Sync + 'static>) {
+        self.put(key, ProcConstructor::Task(task_constructor))
+    }
+
+    pub fn replace_stream(&self, key : u32, task_constructor : Box<dyn StreamTaskConstructor<C> + Send + Sync + 'static>) {
+        self.put(key, ProcConstructor::Stream(task_constructor))
+    }
+
+    pub fn replace_bidi(&self, key : u32, task_constructor : Box<dyn BidiTaskConstructor<C> + Send + Sync + 'static>) {
+        self.put(key, ProcConstructor::Bidi(task_constructor))
+    }
+
+    /// 등록 해제. server 는 해당 protocol 의 stream 을 종료한다.
+    pub fn remove(&self, key : u32) -> Result<(), CuteError> {
+        match self.write().remove(&key) {
+            None => {
+                Err(CuteError::not_found(format!("protocol \"{}\" not found", key)))
+            }
+            Some(_) => {
+                let _ = self.inner.event_tx.send(ProcEvent::Removed(key));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key : u32) -> bool {
+        self.read().contains_key(&key)
+    }
+
+    /// 등록 변경 알림을 받음.
+    pub fn subscribe(&self) -> broadcast::Receiver<ProcEvent> {
+        self.inner.event_tx.subscribe()
+    }
+
+    /// 모두 등록하거나 하나라도 이미 등록된 key 인 경우 아무것도 등록하지 않는다.
+    pub(crate) fn insert_all(&self, constructors : Vec<(u32, ProcConstructor<C>)>) -> Result<Vec<u32>, CuteError> {
+        let mut constructor_map = self.write();
+        let mut keys : Vec<u32> = constructors.iter().map(|(key, _)| *key).collect();
+        keys.sort();
+        for (idx, key) in keys.iter().enumerate() {
+            if constructor_map.contains_key(key) || keys.get(idx + 1) == Some(key) {
+                return Err(CuteError::already_exists(format!("protocol \"{}\" already registered", key)));
+            }
+        }
+        constructor_map.extend(constructors);
+        drop(constructor_map);
+        for key in keys.iter() {
+            let _ = self.inner.event_tx.send(ProcEvent::Added(*key));
+        }
+        Ok(keys)
+    }
+
+    fn add(&self, key : u32, constructor : ProcConstructor<C>) -> Result<(), CuteError> {
+        let mut constructor_map = self.write();
+        if constructor_map.contains_key(&key) {
+            return Err(CuteError::already_exists(format!("protocol \"{}\" already registered", key)));
         }
+        constructor_map.insert(key, constructor);
+        drop(constructor_map);
+        let _ = self.inner.event_tx.send(ProcEvent::Added(key));
+        Ok(())
     }
 
-    pub fn insert(&mut self,key : u32, task_constructor : Box<dyn TaskConstructor<C> + Send + Sync + 'static>) {
-        self.constructor_map.entry(key).or_insert(task_constructor);
+    fn put(&self, key : u32, constructor : ProcConstructor<C>) {
+        let event = match self.write().insert(key, constructor) {
+            None => ProcEvent::Added(key),
+            Some(_) => ProcEvent::Replaced(key),
+        };
+        let _ = self.inner.event_tx.send(event);
+    }
+
+    /// 생성자는 panic 하지 않으므로 poison 된 경우에도 그대로 사용한다.
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<u32, ProcConstructor<C>>> {
+        self.inner.constructor_map.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<u32, ProcConstructor<C>>> {
+        self.inner.constructor_map.write().unwrap_or_else(|e| e.into_inner())
     }
 }
 
@@ -24,21 +220,53 @@ impl<C> Procedure<C> for ProcManager<C>
 where C : Send + Sync + 'static
 {
     async fn get_service_protocols(&self) -> Result<Vec<u32>, CuteError> {
-        let mut results = Vec::new();
-        for (key,_) in self.constructor_map.iter() {
-            results.push(*key);
-        }
+        let mut results : Vec<u32> = self.read().keys().copied().collect();
+        results.sort();
+        Ok(results)
+    }
+
+    async fn get_service_infos(&self) -> Result<Vec<ProtocolInfo>, CuteError> {
+        let mut results : Vec<ProtocolInfo> = self.read().iter()
+            .map(|(key, constructor)| ProtocolInfo::new(*key, constructor.kind(), constructor.metadata()))
+            .collect();
+        results.sort_by_key(|info| info.protocol);
         Ok(results)
     }
 
     async fn get_task(&self, key: u32, input: Option<Box<[u8]>>) -> Result<Box<dyn Task<C> + Send>, CuteError> {
-        match self.constructor_map.get(&key) {
-            None => {
+        match self.read().get(&key) {
+            Some(ProcConstructor::Task(constructor)) => {
+                constructor.create(input)
+            }
+            _ => {
                 Err(CuteError::not_found(format!("Task \"{}\" not found", key)))
             }
-            Some(output) => {
-                output.create(input)
+        }
+    }
+
+    async fn get_stream_task(&self, key: u32, input: Option<Box<[u8]>>) -> Result<Box<dyn StreamTask<C> + Send>, CuteError> {
+        match self.read().get(&key) {
+            Some(ProcConstructor::Stream(constructor)) => {
+                constructor.create(input)
+            }
+            _ => {
+                Err(CuteError::not_found(format!("StreamTask \"{}\" not found", key)))
             }
         }
     }
-}
\ No newline at end of file
+
+    async fn get_bidi_task(&self, key: u32, input: Option<Box<[u8]>>) -> Result<Box<dyn BidiTask<C> + Send>, CuteError> {
+        match self.read().get(&key) {
+            Some(ProcConstructor::Bidi(constructor)) => {
+                constructor.create(input)
+            }
+            _ => {
+                Err(CuteError::not_found(format!("BidiTask \"{}\" not found", key)))
+            }
+        }
+    }
+
+    fn subscribe_changes(&self) -> Option<broadcast::Receiver<ProcEvent>> {
+        Some(self.subscribe())
+    }
+}
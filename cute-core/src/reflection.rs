@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use crate::CuteError;
+
+/// # Comment
+/// 등록된 작업의 종류.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TaskKind {
+    /// `Task`. unary 및 `StreamSchedule` 에 따른 stream 요청에 사용.
+    #[default]
+    Task,
+    /// `StreamTask`. stream 요청에만 사용.
+    Stream,
+    /// `BidiTask`. client streaming 및 bidi streaming 요청에만 사용.
+    Bidi,
+}
+
+impl TaskKind {
+    /// 전송시 사용하는 값.
+    pub fn value(&self) -> u32 {
+        match self {
+            TaskKind::Task => 0,
+            TaskKind::Stream => 1,
+            TaskKind::Bidi => 2,
+        }
+    }
+
+    pub fn from_value(value : u32) -> Result<Self, CuteError> {
+        match value {
+            0 => Ok(TaskKind::Task),
+            1 => Ok(TaskKind::Stream),
+            2 => Ok(TaskKind::Bidi),
+            _ => Err(CuteError::deserialize_invalid(format!("unknown task kind : {}", value))),
+        }
+    }
+}
+
+/// # Comment
+/// 생성자가 제공하는 protocol 설명.
+///
+/// schema 는 입력, 출력의 type 이름이며 byte 를 직접 다루는 경우 비어있다.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolMeta {
+    pub name : String,
+    pub description : String,
+    pub input_schema : String,
+    pub output_schema : String,
+    /// 같은 입력으로 여러번 실행해도 결과가 같은 경우. client 는 실패한 unary 요청을 다시 보낼 수 있다.
+    pub idempotent : bool,
+}
+
+impl ProtocolMeta {
+    pub fn new(name : impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_description(mut self, description : impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_schema(mut self, input_schema : impl Into<String>, output_schema : impl Into<String>) -> Self {
+        self.input_schema = input_schema.into();
+        self.output_schema = output_schema.into();
+        self
+    }
+
+    pub fn with_idempotent(mut self, idempotent : bool) -> Self {
+        self.idempotent = idempotent;
+        self
+    }
+}
+
+/// # Comment
+/// `Procedure::get_service_infos` 의 결과. client 는 `describe_services` 로 받는다.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolInfo {
+    pub protocol : u32,
+    pub kind : TaskKind,
+    pub name : String,
+    pub description : String,
+    pub input_schema : String,
+    pub output_schema : String,
+    pub idempotent : bool,
+}
+
+impl ProtocolInfo {
+    pub fn new(protocol : u32, kind : TaskKind, meta : ProtocolMeta) -> Self {
+        Self {
+            protocol,
+            kind,
+            name: meta.name,
+            description: meta.description,
+            input_schema: meta.input_schema,
+            output_schema: meta.output_schema,
+            idempotent: meta.idempotent,
+        }
+    }
+}
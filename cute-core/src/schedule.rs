@@ -0,0 +1,207 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{Interval, MissedTickBehavior};
+use crate::errors::CuteError;
+
+/// # Comment
+/// server stream 의 `Task::execute` 호출 주기.
+///
+/// client 가 stream 요청시 지정하며 server 에서 주기를 맞춰 호출한다.
+///
+/// Task 내부에서 `sleep` 등으로 직접 주기를 맞출 필요가 없다.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamSchedule {
+    /// 쉬지 않고 계속 호출. 기존 동작과 동일.
+    #[default]
+    Continuous,
+    /// 고정된 주기로 호출. 호출이 주기보다 오래 걸린 경우 밀린 호출은 건너뛴다. 최소 주기는 1ms 이다.
+    Interval(Duration),
+    /// 초당 최대 호출 횟수. 호출간의 간격이 `1초 / 횟수` 이상이 되도록 하며 최소 간격은 1ms 이다. 0 은 1 로 본다.
+    MaxRate(u32),
+    /// 한번만 호출 후 stream 을 종료.
+    Once,
+    /// `execute` 가 `None` 을 반환할때까지 호출 후 stream 을 종료.
+    UntilNone,
+    /// 처음 한번 호출 후 `ContextWatch::notify` 가 호출될때마다 호출.
+    OnChange,
+}
+
+impl StreamSchedule {
+    /// 전송시 사용하는 mode 값.
+    pub fn mode(&self) -> u32 {
+        match self {
+            StreamSchedule::Continuous => 0,
+            StreamSchedule::Interval(_) => 1,
+            StreamSchedule::MaxRate(_) => 2,
+            StreamSchedule::Once => 3,
+            StreamSchedule::UntilNone => 4,
+            StreamSchedule::OnChange => 5,
+        }
+    }
+
+    /// 전송시 사용하는 mode 의 값. `Interval` 은 milli second, `MaxRate` 는 초당 횟수.
+    ///
+    /// 1ms 보다 작은 주기는 `from_parts` 에서 거부되지 않도록 ms 단위로 올린다.
+    pub fn value(&self) -> u64 {
+        match self {
+            StreamSchedule::Interval(period) => (period.as_nanos().div_ceil(1_000_000) as u64).max(1),
+            StreamSchedule::MaxRate(rate) => *rate as u64,
+            _ => 0,
+        }
+    }
+
+    /// `mode` 및 `value` 로부터 생성. 주기 또는 횟수가 0 인 경우 실패한다.
+    pub fn from_parts(mode : u32, value : u64) -> Result<Self, CuteError> {
+        match mode {
+            0 => Ok(StreamSchedule::Continuous),
+            1 if value > 0 => Ok(StreamSchedule::Interval(Duration::from_millis(value))),
+            2 if value > 0 && value <= u32::MAX as u64 => Ok(StreamSchedule::MaxRate(value as u32)),
+            3 => Ok(StreamSchedule::Once),
+            4 => Ok(StreamSchedule::UntilNone),
+            5 => Ok(StreamSchedule::OnChange),
+            _ => Err(CuteError::deserialize_invalid(format!("invalid stream schedule mode {} value {}", mode, value))),
+        }
+    }
+}
+
+/// # Comment
+/// Context 변경 알림.
+///
+/// Context 를 변경한 Task 나 외부에서 `notify` 를 호출하면 `StreamSchedule::OnChange` 인 stream 들이 `execute` 를 호출한다.
+#[derive(Debug, Clone)]
+pub struct ContextWatch {
+    sender : Arc<watch::Sender<u64>>,
+}
+
+impl Default for ContextWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContextWatch {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(0);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Context 가 변경되었음을 알림.
+    pub fn notify(&self) {
+        self.sender.send_modify(|version| *version = version.wrapping_add(1));
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.sender.subscribe()
+    }
+}
+
+/// `MaxRate` 의 최소 호출 간격. 1ms 보다 짧은 간격은 1ms 로 호출한다.
+const MIN_RATE_PERIOD : Duration = Duration::from_millis(1);
+
+/// # Comment
+/// `StreamSchedule` 에 맞춰 server stream 의 `execute` 호출 시점을 결정함.
+pub struct StreamTicker {
+    schedule : StreamSchedule,
+    interval : Option<Interval>,
+    changed : watch::Receiver<u64>,
+    is_first : bool,
+}
+
+impl StreamTicker {
+    /// tokio runtime 내에서 생성해야 한다.
+    pub fn new(schedule : StreamSchedule, context_watch : &ContextWatch) -> Self {
+        let interval = match schedule {
+            StreamSchedule::Interval(period) => {
+                let mut interval = tokio::time::interval(period.max(MIN_RATE_PERIOD));
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                Some(interval)
+            }
+            StreamSchedule::MaxRate(rate) => {
+                // client 가 보낸 값이므로 간격이 0 이 되어 interval 이 panic 하지 않도록 함.
+                let mut interval = tokio::time::interval((Duration::from_secs(1) / rate.max(1)).max(MIN_RATE_PERIOD));
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                Some(interval)
+            }
+            _ => None,
+        };
+
+        Self {
+            schedule,
+            interval,
+            changed: context_watch.subscribe(),
+            is_first: true,
+        }
+    }
+
+    /// 다음 `execute` 를 호출할 시점까지 대기함.
+    pub async fn tick(&mut self) {
+        if let Some(interval) = self.interval.as_mut() {
+            interval.tick().await;
+            return;
+        }
+
+        if self.schedule == StreamSchedule::OnChange {
+            if self.is_first {
+                self.is_first = false;
+                self.changed.borrow_and_update();
+            } else if self.changed.changed().await.is_err() {
+                // 알림을 보낼 곳이 없으므로 더 이상 호출하지 않음.
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+
+    /// `execute` 의 결과를 보고 stream 을 계속 진행할지 판단함.
+    pub fn is_continue(&self, output : &Option<Vec<u8>>) -> bool {
+        match self.schedule {
+            StreamSchedule::Once => false,
+            StreamSchedule::UntilNone => output.is_some(),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn max_rate_over_limit_is_clamped() {
+        let schedule = StreamSchedule::from_parts(2, u32::MAX as u64).unwrap();
+        let mut ticker = StreamTicker::new(schedule, &ContextWatch::new());
+        let start = tokio::time::Instant::now();
+        for _ in 0..3 {
+            ticker.tick().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(2));
+    }
+
+    #[test]
+    fn sub_millisecond_interval_round_trips() {
+        for period in [Duration::from_micros(500), Duration::from_nanos(1), Duration::ZERO] {
+            let schedule = StreamSchedule::Interval(period);
+            assert_eq!(StreamSchedule::from_parts(schedule.mode(), schedule.value()).unwrap(), StreamSchedule::Interval(Duration::from_millis(1)));
+        }
+        assert_eq!(StreamSchedule::Interval(Duration::from_micros(1500)).value(), 2);
+        assert_eq!(StreamSchedule::Interval(Duration::from_millis(3)).value(), 3);
+    }
+
+    #[tokio::test]
+    async fn zero_period_and_rate_do_not_panic() {
+        for schedule in [StreamSchedule::MaxRate(0), StreamSchedule::Interval(Duration::ZERO)] {
+            let mut ticker = StreamTicker::new(schedule, &ContextWatch::new());
+            ticker.tick().await;
+        }
+    }
+
+    #[test]
+    fn invalid_parts_are_rejected() {
+        assert!(StreamSchedule::from_parts(2, 0).is_err());
+        assert!(StreamSchedule::from_parts(2, u32::MAX as u64 + 1).is_err());
+        assert!(StreamSchedule::from_parts(1, 0).is_err());
+        assert!(StreamSchedule::from_parts(9, 1).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::{bin_deserialize, bin_serialize, CallInfo, CuteError, ProtocolMeta, Task, TaskConstructor};
+
+/// # Comment
+/// protocol ID 및 입력, 출력 type 을 정의.
+///
+/// server 의 `ProcManager::register` 와 client 의 `get_typed_unary`, `get_typed_stream` 이 같은 정의를 사용하므로
+/// type 이 맞지 않으면 compile 시 오류가 발생한다.
+///
+/// `create_protocol!` macro 로 만들 수 있다.
+pub trait CuteProtocol : Send + Sync + 'static {
+    const ID : u32;
+    /// reflection 에 사용할 이름. 비어있으면 type 이름을 사용한다.
+    const NAME : &'static str = "";
+    const DESCRIPTION : &'static str = "";
+    /// 같은 입력으로 여러번 실행해도 결과가 같은 경우. client 의 `RetryPolicy` 는 idempotent 인 protocol 만 다시 요청한다.
+    const IDEMPOTENT : bool = false;
+    type Input : Serialize + DeserializeOwned + Send + Sync + 'static;
+    type Output : Serialize + DeserializeOwned + Send + 'static;
+}
+
+/// # Comment
+/// `CuteProtocol` 의 type 으로 입력, 출력을 받는 `Task`.
+///
+/// `bin_serialize` / `bin_deserialize` 는 `TypedTaskConstructor` 에서 수행한다.
+#[async_trait::async_trait]
+pub trait TypedTask<C> : Send {
+    type Protocol : CuteProtocol;
+
+    /// 생성자. 입력이 없는 요청은 빈 payload 를 `Input` 으로 deserialize 하므로 `()` 등을 사용한다.
+    fn new(input : <Self::Protocol as CuteProtocol>::Input) -> Result<Self, CuteError>
+    where Self: Sized;
+
+    /// `Task::execute` 와 동일. `None` 은 빈 응답이 된다.
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<C>>, call : &CallInfo) -> Result<Option<<Self::Protocol as CuteProtocol>::Output>, CuteError>;
+
+    /// `Task::destroy` 와 동일.
+    async fn destroy(&mut self);
+}
+
+/// `TypedTask` 를 `Task` 로 사용하기 위한 wrapper.
+struct TypedTaskAdapter<C, T> {
+    task : T,
+    _phantom_c : PhantomData<fn() -> C>,
+}
+
+#[async_trait::async_trait]
+impl<C, T> Task<C> for TypedTaskAdapter<C, T>
+where C : Send + Sync + 'static,
+      T : TypedTask<C> + 'static,
+{
+    fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn Task<C> + Send>, CuteError> {
+        let input = input.unwrap_or_default();
+        let input = bin_deserialize::<<T::Protocol as CuteProtocol>::Input>(&input)?;
+        Ok(Box::new(Self {
+            task: T::new(input)?,
+            _phantom_c: Default::default(),
+        }))
+    }
+
+    async fn execute(&mut self, ctx: Arc<tokio::sync::RwLock<C>>, call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+        self.task.execute(ctx, call).await?
+            .map(bin_serialize)
+            .transpose()
+    }
+
+    async fn destroy(&mut self) {
+        self.task.destroy().await
+    }
+}
+
+/// # Comment
+/// `TypedTask` 생성자. `ProcManager::register` 에서 사용한다.
+pub struct TypedTaskConstructor<C, T> {
+    _phantom : PhantomData<fn() -> (C, T)>,
+}
+
+impl<C, T> TypedTaskConstructor<C, T> {
+    pub fn new() -> Self {
+        Self {
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<C, T> Default for TypedTaskConstructor<C, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, T> TaskConstructor<C> for TypedTaskConstructor<C, T>
+where C : Send + Sync + 'static,
+      T : TypedTask<C> + 'static,
+{
+    fn create(&self, input : Option<Box<[u8]>>) -> Result<Box<dyn Task<C> + Send>, CuteError> {
+        TypedTaskAdapter::<C, T>::new(input)
+    }
+
+    fn metadata(&self) -> ProtocolMeta {
+        let name = match <T::Protocol as CuteProtocol>::NAME {
+            "" => short_type_name::<T::Protocol>(),
+            name => name,
+        };
+        ProtocolMeta::new(name)
+            .with_description(<T::Protocol as CuteProtocol>::DESCRIPTION)
+            .with_idempotent(<T::Protocol as CuteProtocol>::IDEMPOTENT)
+            .with_schema(std::any::type_name::<<T::Protocol as CuteProtocol>::Input>(),
+                         std::any::type_name::<<T::Protocol as CuteProtocol>::Output>())
+    }
+}
+
+/// module 경로를 제외한 type 이름.
+fn short_type_name<T : ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[macro_export]
+macro_rules! create_protocol {
+    ($protocol : ident, $id : expr, $input : ty, $output : ty) => {
+        $crate::create_protocol!($protocol, $id, $input, $output, stringify!($protocol), "");
+    };
+    ($protocol : ident, $id : expr, $input : ty, $output : ty, $name : expr, $description : expr) => {
+        $crate::create_protocol!(@define $protocol, $id, $input, $output, $name, $description, false);
+    };
+    ($protocol : ident, $id : expr, $input : ty, $output : ty, $name : expr, $description : expr, idempotent) => {
+        $crate::create_protocol!(@define $protocol, $id, $input, $output, $name, $description, true);
+    };
+    (@define $protocol : ident, $id : expr, $input : ty, $output : ty, $name : expr, $description : expr, $idempotent : expr) => {
+        #[derive(Debug, Clone, Copy, Default)]
+        pub struct $protocol;
+
+        impl $crate::CuteProtocol for $protocol {
+            const ID : u32 = $id;
+            const NAME : &'static str = $name;
+            const DESCRIPTION : &'static str = $description;
+            const IDEMPOTENT : bool = $idempotent;
+            type Input = $input;
+            type Output = $output;
+        }
+    };
+}
@@ -0,0 +1,104 @@
+//! `tests/plugin_fixture` 를 build 하여 실제 cdylib 를 불러오는지 확인.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+use cute_core::*;
+
+/// fixture 와 같은 context type 이름.
+type Ctx = ();
+
+struct Host;
+
+#[async_trait::async_trait]
+impl Task<Ctx> for Host {
+    fn new(_input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+        Ok(Box::new(Host))
+    }
+
+    async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+        Ok(Some(vec![0]))
+    }
+
+    async fn destroy(&mut self) {}
+}
+create_task_constructor!(Host, HostConstructor, Ctx);
+
+/// fixture 를 한번만 build 하고 cdylib 경로를 반환함.
+///
+/// test 를 실행하는 cargo 가 target directory 를 잠그고 있으므로 다른 directory 에 build 한다.
+fn fixture() -> &'static Path {
+    static PATH : OnceLock<PathBuf> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("plugin_fixture");
+        let output = Command::new(env!("CARGO"))
+            .args(["build", "-p", "cute-plugin-fixture", "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+            .expect("failed to run cargo");
+        assert!(output.status.success(), "failed to build plugin fixture : {}", String::from_utf8_lossy(&output.stderr));
+        target_dir.join("debug").join(format!("{}cute_plugin_fixture{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX))
+    })
+}
+
+async fn run(proc_map : &ProcManager<Ctx>, key : u32, input : &[u8]) -> Option<Vec<u8>> {
+    let mut task = proc_map.get_task(key, Some(input.into())).await.unwrap();
+    let output = task.execute(Arc::new(tokio::sync::RwLock::new(())), &CallInfo::new(key, None, None)).await.unwrap();
+    task.destroy().await;
+    output
+}
+
+#[tokio::test]
+async fn load_plugin_registers_constructors() {
+    let proc_map = ProcManager::<Ctx>::new();
+    let keys = unsafe { proc_map.load_plugin(fixture()) }.unwrap();
+    assert_eq!(keys, vec![10, 11, 12]);
+    assert_eq!(proc_map.get_service_protocols().await.unwrap(), vec![10, 11, 12]);
+
+    // plugin 의 code 로 생성 및 실행됨.
+    assert_eq!(run(&proc_map, 10, b"hello").await, Some(b"hello".to_vec()));
+    let names : Vec<String> = proc_map.get_service_infos().await.unwrap().into_iter().map(|info| info.name).collect();
+    assert_eq!(names, vec!["Echo", "Runtime", "Wait"]);
+}
+
+/// plugin 의 Task 는 host 의 runtime 에서 poll 되며 깨어날 수 있지만 plugin 에 포함된 tokio 는 host 의 runtime 을 찾지 못한다.
+#[tokio::test]
+async fn plugin_task_runs_on_host_runtime() {
+    let proc_map = ProcManager::<Ctx>::new();
+    unsafe { proc_map.load_plugin(fixture()) }.unwrap();
+
+    assert!(tokio::runtime::Handle::try_current().is_ok());
+    assert_eq!(run(&proc_map, 11, &[]).await, Some(vec![0]));
+    let output = tokio::time::timeout(std::time::Duration::from_secs(3), run(&proc_map, 12, &[])).await
+        .expect("plugin task must be woken by the host runtime");
+    assert_eq!(output, Some(vec![12]));
+}
+
+#[tokio::test]
+async fn duplicated_key_registers_nothing() {
+    let proc_map = ProcManager::<Ctx>::new();
+    proc_map.insert(11, Box::new(HostConstructor)).unwrap();
+
+    let err = unsafe { proc_map.load_plugin(fixture()) }.unwrap_err();
+    assert_eq!(err.code, CuteErrorCode::AlreadyExists);
+    assert_eq!(proc_map.get_service_protocols().await.unwrap(), vec![11]);
+    assert_eq!(run(&proc_map, 11, &[]).await, Some(vec![0]));
+}
+
+#[tokio::test]
+async fn load_plugins_skips_invalid_library() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("plugins");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let extension = std::env::consts::DLL_EXTENSION;
+    std::fs::write(dir.join(format!("a_broken.{}", extension)), b"not a library").unwrap();
+    std::fs::copy(fixture(), dir.join(format!("b_fixture.{}", extension))).unwrap();
+    std::fs::write(dir.join("c_readme.txt"), b"ignored").unwrap();
+
+    let proc_map = ProcManager::<Ctx>::new();
+    assert!(unsafe { proc_map.load_plugin(dir.join(format!("a_broken.{}", extension))) }.is_err());
+    let keys = unsafe { proc_map.load_plugins(&dir) }.unwrap();
+    assert_eq!(keys, vec![10, 11, 12]);
+    assert_eq!(run(&proc_map, 10, b"loaded").await, Some(b"loaded".to_vec()));
+}
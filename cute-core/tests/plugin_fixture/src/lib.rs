@@ -0,0 +1,78 @@
+//! `ProcManager::load_plugin` 의 test 에서 불러오는 plugin.
+//!
+//! host 와 context type 이름이 같아야 하므로 context 는 `()` 를 사용한다.
+
+use std::sync::Arc;
+use std::time::Duration;
+use cute_core::*;
+
+type Ctx = ();
+
+/// input 을 그대로 반환. key 10.
+pub struct Echo {
+    input : Vec<u8>,
+}
+
+#[async_trait::async_trait]
+impl Task<Ctx> for Echo {
+    fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+        Ok(Box::new(Echo { input: input.unwrap_or_default().into_vec() }))
+    }
+
+    async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+        Ok(Some(self.input.clone()))
+    }
+
+    async fn destroy(&mut self) {}
+}
+create_task_constructor!(Echo, EchoConstructor, Ctx);
+
+/// plugin 의 tokio 에서 runtime 을 찾을 수 있는지 반환. key 11.
+pub struct Runtime;
+
+#[async_trait::async_trait]
+impl Task<Ctx> for Runtime {
+    fn new(_input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+        Ok(Box::new(Runtime))
+    }
+
+    async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+        Ok(Some(vec![tokio::runtime::Handle::try_current().is_ok() as u8]))
+    }
+
+    async fn destroy(&mut self) {}
+}
+create_task_constructor!(Runtime, RuntimeConstructor, Ctx);
+
+/// 다른 thread 가 보낸 값을 `tokio::sync::oneshot` 으로 기다림. key 12.
+///
+/// host 의 runtime 이 넘긴 waker 로 깨어나는지 확인한다.
+pub struct Wait;
+
+#[async_trait::async_trait]
+impl Task<Ctx> for Wait {
+    fn new(_input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+        Ok(Box::new(Wait))
+    }
+
+    async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let _ = tx.send(vec![12]);
+        });
+        rx.await.map(Some).map_err(|_| CuteError::internal("sender dropped"))
+    }
+
+    async fn destroy(&mut self) {}
+}
+create_task_constructor!(Wait, WaitConstructor, Ctx);
+
+fn register(registrar : &mut PluginRegistrar<Ctx>) -> Result<(), CuteError> {
+    registrar.insert(10, Box::new(EchoConstructor));
+    registrar.insert(11, Box::new(RuntimeConstructor));
+    registrar.insert(12, Box::new(WaitConstructor));
+    Ok(())
+}
+
+create_plugin!(Ctx, register);
@@ -2,7 +2,7 @@ use crate::ffi::generated::cute_driver_generated::*;
 use crate::ffi::input::*;
 use crate::ffi::output::*;
 use crate::ffi::EmbeddedContext;
-use cute_core::{bin_deserialize, bin_serialize, CuteError, Task};
+use cute_core::{bin_deserialize, bin_serialize, CallInfo, CuteError, Task};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -51,6 +51,7 @@ macro_rules! generate_cute_task {
             async fn execute(
                 &mut self,
                 ctx: Arc<tokio::sync::RwLock<$context>>,
+                _call: &CallInfo,
             ) -> Result<Option<Vec<u8>>, CuteError> {
                 unsafe {
                     let mut res = execute_driver_task($id, &mut self.inner);
@@ -123,6 +124,7 @@ impl Task<EmbeddedContext> for EmbeddedEchoTask {
     async fn execute(
         &mut self,
         ctx: Arc<tokio::sync::RwLock<EmbeddedContext>>,
+        _call: &CallInfo,
     ) -> Result<Option<Vec<u8>>, CuteError> {
         unsafe {
             let mut res = execute_driver_task(0, &mut self.inner);
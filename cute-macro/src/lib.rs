@@ -0,0 +1,246 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Expr, ExprLit, FnArg, GenericArgument, Ident, Item, ItemFn, ItemStruct, Lit, Meta, PathArguments, ReturnType, Type};
+
+/// `#[cute_task(id = .., context = ..)]` 의 인자.
+struct TaskArgs {
+    id : Expr,
+    context : Type,
+    idempotent : bool,
+}
+
+/// # Comment
+/// `Task` 및 `TaskConstructor` 를 생성하는 attribute.
+///
+/// async fn 에 사용하면 `TypedTask` 및 `CuteProtocol` 을 생성한다.
+/// ```ignore
+/// #[cute_task(id = 3, context = TestContext)]
+/// async fn echo(ctx : Arc<RwLock<TestContext>>, call : &CallInfo, input : &EchoInput) -> Result<EchoData, CuteError> {
+///     Ok(EchoData { data : input.data })
+/// }
+///
+/// // EchoTask, EchoProtocol 이 생성된다.
+/// EchoTask::register(&proc_map)?;
+/// ```
+///
+/// struct 에 사용하면 `create_task_constructor!` 와 같이 `TaskConstructor` 를 생성하며 `Task` 는 직접 구현한다.
+///
+/// reflection 의 이름은 fn 또는 struct 이름이며 설명은 doc comment 를 사용한다.
+/// `idempotent` 를 지정하면 client 가 실패한 요청을 다시 보낼 수 있는 protocol 로 알린다.
+/// ```ignore
+/// #[cute_task(id = 4, context = TestContext, idempotent)]
+/// ```
+#[proc_macro_attribute]
+pub fn cute_task(attr : TokenStream, item : TokenStream) -> TokenStream {
+    let mut id = None;
+    let mut context = None;
+    let mut idempotent = false;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("id") {
+            id = Some(meta.value()?.parse::<Expr>()?);
+            Ok(())
+        } else if meta.path.is_ident("context") {
+            context = Some(meta.value()?.parse::<Type>()?);
+            Ok(())
+        } else if meta.path.is_ident("idempotent") {
+            idempotent = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported cute_task argument. expected `id`, `context` or `idempotent`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+
+    let args = match (id, context) {
+        (Some(id), Some(context)) => TaskArgs { id, context, idempotent },
+        (None, _) => return missing_arg("id"),
+        (_, None) => return missing_arg("context"),
+    };
+
+    let result = match parse_macro_input!(item as Item) {
+        Item::Fn(item_fn) => expand_fn(args, item_fn),
+        Item::Struct(item_struct) => expand_struct(args, item_struct),
+        other => Err(syn::Error::new_spanned(other, "cute_task can only be used on an async fn or a struct")),
+    };
+
+    result.unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn missing_arg(name : &str) -> TokenStream {
+    syn::Error::new(Span::call_site(), format!("cute_task requires `{}`", name))
+        .into_compile_error()
+        .into()
+}
+
+/// async fn 을 실행하는 `TypedTask` 를 생성.
+///
+/// 인자는 `(ctx, call)` 또는 `(ctx, call, input)` 이며 input 이 참조가 아닌 경우 실행마다 clone 한다.
+fn expand_fn(args : TaskArgs, item_fn : ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &item_fn.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(sig.fn_token, "cute_task fn must be async"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&sig.generics, "cute_task fn can not be generic"));
+    }
+
+    let (input_ty, input_arg) = match sig.inputs.len() {
+        2 => (quote! { () }, quote! {}),
+        3 => {
+            let ty = match &sig.inputs[2] {
+                FnArg::Typed(pat_type) => pat_type.ty.as_ref(),
+                FnArg::Receiver(receiver) => return Err(syn::Error::new_spanned(receiver, "cute_task fn can not take self")),
+            };
+            match ty {
+                Type::Reference(reference) => {
+                    let elem = &reference.elem;
+                    (quote! { #elem }, quote! { , &self.input })
+                }
+                _ => (quote! { #ty }, quote! { , ::core::clone::Clone::clone(&self.input) }),
+            }
+        }
+        _ => return Err(syn::Error::new_spanned(&sig.inputs, "cute_task fn takes (ctx, call) or (ctx, call, input)")),
+    };
+    let output_ty = result_ok_type(&sig.output)?;
+
+    let vis = &item_fn.vis;
+    let fn_ident = &sig.ident;
+    let name = to_pascal_case(&fn_ident.to_string());
+    let task_ident = format_ident!("{}Task", name);
+    let protocol_ident = format_ident!("{}Protocol", name);
+    let fn_name = fn_ident.to_string();
+    let description = doc_string(&item_fn.attrs);
+    let TaskArgs { id, context, idempotent } = args;
+
+    Ok(quote! {
+        #item_fn
+
+        #vis struct #task_ident {
+            input : #input_ty,
+        }
+
+        #[derive(Debug, Clone, Copy, Default)]
+        #vis struct #protocol_ident;
+
+        impl ::cute_core::CuteProtocol for #protocol_ident {
+            const ID : u32 = #id;
+            const NAME : &'static str = #fn_name;
+            const DESCRIPTION : &'static str = #description;
+            const IDEMPOTENT : bool = #idempotent;
+            type Input = #input_ty;
+            type Output = #output_ty;
+        }
+
+        #[::cute_core::__private::async_trait]
+        impl ::cute_core::TypedTask<#context> for #task_ident {
+            type Protocol = #protocol_ident;
+
+            fn new(input : #input_ty) -> ::core::result::Result<Self, ::cute_core::CuteError> {
+                ::core::result::Result::Ok(Self { input })
+            }
+
+            async fn execute(&mut self,
+                             ctx : ::std::sync::Arc<::cute_core::__private::RwLock<#context>>,
+                             call : &::cute_core::CallInfo) -> ::core::result::Result<::core::option::Option<#output_ty>, ::cute_core::CuteError> {
+                #fn_ident(ctx, call #input_arg).await.map(::core::option::Option::Some)
+            }
+
+            async fn destroy(&mut self) {
+            }
+        }
+
+        impl #task_ident {
+            /// `ProcManager` 에 등록.
+            #vis fn register(proc_map : &::cute_core::ProcManager<#context>) -> ::core::result::Result<(), ::cute_core::CuteError> {
+                proc_map.register::<Self>()
+            }
+        }
+    })
+}
+
+/// `Task` 를 직접 구현한 struct 의 `TaskConstructor` 를 생성.
+fn expand_struct(args : TaskArgs, item_struct : ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    if !item_struct.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&item_struct.generics, "cute_task struct can not be generic"));
+    }
+
+    let vis = &item_struct.vis;
+    let ident = &item_struct.ident;
+    let constructor_ident = format_ident!("{}Constructor", ident);
+    let name = ident.to_string();
+    let description = doc_string(&item_struct.attrs);
+    let TaskArgs { id, context, idempotent } = args;
+
+    Ok(quote! {
+        #item_struct
+
+        #[derive(Debug, Clone, Default)]
+        #vis struct #constructor_ident;
+
+        impl ::cute_core::TaskConstructor<#context> for #constructor_ident {
+            fn create(&self, input : ::core::option::Option<::std::boxed::Box<[u8]>>) -> ::core::result::Result<::std::boxed::Box<dyn ::cute_core::Task<#context> + Send>, ::cute_core::CuteError> {
+                <#ident as ::cute_core::Task<#context>>::new(input)
+            }
+
+            fn metadata(&self) -> ::cute_core::ProtocolMeta {
+                ::cute_core::ProtocolMeta::new(#name).with_description(#description).with_idempotent(#idempotent)
+            }
+        }
+
+        impl #ident {
+            pub const ID : u32 = #id;
+
+            /// `ProcManager` 에 등록.
+            #vis fn register(proc_map : &::cute_core::ProcManager<#context>) -> ::core::result::Result<(), ::cute_core::CuteError> {
+                proc_map.insert(Self::ID, ::std::boxed::Box::new(#constructor_ident))
+            }
+        }
+    })
+}
+
+/// doc comment 를 줄바꿈으로 이어 붙임.
+fn doc_string(attrs : &[Attribute]) -> String {
+    attrs.iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(name_value) if name_value.path.is_ident("doc") => match &name_value.value {
+                Expr::Lit(ExprLit { lit : Lit::Str(doc), .. }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `Result<T, E>` 에서 `T` 를 추출.
+fn result_ok_type(output : &ReturnType) -> syn::Result<&Type> {
+    if let ReturnType::Type(_, ty) = output {
+        if let Type::Path(type_path) = ty.as_ref() {
+            if let Some(segment) = type_path.path.segments.last() {
+                if segment.ident == "Result" {
+                    if let PathArguments::AngleBracketed(generic) = &segment.arguments {
+                        if let Some(GenericArgument::Type(ok_ty)) = generic.args.first() {
+                            return Ok(ok_ty);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Err(syn::Error::new_spanned(output, "cute_task fn must return Result<Output, CuteError>"))
+}
+
+fn to_pascal_case(name : &str) -> Ident {
+    let pascal : String = name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    Ident::new(&pascal, Span::call_site())
+}
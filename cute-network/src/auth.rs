@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use cute_core::{CallInfo, CuteError, Identity};
+
+/// 연결한 peer 가 제시한 인증 정보.
+///
+/// + Raw : 연결 후 처음 받은 packet 에서 생성. `CutePacketType::Auth` 인 경우 payload 가 token 이다.
+/// + gRPC : 요청마다 생성. `authorization` metadata 가 token 이다. (`Bearer ` 는 제거됨)
+#[derive(Debug, Clone, Default)]
+pub struct Credential {
+    pub peer_addr : Option<SocketAddr>,
+    pub token : Option<String>,
+    /// mutual TLS 를 사용하는 경우 client 인증서 목록. (DER)
+    pub peer_certificates : Vec<Vec<u8>>,
+}
+
+/// # Comment
+/// 연결한 peer 를 인증함.
+///
+/// 실패한 경우 `CuteError::unauthenticated` 를 반환해야 하며 Raw 는 해당 연결을 종료한다.
+#[async_trait::async_trait]
+pub trait Authenticator : Send + Sync {
+    async fn authenticate(&self, credential : &Credential) -> Result<Identity, CuteError>;
+}
+
+/// # Comment
+/// `Procedure::get_task` 전에 protocol 실행 가능 여부를 확인함.
+///
+/// 거부하는 경우 `CuteError::permission_denied` 를 반환한다.
+#[async_trait::async_trait]
+pub trait AuthorizationPolicy : Send + Sync {
+    async fn authorize(&self, call : &CallInfo) -> Result<(), CuteError>;
+}
+
+/// 등록된 token 과 일치하는 경우 해당 `Identity` 로 인증함.
+#[derive(Debug, Clone, Default)]
+pub struct TokenAuthenticator {
+    tokens : HashMap<String, Identity>,
+}
+
+impl TokenAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_token(mut self, token : impl Into<String>, identity : Identity) -> Self {
+        self.tokens.insert(token.into(), identity);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Authenticator for TokenAuthenticator {
+    async fn authenticate(&self, credential : &Credential) -> Result<Identity, CuteError> {
+        credential.token.as_ref()
+            .and_then(|token| self.tokens.get(token))
+            .cloned()
+            .ok_or_else(|| CuteError::unauthenticated("invalid token"))
+    }
+}
+
+/// protocol 마다 허용할 `Identity` (이름 또는 역할) 및 peer ip 를 지정함.
+///
+/// 규칙이 없는 protocol 은 `default_allow` 에 따른다.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolAcl {
+    default_allow : bool,
+    subject_rules : HashMap<u32, HashSet<String>>,
+    peer_rules : HashMap<u32, HashSet<IpAddr>>,
+}
+
+impl ProtocolAcl {
+    pub fn new(default_allow : bool) -> Self {
+        Self {
+            default_allow,
+            ..Default::default()
+        }
+    }
+
+    /// `subject` 는 `Identity` 의 이름 또는 역할.
+    pub fn allow(mut self, protocol : u32, subject : impl Into<String>) -> Self {
+        self.subject_rules.entry(protocol).or_default().insert(subject.into());
+        self
+    }
+
+    pub fn allow_peer(mut self, protocol : u32, peer_ip : IpAddr) -> Self {
+        self.peer_rules.entry(protocol).or_default().insert(peer_ip);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationPolicy for ProtocolAcl {
+    async fn authorize(&self, call : &CallInfo) -> Result<(), CuteError> {
+        let subjects = self.subject_rules.get(&call.protocol);
+        let peers = self.peer_rules.get(&call.protocol);
+        if subjects.is_none() && peers.is_none() {
+            return if self.default_allow {
+                Ok(())
+            } else {
+                Err(CuteError::permission_denied(format!("protocol {} is not allowed", call.protocol)))
+            };
+        }
+
+        let subject_allowed = match (subjects, &call.identity) {
+            (Some(subjects), Some(identity)) => {
+                subjects.contains(&identity.name) || identity.roles.iter().any(|role| subjects.contains(role))
+            }
+            _ => false,
+        };
+        let peer_allowed = match (peers, call.peer_addr) {
+            (Some(peers), Some(peer_addr)) => peers.contains(&peer_addr.ip()),
+            _ => false,
+        };
+
+        if subject_allowed || peer_allowed {
+            Ok(())
+        } else {
+            Err(CuteError::permission_denied(format!("protocol {} is not allowed", call.protocol)))
+        }
+    }
+}
+
+/// 인증 및 권한 설정.
+///
+/// + Server : `authenticator` 가 없으면 인증하지 않으며 `policy` 가 없으면 모든 protocol 을 허용한다.
+/// + Client : `token` 이 있는 경우 연결시 (gRPC 는 요청마다) 전송한다.
+#[derive(Clone, Default)]
+pub struct AuthConfig {
+    pub authenticator : Option<Arc<dyn Authenticator>>,
+    pub policy : Option<Arc<dyn AuthorizationPolicy>>,
+    pub token : Option<String>,
+}
+
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("authenticator", &self.authenticator.is_some())
+            .field("policy", &self.policy.is_some())
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+impl AuthConfig {
+    /// 인증이 설정되지 않은 경우 `None`.
+    pub(crate) async fn authenticate(&self, credential : &Credential) -> Result<Option<Identity>, CuteError> {
+        match &self.authenticator {
+            None => Ok(None),
+            Some(authenticator) => authenticator.authenticate(credential).await.map(Some),
+        }
+    }
+
+    pub(crate) async fn authorize(&self, call : &CallInfo) -> Result<(), CuteError> {
+        match &self.policy {
+            None => Ok(()),
+            Some(policy) => policy.authorize(call).await,
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+use futures_util::stream;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+use cute_core::{CallInfo, CuteError, DataStream};
+
+/// # Comment
+/// stream 의 결과를 받는 쪽이 처리하지 못해 `max_channel_size` 만큼 쌓인 경우의 동작.
+///
+/// 오류는 버리지 않으며 버린 결과는 `FrameCounters` 에 기록된다.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// 받는 쪽이 처리할 때까지 보내는 쪽 (`Task`) 이 대기함.
+    #[default]
+    Block,
+    /// 가장 오래된 결과를 버리고 새 결과를 쌓음.
+    DropOldest,
+    /// 새 결과를 버림.
+    DropNewest,
+    /// 처리하지 못한 결과는 모두 버리고 가장 최근 결과 하나만 유지함.
+    CoalesceLatest,
+}
+
+/// # Comment
+/// stream 의 backpressure 및 server 의 동시 stream 수 제한 설정.
+///
+/// `policy` 는 server 및 client 모두 사용하며 `protocols` 에 지정한 protocol 은 해당 방식을 사용한다.
+/// `max_streams`, `max_streams_per_peer` 는 server 에서만 사용하며 넘는 요청은 `ResourceExhausted` 로 거부한다.
+#[derive(Debug, Clone, Default)]
+pub struct BackpressureConfig {
+    pub policy : BackpressurePolicy,
+    pub protocols : HashMap<u32, BackpressurePolicy>,
+    /// 동시에 실행하는 server streaming, client streaming 및 bidi streaming 의 수. 0 은 제한하지 않음.
+    pub max_streams : usize,
+    /// peer (ip) 마다 동시에 실행하는 stream 의 수. 0 은 제한하지 않음.
+    ///
+    /// 같은 연결 및 protocol 의 server streaming 을 교체하는 요청은 두 제한 모두 확인하지 않는다.
+    pub max_streams_per_peer : usize,
+}
+
+impl BackpressureConfig {
+    pub fn with_policy(mut self, policy : BackpressurePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_protocol(mut self, protocol : u32, policy : BackpressurePolicy) -> Self {
+        self.protocols.insert(protocol, policy);
+        self
+    }
+
+    pub fn with_max_streams(mut self, max_streams : usize, max_streams_per_peer : usize) -> Self {
+        self.max_streams = max_streams;
+        self.max_streams_per_peer = max_streams_per_peer;
+        self
+    }
+
+    pub(crate) fn policy(&self, protocol : u32) -> BackpressurePolicy {
+        self.protocols.get(&protocol).copied().unwrap_or(self.policy)
+    }
+}
+
+/// # Comment
+/// protocol 별 버린 결과의 수. clone 된 counter 는 같은 값을 공유한다.
+///
+/// `ServerHandle::frame_counters` 및 `Client::frame_counters` 로 받는다.
+#[derive(Debug, Clone, Default)]
+pub struct FrameCounters {
+    dropped : Arc<std::sync::Mutex<HashMap<u32, u64>>>,
+}
+
+impl FrameCounters {
+    /// 해당 protocol 에서 버린 결과의 수.
+    pub fn dropped(&self, protocol : u32) -> u64 {
+        self.lock().get(&protocol).copied().unwrap_or_default()
+    }
+
+    /// 모든 protocol 에서 버린 결과의 수.
+    pub fn total_dropped(&self) -> u64 {
+        self.lock().values().sum()
+    }
+
+    fn add(&self, protocol : u32, count : u64) {
+        *self.lock().entry(protocol).or_default() += count;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u32, u64>> {
+        self.dropped.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 보내는 쪽과 받는 쪽이 공유하는 queue.
+struct Buffer<T> {
+    queue : std::sync::Mutex<(VecDeque<Result<T, CuteError>>, bool)>,
+    ready : tokio::sync::Notify,
+}
+
+/// `policy` 에 따라 stream 의 결과를 쌓음.
+///
+/// `Block` 인 경우 그대로 반환하며 그 외에는 task 에서 `stream` 을 계속 읽어 받는 쪽과 관계없이 진행되도록 한다.
+/// 반환된 stream 이 drop 되면 `stream` 도 drop 된다.
+pub(crate) fn apply_backpressure<T>(stream : DataStream<T>,
+                                    policy : BackpressurePolicy,
+                                    capacity : usize,
+                                    counters : FrameCounters,
+                                    protocol : u32) -> DataStream<T>
+where T : Send + 'static
+{
+    if policy == BackpressurePolicy::Block {
+        return stream;
+    }
+
+    let capacity = capacity.max(1);
+    let buffer = Arc::new(Buffer {
+        queue: std::sync::Mutex::new((VecDeque::new(), false)),
+        ready: tokio::sync::Notify::new(),
+    });
+    let closed = CancellationToken::new();
+    {
+        let buffer = buffer.clone();
+        let closed = closed.clone();
+        tokio::spawn(async move {
+            let mut stream = stream;
+            loop {
+                let item = tokio::select! {
+                    _ = closed.cancelled() => break,
+                    item = stream.next() => item,
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                let mut queue = buffer.queue.lock().unwrap_or_else(|e| e.into_inner());
+                let dropped = push(&mut queue.0, item, policy, capacity);
+                drop(queue);
+                if dropped != 0 {
+                    counters.add(protocol, dropped);
+                }
+                buffer.ready.notify_one();
+            }
+            buffer.queue.lock().unwrap_or_else(|e| e.into_inner()).1 = true;
+            buffer.ready.notify_one();
+        });
+    }
+
+    // 받는 쪽이 drop 되면 guard 에서 cancel 하여 task 를 종료함.
+    Box::pin(stream::unfold((buffer, closed.drop_guard()), |(buffer, guard)| async move {
+        loop {
+            {
+                let mut queue = buffer.queue.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(item) = queue.0.pop_front() {
+                    drop(queue);
+                    return Some((item, (buffer, guard)));
+                }
+                if queue.1 {
+                    return None;
+                }
+            }
+            buffer.ready.notified().await;
+        }
+    }))
+}
+
+/// queue 에 결과를 추가하고 버린 결과의 수를 반환함.
+fn push<T>(queue : &mut VecDeque<Result<T, CuteError>>, item : Result<T, CuteError>, policy : BackpressurePolicy, capacity : usize) -> u64 {
+    if item.is_err() {
+        queue.push_back(item);
+        return 0;
+    }
+    let mut dropped = 0;
+    match policy {
+        BackpressurePolicy::Block => {}
+        BackpressurePolicy::DropOldest => {
+            // 오류만 남은 경우 버리지 않고 추가함.
+            while queue.len() >= capacity {
+                let Some(oldest) = queue.iter().position(|item| item.is_ok()) else {
+                    break;
+                };
+                queue.remove(oldest);
+                dropped += 1;
+            }
+        }
+        BackpressurePolicy::DropNewest => {
+            if queue.len() >= capacity {
+                return 1;
+            }
+        }
+        BackpressurePolicy::CoalesceLatest => {
+            let len = queue.len();
+            queue.retain(|item| item.is_err());
+            dropped = (len - queue.len()) as u64;
+        }
+    }
+    queue.push_back(item);
+    dropped
+}
+
+/// server 에서 실행중인 stream 의 수.
+#[derive(Debug, Default)]
+struct StreamCounts {
+    total : usize,
+    peers : HashMap<Option<IpAddr>, usize>,
+    /// 교체 가능한 stream 의 key 별 수.
+    replaceable : HashMap<String, usize>,
+}
+
+/// server 의 전체 및 peer 별 동시 stream 수 제한.
+#[derive(Debug)]
+pub(crate) struct StreamLimiter {
+    max_streams : usize,
+    max_streams_per_peer : usize,
+    counts : Arc<std::sync::Mutex<StreamCounts>>,
+}
+
+impl StreamLimiter {
+    pub(crate) fn new(config : &BackpressureConfig) -> Self {
+        Self {
+            max_streams: config.max_streams,
+            max_streams_per_peer: config.max_streams_per_peer,
+            counts: Arc::new(std::sync::Mutex::new(StreamCounts::default())),
+        }
+    }
+
+    /// stream 하나를 시작함. 제한을 넘는 경우 `ResourceExhausted` 를 반환한다.
+    ///
+    /// 반환된 permit 이 drop 되면 stream 이 끝난 것으로 본다.
+    pub(crate) fn acquire(&self, call : &CallInfo) -> Result<StreamPermit, CuteError> {
+        self.acquire_inner(call, None)
+    }
+
+    /// 같은 `key` 의 실행중인 stream 을 교체하는 stream 을 시작함.
+    ///
+    /// `key` 의 stream 이 실행중이면 곧 종료되므로 제한을 확인하지 않는다.
+    pub(crate) fn acquire_replacing(&self, call : &CallInfo, key : String) -> Result<StreamPermit, CuteError> {
+        self.acquire_inner(call, Some(key))
+    }
+
+    fn acquire_inner(&self, call : &CallInfo, key : Option<String>) -> Result<StreamPermit, CuteError> {
+        let peer = call.peer_addr.map(|addr| addr.ip());
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        if !key.as_ref().is_some_and(|key| counts.replaceable.contains_key(key)) {
+            self.check(call, &counts, peer)?;
+        }
+        if let Some(key) = &key {
+            *counts.replaceable.entry(key.clone()).or_default() += 1;
+        }
+        counts.total += 1;
+        *counts.peers.entry(peer).or_default() += 1;
+        Ok(StreamPermit {
+            peer,
+            key,
+            counts: self.counts.clone(),
+        })
+    }
+
+    fn check(&self, call : &CallInfo, counts : &StreamCounts, peer : Option<IpAddr>) -> Result<(), CuteError> {
+        if self.max_streams != 0 && counts.total >= self.max_streams {
+            return Err(CuteError::resource_exhausted(format!("protocol {} stream limit exceeded : {} streams running", call.protocol, counts.total)));
+        }
+        let peer_count = counts.peers.get(&peer).copied().unwrap_or_default();
+        if self.max_streams_per_peer != 0 && peer_count >= self.max_streams_per_peer {
+            return Err(CuteError::resource_exhausted(format!("protocol {} stream limit exceeded : {} streams running on peer", call.protocol, peer_count)));
+        }
+        Ok(())
+    }
+}
+
+/// 실행중인 stream 하나. drop 시 수를 줄임.
+#[derive(Debug)]
+pub(crate) struct StreamPermit {
+    peer : Option<IpAddr>,
+    key : Option<String>,
+    counts : Arc<std::sync::Mutex<StreamCounts>>,
+}
+
+impl StreamPermit {
+    /// `stream` 이 끝나거나 drop 될 때까지 유지함.
+    pub(crate) fn attach<T>(self, mut stream : DataStream<T>) -> DataStream<T>
+    where T : Send + 'static
+    {
+        Box::pin(async_stream::stream! {
+            let _permit = self;
+            while let Some(item) = stream.next().await {
+                yield item;
+            }
+        })
+    }
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.total = counts.total.saturating_sub(1);
+        if let Some(count) = counts.peers.get_mut(&self.peer) {
+            *count -= 1;
+            if *count == 0 {
+                counts.peers.remove(&self.peer);
+            }
+        }
+        if let Some(key) = &self.key {
+            if let Some(count) = counts.replaceable.get_mut(key) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.replaceable.remove(key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(queue : &VecDeque<Result<u32, CuteError>>) -> Vec<Option<u32>> {
+        queue.iter().map(|item| item.as_ref().ok().copied()).collect()
+    }
+
+    #[test]
+    fn replacing_stream_skips_limit() {
+        let limiter = StreamLimiter::new(&BackpressureConfig::default().with_max_streams(0, 1));
+        let call = CallInfo::new(1, Some(std::net::SocketAddr::from(([127, 0, 0, 1], 10000))), None);
+        let running = limiter.acquire_replacing(&call, "a".to_string()).unwrap();
+        assert!(limiter.acquire(&call).is_err());
+        assert!(limiter.acquire_replacing(&call, "b".to_string()).is_err());
+
+        let replaced = limiter.acquire_replacing(&call, "a".to_string()).unwrap();
+        drop(running);
+        assert!(limiter.acquire(&call).is_err());
+        drop(replaced);
+        // 교체된 stream 이 모두 끝나면 다시 제한함.
+        let running = limiter.acquire(&call).unwrap();
+        assert!(limiter.acquire_replacing(&call, "a".to_string()).is_err());
+        drop(running);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_errors() {
+        let mut queue = VecDeque::new();
+        assert_eq!(push(&mut queue, Err(CuteError::internal("first")), BackpressurePolicy::DropOldest, 2), 0);
+        assert_eq!(push(&mut queue, Ok(1), BackpressurePolicy::DropOldest, 2), 0);
+        assert_eq!(push(&mut queue, Ok(2), BackpressurePolicy::DropOldest, 2), 1);
+        assert_eq!(values(&queue), vec![None, Some(2)]);
+
+        let mut queue = VecDeque::new();
+        push(&mut queue, Err(CuteError::internal("only")), BackpressurePolicy::DropOldest, 1);
+        assert_eq!(push(&mut queue, Ok(1), BackpressurePolicy::DropOldest, 1), 0);
+        assert_eq!(values(&queue), vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn drop_newest_and_coalesce() {
+        let mut queue = VecDeque::new();
+        push(&mut queue, Ok(1), BackpressurePolicy::DropNewest, 1);
+        assert_eq!(push(&mut queue, Ok(2), BackpressurePolicy::DropNewest, 1), 1);
+        assert_eq!(values(&queue), vec![Some(1)]);
+
+        let mut queue = VecDeque::new();
+        push(&mut queue, Ok(1), BackpressurePolicy::CoalesceLatest, 8);
+        push(&mut queue, Err(CuteError::internal("kept")), BackpressurePolicy::CoalesceLatest, 8);
+        assert_eq!(push(&mut queue, Ok(2), BackpressurePolicy::CoalesceLatest, 8), 1);
+        assert_eq!(values(&queue), vec![None, Some(2)]);
+    }
+}
@@ -1,50 +1,214 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use async_stream::stream;
+use log::warn;
 use tokio_stream::StreamExt;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::Status;
 use tonic::transport::Endpoint;
-use cute_core::{CuteError, DataStream};
-use crate::grpc::convert_status_to_cute_error;
+use cute_core::{CuteError, DataStream, ProcEvent, ProtocolInfo, StreamSchedule, TaskKind};
+use crate::grpc::{convert_status_to_cute_error, is_connection_lost};
 use crate::grpc::proto::cute::cute_service_client::CuteServiceClient;
-use crate::grpc::proto::cute::{Empty, Input};
-use crate::NetworkConfig;
+use crate::grpc::proto::cute::{Empty, Input, Output, Schedule};
+use crate::{BidiSender, CompressType, ConnectionEvent, FrameCounters, NetworkConfig};
+use crate::backpressure::apply_backpressure;
+use crate::reconnect::{resubscribe, Reconnector};
+use crate::retry::{Retrier, RetryPolicy};
+use crate::tls::create_grpc_client_tls;
+
+type ServiceClient = CuteServiceClient<InterceptedService<tonic::transport::Channel, AuthInterceptor>>;
 
 #[derive(Debug)]
 pub struct GRPCClient<C>
 where C : Send + Sync + 'static,
 {
     config : NetworkConfig,
-    client : CuteServiceClient<tonic::transport::Channel>,
+    connection : Arc<Reconnector<ServiceClient>>,
     context : Arc<tokio::sync::RwLock<C>>,
+    protocol_name_map : std::collections::HashMap<Box<str>, u32>,
+    retry : Retrier,
+    frame_counters : FrameCounters,
+}
+
+/// 인증 token 이 있는 경우 모든 요청의 `authorization` metadata 에 기록함.
+#[derive(Debug, Clone)]
+pub struct AuthInterceptor {
+    token : Option<MetadataValue<Ascii>>,
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        if let Some(token) = &self.token {
+            request.metadata_mut().insert("authorization", token.clone());
+        }
+        Ok(request)
+    }
 }
 
 impl<C> GRPCClient<C>
 where C : Clone + Send + Sync + 'static
 {
     pub async fn new(config: NetworkConfig, ctx : Arc<tokio::sync::RwLock<C>>) -> Result<Self, CuteError> {
-        let url = format!("http://{}", config.host_address);
-        let endpoint = Endpoint::from_shared(url)
-            .map_err(|e| CuteError::internal(e.to_string()))?
-            .connect_timeout(tokio::time::Duration::from_secs(config.keep_alive_time_out))
-            .timeout(tokio::time::Duration::from_secs(config.time_out));
+        let client = Self::connect(&config).await?;
+        let connection = Arc::new(Reconnector::new(config.reconnect.clone(), client));
+        let retry = Retrier::new(config.retry.clone());
 
         Ok(Self {
             config,
-            client: CuteServiceClient::connect(endpoint).await.map_err(|e| CuteError::internal(e.to_string()))?,
+            connection,
             context: ctx,
+            protocol_name_map: std::collections::HashMap::new(),
+            retry,
+            frame_counters: FrameCounters::default(),
         })
     }
 
-    pub async fn get_service_names(&mut self) {
-        match self.client.get_services_name(Empty {}).await {
-            Ok(_) => {}
-            Err(_) => {}
+    /// 새 channel 로 연결함. 끊긴 channel 은 요청시 tonic 이 다시 연결하지만 stream 은 다시 요청해야 한다.
+    async fn connect(config : &NetworkConfig) -> Result<ServiceClient, CuteError> {
+        let scheme = if config.tls.is_some() { "https" } else { "http" };
+        let url = format!("{}://{}", scheme, config.host_address);
+        let mut endpoint = Endpoint::from_shared(url)
+            .map_err(|e| CuteError::internal(e.to_string()))?
+            .connect_timeout(tokio::time::Duration::from_secs(config.keep_alive_time_out));
+        if let Some(tls) = &config.tls {
+            endpoint = endpoint.tls_config(create_grpc_client_tls(tls)?)
+                .map_err(|e| CuteError::internal(e.to_string()))?;
         }
+
+        let token = config.auth.token.as_ref()
+            .map(|token| MetadataValue::try_from(format!("Bearer {}", token)))
+            .transpose()
+            .map_err(|e| CuteError::internal(format!("invalid auth token : {}", e)))?;
+        let channel = endpoint.connect().await.map_err(|e| CuteError::unavailable(e.to_string()))?;
+        let mut client = CuteServiceClient::with_interceptor(channel, AuthInterceptor { token })
+            .accept_compressed(CompressionEncoding::Gzip);
+        if config.compress_type != CompressType::None {
+            client = client.send_compressed(CompressionEncoding::Gzip);
+        }
+        Ok(client)
+    }
+
+    fn client(&self) -> ServiceClient {
+        self.connection.current().1.as_ref().clone()
+    }
+
+    pub fn watch_connection(&self) -> tokio::sync::broadcast::Receiver<ConnectionEvent> {
+        self.connection.subscribe()
+    }
+
+    pub fn frame_counters(&self) -> FrameCounters {
+        self.frame_counters.clone()
+    }
+
+    /// 처리하지 못한 결과는 protocol 의 backpressure 에 따라 대기하거나 버림.
+    fn apply_backpressure(&self, key : u32, stream : DataStream<Vec<u8>>) -> DataStream<Vec<u8>> {
+        apply_backpressure(stream,
+                           self.config.backpressure.policy(key),
+                           self.config.max_channel_size,
+                           self.frame_counters.clone(),
+                           key)
     }
 
-    pub async fn get_unary_data(&mut self, key: u32, parameter: Option<Vec<u8>>) -> Result<Vec<u8>, CuteError>
+    /// channel 은 끊긴 후 요청시 다시 연결하므로 항상 `false`.
+    pub(crate) fn is_closed(&self) -> bool {
+        false
+    }
+
+    pub async fn get_service_names(&mut self) -> Result<Vec<u32>, CuteError> {
+        let protocols = self.client().get_services_name(Empty {}).await
+            .map_err(convert_status_to_cute_error)?;
+        Ok(protocols.into_inner().protocol)
+    }
+
+    /// 등록된 protocol 의 설명을 받고 이름으로 찾을 수 있도록 기록함.
+    pub async fn describe_services(&mut self) -> Result<Vec<ProtocolInfo>, CuteError> {
+        let infos = self.client().get_services_info(Empty {}).await
+            .map_err(convert_status_to_cute_error)?
+            .into_inner().info.into_iter()
+            .map(|info| Ok(ProtocolInfo {
+                protocol: info.protocol,
+                kind: TaskKind::from_value(info.kind)?,
+                name: info.name,
+                description: info.description,
+                input_schema: info.input_schema,
+                output_schema: info.output_schema,
+                idempotent: info.idempotent,
+            }))
+            .collect::<Result<Vec<_>, CuteError>>()?;
+        self.protocol_name_map = infos.iter()
+            .map(|info| (info.name.as_str().into(), info.protocol))
+            .collect();
+        self.retry.set_infos(&infos);
+        Ok(infos)
+    }
+
+    /// server 의 protocol 등록 변경 알림. 요청한 이후의 변경만 전달된다.
+    pub async fn watch_services(&mut self) -> Result<DataStream<ProcEvent>, CuteError> {
+        let events = self.client().watch_services(Empty {}).await
+            .map_err(convert_status_to_cute_error)?
+            .into_inner();
+        Ok(Box::pin(events.map(|event| match event {
+            Ok(event) => ProcEvent::from_parts(event.kind, event.protocol),
+            Err(status) => Err(convert_status_to_cute_error(status)),
+        })))
+    }
+
+    /// 이름에 해당하는 protocol. 기록되지 않은 이름인 경우 server 에 다시 요청한다.
+    pub async fn get_protocol(&mut self, name : &str) -> Result<u32, CuteError> {
+        if let Some(protocol) = self.protocol_name_map.get(name) {
+            return Ok(*protocol);
+        }
+        self.describe_services().await?;
+        self.protocol_name_map.get(name).copied()
+            .ok_or_else(|| CuteError::not_found(format!("protocol \"{}\" not found", name)))
+    }
+
+    pub fn set_retry_policy(&mut self, key : u32, policy : Option<RetryPolicy>) {
+        self.retry.set_policy(key, policy);
+    }
+
+    /// protocol 의 재시도 방식. idempotent 여부를 모르는 경우 `describe_services` 를 요청한다.
+    async fn retry_policy(&mut self, key : u32) -> Option<RetryPolicy> {
+        if self.retry.needs_describe(key) {
+            if let Err(e) = self.describe_services().await {
+                warn!("describe services for retry failed : {}", e);
+            }
+        }
+        self.retry.policy(key)
+    }
+
+    /// `timeout` 은 server 에 전달되며 지나면 `DeadlineExceeded` 를 반환한다. `None` 이면 `NetworkConfig::time_out` 을 사용.
+    ///
+    /// 다시 요청하는 경우 `timeout` 은 모든 시도를 포함하며 각 요청에는 남은 시간을 전달한다.
+    pub async fn get_unary_data(&mut self, key: u32, parameter: Option<Vec<u8>>, timeout: Option<std::time::Duration>) -> Result<Vec<u8>, CuteError>
     {
-        match self.client.server_unary(Input {
+        let timeout = timeout.or_else(|| self.config.unary_timeout());
+        let policy = self.retry_policy(key).await;
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let unary = async {
+            match &policy {
+                Some(policy) => self.retry.run(key, policy, deadline, |timeout| self.unary_data(key, parameter.clone(), timeout)).await,
+                None => self.unary_data(key, parameter.clone(), timeout).await,
+            }
+        };
+        match timeout {
+            None => unary.await,
+            Some(timeout) => tokio::time::timeout(timeout, unary).await
+                .map_err(|_| CuteError::deadline_exceeded(format!("Unary {} deadline exceeded", key)))?,
+        }
+    }
+
+    async fn unary_data(&self, key: u32, parameter: Option<Vec<u8>>, timeout: Option<std::time::Duration>) -> Result<Vec<u8>, CuteError>
+    {
+        match self.client().server_unary(Input {
             protocol: key,
             data: parameter,
+            schedule: None,
+            // 0 은 제한 없음으로 사용하므로 1ms 미만은 1ms 로 보냄.
+            timeout: timeout.map(|timeout| (timeout.as_millis() as u64).max(1)),
         }).await.map_err(|e| convert_status_to_cute_error(e)) {
             Ok(response) => {
                 let mut stream = response.into_inner();
@@ -65,51 +229,122 @@ where C : Clone + Send + Sync + 'static
         }
     }
 
-    pub async fn get_stream_data(&mut self, key: u32, parameter: Option<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError>
+    /// 재연결을 사용하는 경우 연결이 끊기면 다시 연결한 후 같은 요청으로 stream 을 이어간다.
+    pub async fn get_stream_data(&mut self, key: u32, parameter: Option<Vec<u8>>, schedule: StreamSchedule) -> Result<DataStream<Vec<u8>>, CuteError>
+    {
+        let (generation, client) = self.connection.current();
+        let lost = Arc::new(AtomicBool::new(false));
+        let res_stream = Self::server_stream(client.as_ref().clone(), key, parameter.clone(), schedule, lost.clone()).await?;
+        if !self.connection.is_enabled() {
+            return Ok(self.apply_backpressure(key, res_stream));
+        }
+
+        let connection = self.connection.clone();
+        let config = self.config.clone();
+        // channel 은 닫힘 여부를 알 수 없으므로 stream 이 transport 오류로 끝난 경우만 끊긴 것으로 봄.
+        let is_lost = {
+            let lost = lost.clone();
+            move |_| lost.swap(false, Ordering::AcqRel)
+        };
+        let res_stream = resubscribe(generation, res_stream, is_lost, move |generation| {
+            let (connection, config, parameter, lost) = (connection.clone(), config.clone(), parameter.clone(), lost.clone());
+            async move {
+                let (generation, client) = connection.reconnect(generation, || Self::connect(&config)).await?;
+                let res_stream = Self::server_stream(client.as_ref().clone(), key, parameter, schedule, lost).await?;
+                Ok((generation, res_stream))
+            }
+        });
+        Ok(self.apply_backpressure(key, res_stream))
+    }
+
+    async fn server_stream(mut client : ServiceClient, key: u32, parameter: Option<Vec<u8>>, schedule: StreamSchedule, lost : Arc<AtomicBool>) -> Result<DataStream<Vec<u8>>, CuteError>
     {
-        match self.client.server_stream(Input {
+        let response = client.server_stream(Input {
             protocol: key,
             data: parameter,
-        }).await.map_err(|e| convert_status_to_cute_error(e)) {
-            Ok(response) => {
-                let mut stream = response.into_inner();
-                let (tx, rx) = tokio::sync::mpsc::channel(self.config.max_channel_size);
-                tokio::spawn(async move {
-                    let mut flat_vec = Vec::new();
-                    while let Some(output) = stream.next().await {
-                        match output {
-                            Ok(value) => {
-                                if value.page_idx == 0 {
-                                    flat_vec.clear();
-                                }
-                                flat_vec.extend(value.data);
-                                if value.page_idx + 1 == value.page_size {
-                                    match tx.try_send(Ok(flat_vec.clone())) {
-                                        Ok(_) => {}
-                                        Err(_) => {}
-                                    }
-                                    flat_vec.clear();
-                                }
-                            }
-                            Err(_) => {
-                                break;
-                            }
+            schedule: Some(Schedule {
+                mode: schedule.mode(),
+                value: schedule.value(),
+            }),
+            timeout: None,
+        }).await.map_err(convert_status_to_cute_error)?;
+        Ok(Self::merge_pages(response.into_inner(), Some(lost)))
+    }
+
+    pub async fn send_stream_data<S>(&mut self, key: u32, parameter: Option<Vec<u8>>, input: S) -> Result<Vec<u8>, CuteError>
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        let response = self.client().client_stream(Self::input_stream(key, parameter, input))
+            .await.map_err(convert_status_to_cute_error)?;
+        let mut stream = response.into_inner();
+        let mut flat_vec = Vec::new();
+        while let Some(output) = stream.message().await.map_err(convert_status_to_cute_error)? {
+            flat_vec.extend(output.data);
+        }
+        Ok(flat_vec)
+    }
+
+    pub async fn open_bidi(&mut self, key: u32, parameter: Option<Vec<u8>>) -> Result<(BidiSender, DataStream<Vec<u8>>), CuteError> {
+        let (input_tx, input_rx) = tokio::sync::mpsc::channel(self.config.max_channel_size);
+        let input = tokio_stream::wrappers::ReceiverStream::new(input_rx);
+        let response = self.client().bidi_stream(Self::input_stream(key, parameter, input))
+            .await.map_err(convert_status_to_cute_error)?;
+        Ok((BidiSender::new(input_tx), self.apply_backpressure(key, Self::merge_pages(response.into_inner(), None))))
+    }
+
+    /// 처음 Input 에는 `BidiTask` 생성 parameter 를, 이후에는 입력을 기록함.
+    fn input_stream<S>(key: u32, parameter: Option<Vec<u8>>, input: S) -> impl tokio_stream::Stream<Item = Input> + Send + 'static
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        tokio_stream::once(Input {
+            protocol: key,
+            data: parameter,
+            schedule: None,
+            timeout: None,
+        }).chain(input.map(move |item| Input {
+            protocol: key,
+            data: Some(item),
+            schedule: None,
+            timeout: None,
+        }))
+    }
+
+    /// page 로 나뉜 Output 을 합쳐서 하나의 결과로 반환함.
+    ///
+    /// 결과를 읽을 때만 다음 Output 을 받으므로 처리하지 못하면 HTTP/2 flow control 로 server 가 대기한다.
+    /// 연결이 끊겨 끝난 경우 `lost` 를 기록함.
+    fn merge_pages(mut stream: tonic::Streaming<Output>, lost : Option<Arc<AtomicBool>>) -> DataStream<Vec<u8>> {
+        Box::pin(stream! {
+            let mut flat_vec = Vec::new();
+            while let Some(output) = stream.next().await {
+                match output {
+                    Ok(value) => {
+                        if value.page_idx == 0 {
+                            flat_vec.clear();
+                        }
+                        flat_vec.extend(value.data);
+                        if value.page_idx + 1 == value.page_size {
+                            yield Ok(std::mem::take(&mut flat_vec));
                         }
                     }
-                    drop(tx);
-                });
-                Ok(Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx)))
-            }
-            Err(e) => {
-                Err(e)
+                    Err(status) => {
+                        if let Some(lost) = lost.as_ref().filter(|_| is_connection_lost(&status)) {
+                            lost.store(true, Ordering::Release);
+                        }
+                        yield Err(convert_status_to_cute_error(status));
+                        break;
+                    }
+                }
             }
-        }
+        })
     }
 
     pub async fn close_stream(&mut self, key : u32) -> Result<(), CuteError> {
-        match self.client.server_stream_close(Input {
+        match self.client().server_stream_close(Input {
             protocol: key,
             data: None,
+            schedule: None,
+            timeout: None,
         }).await.map_err(|e| convert_status_to_cute_error(e)) {
             Ok(_) => {
                 Ok(())
@@ -121,7 +356,7 @@ where C : Clone + Send + Sync + 'static
     }
 
     pub async fn close_stream_all(&mut self) -> Result<(), CuteError> {
-        match self.client.server_stream_all_close(Empty {}).await.map_err(|e| convert_status_to_cute_error(e)) {
+        match self.client().server_stream_all_close(Empty {}).await.map_err(|e| convert_status_to_cute_error(e)) {
             Ok(_) => {
                 Ok(())
             }
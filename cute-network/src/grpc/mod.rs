@@ -5,9 +5,13 @@ use cute_core::{CuteError, CuteErrorCode};
 pub use self::server::GRPCServer;
 pub use self::client::GRPCClient;
 
+/// 다시 요청하기 전에 기다려야 하는 시간 (milli second). gRPC 의 server pushback 과 같은 metadata 를 사용한다.
+const RETRY_PUSHBACK_KEY : &str = "grpc-retry-pushback-ms";
+
 #[allow(unused)]
 fn convert_cute_error_to_status(e : CuteError) -> Status {
-    match e.code {
+    let retry_after = e.retry_after;
+    let mut status = match e.code {
         CuteErrorCode::SerializeInvalid => {
             Status::invalid_argument(e.message)
         }
@@ -35,11 +39,48 @@ fn convert_cute_error_to_status(e : CuteError) -> Status {
         CuteErrorCode::Ok => {
             Status::ok(e.message)
         }
+        CuteErrorCode::AlreadyExists => {
+            Status::already_exists(e.message)
+        }
+        CuteErrorCode::Unavailable => {
+            Status::unavailable(e.message)
+        }
+        CuteErrorCode::ResourceExhausted => {
+            Status::resource_exhausted(e.message)
+        }
+    };
+    if let Some(retry_after) = retry_after {
+        if let Ok(value) = retry_after.as_millis().to_string().parse() {
+            status.metadata_mut().insert(RETRY_PUSHBACK_KEY, value);
+        }
+    }
+    status
+}
+/// 연결이 끊겨 실패한 경우. tonic 은 h2 의 io 오류를 `Internal` 로 전달하므로 원인을 확인한다.
+fn is_connection_lost(e : &Status) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<h2::Error>() {
+            return error.is_io() || error.is_go_away();
+        }
+        if error.is::<std::io::Error>() {
+            return true;
+        }
+        source = error.source();
     }
+    false
 }
+
 #[allow(unused)]
 fn convert_status_to_cute_error(e : Status) -> CuteError {
-    match e.code() {
+    if is_connection_lost(&e) {
+        return CuteError::unavailable(e.message());
+    }
+    let retry_after = e.metadata().get(RETRY_PUSHBACK_KEY)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(std::time::Duration::from_millis);
+    let error = match e.code() {
         Code::Ok => {
             CuteError::ok(e.message())
         }
@@ -64,7 +105,20 @@ fn convert_status_to_cute_error(e : Status) -> CuteError {
         Code::Unauthenticated => {
             CuteError::unauthenticated(e.message())
         }
+        Code::AlreadyExists => {
+            CuteError::already_exists(e.message())
+        }
+        Code::Unavailable => {
+            CuteError::unavailable(e.message())
+        }
+        Code::ResourceExhausted => {
+            CuteError::resource_exhausted(e.message())
+        }
         _ => CuteError::internal(e.message()),
+    };
+    match retry_after {
+        Some(retry_after) => error.with_retry_after(retry_after),
+        None => error,
     }
 }
 
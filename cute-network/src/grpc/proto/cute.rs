@@ -8,6 +8,50 @@ pub struct Protocols {
     #[prost(uint32, repeated, tag = "1")]
     pub protocol: ::prost::alloc::vec::Vec<u32>,
 }
+/// 등록된 protocol 의 설명. kind 는 TaskKind 참고.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProtocolInfo {
+    #[prost(uint32, tag = "1")]
+    pub protocol: u32,
+    #[prost(uint32, tag = "2")]
+    pub kind: u32,
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub description: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub input_schema: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub output_schema: ::prost::alloc::string::String,
+    /// 여러번 실행해도 결과가 같아 client 가 다시 요청할 수 있는 protocol.
+    #[prost(bool, tag = "7")]
+    pub idempotent: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProtocolInfos {
+    #[prost(message, repeated, tag = "1")]
+    pub info: ::prost::alloc::vec::Vec<ProtocolInfo>,
+}
+/// protocol 등록 변경 알림. kind 는 ProcEvent 참고.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ServiceEvent {
+    #[prost(uint32, tag = "1")]
+    pub kind: u32,
+    #[prost(uint32, tag = "2")]
+    pub protocol: u32,
+}
+/// stream 의 execute 호출 주기. mode 는 StreamSchedule 참고.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Schedule {
+    #[prost(uint32, tag = "1")]
+    pub mode: u32,
+    #[prost(uint64, tag = "2")]
+    pub value: u64,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Input {
@@ -15,6 +59,11 @@ pub struct Input {
     pub protocol: u32,
     #[prost(bytes = "vec", optional, tag = "2")]
     pub data: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(message, optional, tag = "3")]
+    pub schedule: ::core::option::Option<Schedule>,
+    /// unary 요청 제한 시간 (milli second). 0 은 제한하지 않음.
+    #[prost(uint64, optional, tag = "4")]
+    pub timeout: ::core::option::Option<u64>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -135,6 +184,53 @@ pub mod cute_service_client {
                 .insert(GrpcMethod::new("cute.CuteService", "GetServicesName"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn get_services_info(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Empty>,
+        ) -> std::result::Result<tonic::Response<super::ProtocolInfos>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cute.CuteService/GetServicesInfo",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cute.CuteService", "GetServicesInfo"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn watch_services(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Empty>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ServiceEvent>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cute.CuteService/WatchServices",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cute.CuteService", "WatchServices"));
+            self.inner.server_streaming(req, path, codec).await
+        }
         pub async fn server_unary(
             &mut self,
             request: impl tonic::IntoRequest<super::Input>,
@@ -229,6 +325,57 @@ pub mod cute_service_client {
                 .insert(GrpcMethod::new("cute.CuteService", "ServerStreamAllClose"));
             self.inner.unary(req, path, codec).await
         }
+        /// 처음 받은 Input 의 protocol 및 data 로 BidiTask 를 생성하며 이후 Input 의 data 를 입력으로 전달한다.
+        pub async fn client_stream(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::Input>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Output>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cute.CuteService/ClientStream",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cute.CuteService", "ClientStream"));
+            self.inner.streaming(req, path, codec).await
+        }
+        pub async fn bidi_stream(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::Input>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Output>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/cute.CuteService/BidiStream",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("cute.CuteService", "BidiStream"));
+            self.inner.streaming(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -242,6 +389,23 @@ pub mod cute_service_server {
             &self,
             request: tonic::Request<super::Empty>,
         ) -> std::result::Result<tonic::Response<super::Protocols>, tonic::Status>;
+        async fn get_services_info(
+            &self,
+            request: tonic::Request<super::Empty>,
+        ) -> std::result::Result<tonic::Response<super::ProtocolInfos>, tonic::Status>;
+        /// Server streaming response type for the WatchServices method.
+        type WatchServicesStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ServiceEvent, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn watch_services(
+            &self,
+            request: tonic::Request<super::Empty>,
+        ) -> std::result::Result<
+            tonic::Response<Self::WatchServicesStream>,
+            tonic::Status,
+        >;
         /// Server streaming response type for the ServerUnary method.
         type ServerUnaryStream: tonic::codegen::tokio_stream::Stream<
                 Item = std::result::Result<super::Output, tonic::Status>,
@@ -276,6 +440,30 @@ pub mod cute_service_server {
             &self,
             request: tonic::Request<super::Empty>,
         ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
+        /// Server streaming response type for the ClientStream method.
+        type ClientStreamStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Output, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// 처음 받은 Input 의 protocol 및 data 로 BidiTask 를 생성하며 이후 Input 의 data 를 입력으로 전달한다.
+        async fn client_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::Input>>,
+        ) -> std::result::Result<
+            tonic::Response<Self::ClientStreamStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the BidiStream method.
+        type BidiStreamStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Output, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn bidi_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::Input>>,
+        ) -> std::result::Result<tonic::Response<Self::BidiStreamStream>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct CuteServiceServer<T: CuteService> {
@@ -400,6 +588,97 @@ pub mod cute_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/cute.CuteService/GetServicesInfo" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetServicesInfoSvc<T: CuteService>(pub Arc<T>);
+                    impl<T: CuteService> tonic::server::UnaryService<super::Empty>
+                    for GetServicesInfoSvc<T> {
+                        type Response = super::ProtocolInfos;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Empty>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CuteService>::get_services_info(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetServicesInfoSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cute.CuteService/WatchServices" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchServicesSvc<T: CuteService>(pub Arc<T>);
+                    impl<
+                        T: CuteService,
+                    > tonic::server::ServerStreamingService<super::Empty>
+                    for WatchServicesSvc<T> {
+                        type Response = super::ServiceEvent;
+                        type ResponseStream = T::WatchServicesStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Empty>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CuteService>::watch_services(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WatchServicesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/cute.CuteService/ServerUnary" => {
                     #[allow(non_camel_case_types)]
                     struct ServerUnarySvc<T: CuteService>(pub Arc<T>);
@@ -584,6 +863,96 @@ pub mod cute_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/cute.CuteService/ClientStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct ClientStreamSvc<T: CuteService>(pub Arc<T>);
+                    impl<T: CuteService> tonic::server::StreamingService<super::Input>
+                    for ClientStreamSvc<T> {
+                        type Response = super::Output;
+                        type ResponseStream = T::ClientStreamStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::Input>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CuteService>::client_stream(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ClientStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/cute.CuteService/BidiStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct BidiStreamSvc<T: CuteService>(pub Arc<T>);
+                    impl<T: CuteService> tonic::server::StreamingService<super::Input>
+                    for BidiStreamSvc<T> {
+                        type Response = super::Output;
+                        type ResponseStream = T::BidiStreamStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::Input>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as CuteService>::bidi_stream(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = BidiStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
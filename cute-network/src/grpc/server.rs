@@ -2,13 +2,24 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::Arc;
 use async_stream::stream;
-use log::info;
-use tonic::{Request, Response, Status};
-use cute_core::Procedure;
-use crate::grpc::convert_cute_error_to_status;
+use log::{info, warn};
+use tonic::{Request, Response, Status, Streaming};
+use tonic::codec::CompressionEncoding;
+use tonic::transport::server::TcpIncoming;
+use tokio_util::sync::CancellationToken;
+use tokio_stream::StreamExt;
+use cute_core::{CallInfo, ContextWatch, CuteErrorCode, DataStream, ProcEvent, Procedure, StreamSchedule, StreamTicker, TaskGuard};
+use crate::grpc::{convert_cute_error_to_status, convert_status_to_cute_error};
 use crate::grpc::proto::cute::cute_service_server::{CuteService, CuteServiceServer};
-use crate::grpc::proto::cute::{Empty, Input, Output, Protocols};
-use crate::NetworkConfig;
+use crate::grpc::proto::cute::{Empty, Input, Output, ProtocolInfo, ProtocolInfos, Protocols, ServiceEvent};
+use crate::{CompressType, FrameCounters, NetworkConfig};
+use crate::auth::Credential;
+use crate::backpressure::{apply_backpressure, StreamLimiter};
+use crate::rate_limit::RateLimiter;
+use crate::tls::create_grpc_server_tls;
+
+/// `shutdown_time_out` 이 지나 중단한 요청이 끝나기를 기다리는 시간.
+const ABORT_WAIT_TIME : std::time::Duration = std::time::Duration::from_secs(1);
 
 /// Comment
 /// `cute.proto` 를 통해 generate 된 CuteService 특성을 지정받아 제작하기 위한 Server Struct
@@ -22,6 +33,14 @@ where R : AsRef<P>,
     procedure: R,
     context : Arc<tokio::sync::RwLock<C>>,
     peer_map : Arc<tokio::sync::Mutex<std::collections::HashMap<Box<str>, tokio::sync::watch::Sender<bool>>>>,
+    /// 종료 대기 시간이 지난 경우 처리중인 요청 및 stream 을 중단시킴.
+    abort : CancellationToken,
+    /// 종료 요청. bidi streaming 의 입력을 종료시킴.
+    shutdown : CancellationToken,
+    context_watch : ContextWatch,
+    rate_limiter : Arc<RateLimiter>,
+    stream_limiter : Arc<StreamLimiter>,
+    frame_counters : FrameCounters,
     _phantom_p: PhantomData<fn() -> P>,
 }
 
@@ -30,25 +49,259 @@ where R : AsRef<P> + Send + Sync + 'static,
       P : Procedure<C> + Send + Sync + 'static,
       C : Clone + Send + Sync + 'static,
 {
-    pub async fn start(procedure : R, config : NetworkConfig, ctx : Arc<tokio::sync::RwLock<C>>) -> Result<() , std::io::Error> {
+    /// `shutdown` 이 cancel 되면 연결을 더 받지 않고 모든 stream 을 종료한다.
+    ///
+    /// 처리중인 요청은 `shutdown_time_out` 동안 기다린다.
+    pub async fn start(procedure : R,
+                       config : NetworkConfig,
+                       ctx : Arc<tokio::sync::RwLock<C>>,
+                       listener : tokio::net::TcpListener,
+                       shutdown : CancellationToken,
+                       context_watch : ContextWatch,
+                       frame_counters : FrameCounters) -> Result<() , std::io::Error> {
         let server = GRPCServer {
-            config,
+            config : config.clone(),
             procedure,
             context : ctx,
             peer_map : Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+            abort : CancellationToken::new(),
+            shutdown : shutdown.clone(),
+            context_watch,
+            rate_limiter : Arc::new(RateLimiter::new(config.request_limit_milli_second, &config.rate_limit)),
+            stream_limiter : Arc::new(StreamLimiter::new(&config.backpressure)),
+            frame_counters,
             _phantom_p: Default::default(),
         };
-        tonic::transport::Server::builder()
+        let abort = server.abort.clone();
+        let peer_map = server.peer_map.clone();
+        // 등록 해제된 protocol 의 stream 은 종료 signal 을 보내 destroy 후 끝나도록 함.
+        if let Some(mut event_rx) = server.procedure.as_ref().subscribe_changes() {
+            let peer_map = peer_map.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                loop {
+                    let event = tokio::select! {
+                        _ = shutdown.cancelled() => break,
+                        event = event_rx.recv() => event,
+                    };
+                    match event {
+                        Ok(ProcEvent::Removed(protocol)) => {
+                            let suffix = format!("_{}", protocol);
+                            peer_map.lock().await.retain(|key, sender| {
+                                if key.ends_with(suffix.as_str()) {
+                                    let _ = sender.send(true);
+                                    false
+                                } else {
+                                    true
+                                }
+                            });
+                        }
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+        // 압축된 요청은 항상 받으며 응답은 설정된 경우에만 gzip 으로 압축함.
+        let mut service = CuteServiceServer::new(server)
+            .accept_compressed(CompressionEncoding::Gzip);
+        if config.compress_type != CompressType::None {
+            service = service.send_compressed(CompressionEncoding::Gzip);
+        }
+        let mut builder = tonic::transport::Server::builder();
+        if let Some(tls) = &config.tls {
+            builder = builder.tls_config(create_grpc_server_tls(tls)?)
+                .map_err(std::io::Error::other)?;
+        }
+        let incoming = TcpIncoming::from_listener(listener, true, None).map_err(std::io::Error::other)?;
+        let signal = {
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown.cancelled().await;
+                info!("grpc server shutdown requested");
+                for (_, sender) in peer_map.lock().await.drain() {
+                    let _ = sender.send(true);
+                }
+            }
+        };
+        let serve = builder
             .http2_keepalive_timeout(Some(tokio::time::Duration::from_secs(config.keep_alive_time_out)))
-            .timeout(std::time::Duration::from_secs(config.time_out))
-            .add_service(CuteServiceServer::new(server))
-            .serve_with_shutdown(config.host_address, async {
-            tokio::signal::ctrl_c().await.unwrap();
-        }).await.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            .add_service(service)
+            .serve_with_incoming_shutdown(incoming, signal);
+        tokio::pin!(serve);
+
+        tokio::select! {
+            res = &mut serve => {
+                res.map_err(std::io::Error::other)?;
+            }
+            _ = async {
+                shutdown.cancelled().await;
+                tokio::time::sleep(std::time::Duration::from_secs(config.shutdown_time_out)).await;
+            } => {
+                warn!("grpc server shutdown timed out after {} seconds", config.shutdown_time_out);
+                abort.cancel();
+                // 중단된 요청 및 stream 의 destroy 가 끝날 때까지 기다림.
+                match tokio::time::timeout(ABORT_WAIT_TIME, &mut serve).await {
+                    Ok(res) => {
+                        res.map_err(std::io::Error::other)?;
+                    }
+                    Err(_) => {
+                        warn!("grpc server aborted calls did not finish in {:?}", ABORT_WAIT_TIME);
+                    }
+                }
+            }
+        }
         Ok(())
     }
 }
 
+impl<R, P, C> GRPCServer<R, P, C>
+where R : AsRef<P> + Send + Sync + 'static,
+      P : Procedure<C> + Send + Sync + 'static,
+      C : Clone + Send + Sync + 'static,
+{
+    /// client streaming 및 bidi streaming 의 공통 처리.
+    ///
+    /// 처음 받은 Input 으로 `BidiTask` 를 생성하고 이후 Input 의 data 를 입력 stream 으로 전달함.
+    async fn open_bidi(&self, request : Request<Streaming<Input>>) -> Result<(u32, DataStream<Vec<u8>>), Status> {
+        // Streaming 은 Sync 가 아니므로 인증에 필요한 metadata 만 분리함.
+        let (metadata, extensions, mut inbound) = request.into_parts();
+        let first = inbound.message().await?
+            .ok_or_else(|| Status::invalid_argument("bidi stream closed before first input"))?;
+        let protocol = first.protocol;
+        let call = self.authorize(&Request::from_parts(metadata, extensions, ()), protocol).await?;
+        let permit = self.stream_limiter.acquire(&call).map_err(convert_cute_error_to_status)?;
+        let task = self.procedure.as_ref().get_bidi_task(protocol, first.data.map(Vec::into_boxed_slice)).await
+            .map_err(convert_cute_error_to_status)?;
+
+        let shutdown = self.shutdown.clone();
+        // 종료 요청시 입력을 종료하여 task 가 스스로 끝날 수 있도록 함.
+        let input_stream : DataStream<Vec<u8>> = Box::pin(stream! {
+            loop {
+                let message = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    message = inbound.message() => message,
+                };
+                match message {
+                    Ok(Some(input)) => {
+                        yield Ok(input.data.unwrap_or_default());
+                    }
+                    Ok(None) => {
+                        break;
+                    }
+                    Err(status) => {
+                        yield Err(convert_status_to_cute_error(status));
+                        break;
+                    }
+                }
+            }
+        });
+
+        let ctx = self.context.clone();
+        // stream 이 끝나거나 drop 되면 cancel 됨.
+        let cancel_guard = call.cancel.clone().drop_guard();
+        let mut task = TaskGuard::new(task);
+        // 종료 대기 시간이 지난 경우 중단하고 destroy 를 호출함.
+        let abort = self.abort.clone();
+        Ok((protocol, permit.attach(Box::pin(stream! {
+            match abort.run_until_cancelled(task.execute(ctx, &call, input_stream)).await {
+                None => {}
+                Some(Ok(mut output_stream)) => {
+                    while let Some(res) = abort.run_until_cancelled(output_stream.next()).await.flatten() {
+                        match res {
+                            Ok(output) => {
+                                yield Ok(output);
+                            }
+                            Err(e) => {
+                                yield Err(e);
+                                break;
+                            }
+                        }
+                    }
+                    drop(output_stream);
+                }
+                Some(Err(e)) => {
+                    yield Err(e);
+                }
+            }
+            drop(cancel_guard);
+            task.destroy().await;
+        }))))
+    }
+
+    /// protocol 의 backpressure 를 적용한 결과를 `max_page_byte_size` 크기의 Output 으로 나눔.
+    ///
+    /// page 단위가 아닌 결과 단위로 버리므로 나뉜 결과의 일부만 전달되지 않는다.
+    fn paged_stream(&self, protocol : u32, stream : DataStream<Vec<u8>>) -> Pin<Box<dyn tokio_stream::Stream<Item = Result<Output, Status>> + Send>> {
+        let mut stream = apply_backpressure(stream,
+                                            self.config.backpressure.policy(protocol),
+                                            self.config.max_channel_size,
+                                            self.frame_counters.clone(),
+                                            protocol);
+        let max_page_byte_size = self.config.max_page_byte_size;
+        Box::pin(stream! {
+            while let Some(res) = stream.next().await {
+                match res {
+                    Ok(output) => {
+                        for paged_output in paged_outputs(protocol, output, max_page_byte_size) {
+                            yield Ok(paged_output);
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(convert_cute_error_to_status(e));
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// 같은 peer 및 protocol 로 실행중인 stream 을 종료하고 새 stream 의 종료 signal 을 등록함.
+    ///
+    /// 이미 끝난 stream 의 signal 은 함께 제거한다.
+    async fn replace_stream(&self, key_name : String, stop_signal : tokio::sync::watch::Sender<bool>) {
+        let mut lock_peer_map = self.peer_map.lock().await;
+        lock_peer_map.retain(|_, sender| !sender.is_closed());
+        if let Some(sender) = lock_peer_map.insert(key_name.into_boxed_str(), stop_signal) {
+            let _ = sender.send(true);
+        }
+    }
+
+    /// 인증 후 `Task::execute` 에 전달할 `CallInfo` 를 생성함.
+    ///
+    /// token 은 `authorization` metadata 에서 읽으며 `Bearer ` 는 제거한다.
+    async fn identify<T>(&self, request : &Request<T>, protocol : u32) -> Result<CallInfo, Status> {
+        let token = request.metadata().get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.strip_prefix("Bearer ").unwrap_or(value).to_string());
+        let credential = Credential {
+            peer_addr: request.remote_addr(),
+            token,
+            peer_certificates: request.peer_certs()
+                .map(|certs| certs.iter().map(|cert| cert.get_ref().to_vec()).collect())
+                .unwrap_or_default(),
+        };
+        let identity = self.config.auth.authenticate(&credential).await.map_err(convert_cute_error_to_status)?;
+        Ok(CallInfo::new(protocol, request.remote_addr(), identity)
+            .with_context_watch(self.context_watch.clone()))
+    }
+
+    /// 요청마다 인증, 권한 확인 및 요청 수 제한을 수행함.
+    async fn authorize<T>(&self, request : &Request<T>, protocol : u32) -> Result<CallInfo, Status> {
+        let call = self.identify(request, protocol).await?;
+        self.config.auth.authorize(&call).await.map_err(convert_cute_error_to_status)?;
+        self.rate_limiter.acquire(&call).map_err(convert_cute_error_to_status)?;
+        Ok(call)
+    }
+
+    /// protocol 권한 검사 없이 인증 및 요청 수 제한만 수행함.
+    /// 조회, 감시 및 stream 종료 요청에 사용한다.
+    async fn authenticate<T>(&self, request : &Request<T>, protocol : u32) -> Result<CallInfo, Status> {
+        let call = self.identify(request, protocol).await?;
+        self.rate_limiter.acquire(&call).map_err(convert_cute_error_to_status)?;
+        Ok(call)
+    }
+}
 
 #[async_trait::async_trait]
 impl<R, P, C> CuteService for GRPCServer<R, P, C>
@@ -56,7 +309,8 @@ where R : AsRef<P> + Send + Sync + 'static,
       P : Procedure<C> + Send + Sync + 'static,
       C : Clone + Send + Sync + 'static,
 {
-    async fn get_services_name(&self, _request: Request<Empty>) -> Result<Response<Protocols>, Status> {
+    async fn get_services_name(&self, request: Request<Empty>) -> Result<Response<Protocols>, Status> {
+        self.authenticate(&request, 0).await?;
         let proc_map = self.procedure.as_ref();
         match proc_map.get_service_protocols().await {
             Ok(res) => {
@@ -71,15 +325,71 @@ where R : AsRef<P> + Send + Sync + 'static,
     }
     type ServerUnaryStream = Pin<Box<dyn tokio_stream::Stream<Item = Result<Output, Status>> + Send>>;
 
+    async fn get_services_info(&self, request: Request<Empty>) -> Result<Response<ProtocolInfos>, Status> {
+        self.authenticate(&request, 0).await?;
+        let infos = self.procedure.as_ref().get_service_infos().await
+            .map_err(convert_cute_error_to_status)?;
+        Ok(Response::new(ProtocolInfos {
+            info: infos.into_iter().map(|info| ProtocolInfo {
+                protocol: info.protocol,
+                kind: info.kind.value(),
+                name: info.name,
+                description: info.description,
+                input_schema: info.input_schema,
+                output_schema: info.output_schema,
+                idempotent: info.idempotent,
+            }).collect(),
+        }))
+    }
+
+    type WatchServicesStream = Pin<Box<dyn tokio_stream::Stream<Item = Result<ServiceEvent, Status>> + Send>>;
+
+    async fn watch_services(&self, request: Request<Empty>) -> Result<Response<Self::WatchServicesStream>, Status> {
+        self.authenticate(&request, 0).await?;
+        let mut event_rx = self.procedure.as_ref().subscribe_changes()
+            .ok_or_else(|| Status::unimplemented("procedure does not support change events"))?;
+        let shutdown = self.shutdown.clone();
+        Ok(Response::new(Box::pin(stream! {
+            loop {
+                let event = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    event = event_rx.recv() => event,
+                };
+                match event {
+                    Ok(event) => {
+                        yield Ok(ServiceEvent {
+                            kind: event.kind(),
+                            protocol: event.protocol(),
+                        });
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })))
+    }
+
     async fn server_unary(&self, mut request: Request<Input>) -> Result<Response<Self::ServerUnaryStream>, Status> {
         let proc_map = self.procedure.as_ref();
         let protocol = request.get_ref().protocol;
+        let timeout = request.get_ref().timeout
+            .filter(|timeout| *timeout != 0)
+            .map(std::time::Duration::from_millis);
+        let call = self.authorize(&request, protocol).await?
+            .with_deadline(self.config.call_deadline(timeout));
 
         match proc_map.get_task(protocol,
                                 request.get_mut().data.take().map(Vec::into_boxed_slice)).await {
-            Ok(mut task) => {
+            Ok(task) => {
                 let mut result = Vec::new();
-                let opt_output = task.execute(self.context.clone()).await.map_err(|e| convert_cute_error_to_status(e))?;
+                // 요청이 취소되어 해당 future 가 drop 되면 guard 에서 cancel 및 destroy 를 수행함.
+                let _cancel_guard = call.cancel.clone().drop_guard();
+                let mut task = TaskGuard::new(task);
+                let opt_result = self.abort.run_until_cancelled(call.until_deadline(task.execute(self.context.clone(), &call))).await;
+                task.destroy().await;
+                let opt_output = opt_result
+                    .ok_or_else(|| Status::cancelled("server shutdown"))?
+                    .map_err(convert_cute_error_to_status)?;
                 match opt_output {
                     None => {
                     }
@@ -111,69 +421,134 @@ where R : AsRef<P> + Send + Sync + 'static,
     async fn server_stream(&self, mut request: Request<Input>) -> Result<Response<Self::ServerStreamStream>, Status> {
         let (stop_signal,_) = tokio::sync::watch::channel(false);
         let protocol = request.get_ref().protocol;
+        let call = self.authorize(&request, protocol).await?;
+        let schedule = request.get_ref().schedule.as_ref()
+            .map(|schedule| StreamSchedule::from_parts(schedule.mode, schedule.value))
+            .transpose()
+            .map_err(convert_cute_error_to_status)?
+            .unwrap_or_default();
         let remote_addr = request
             .remote_addr()
             .map(|addr| addr.to_string())
             .unwrap_or_else(|| "unknown".to_string());
         let key_name = format!("{}_{}",remote_addr,protocol);
+        // 같은 key 의 stream 은 교체되므로 peer 별 제한에 걸린 경우에도 교체할 수 있음.
+        let permit = self.stream_limiter.acquire_replacing(&call, key_name.clone()).map_err(convert_cute_error_to_status)?;
 
         info!("key : {}",key_name);
 
-        let stop_rx = stop_signal.subscribe();
-        let mut lock_peer_map = self.peer_map.lock().await;
-        if let Some(sender) = lock_peer_map.get(&key_name.clone().into_boxed_str()) {
-            let _ = sender.send(true).is_err();
-        }
-        lock_peer_map.entry(key_name.clone().into_boxed_str()).or_insert(stop_signal);
-        drop(lock_peer_map);
-
+        let mut stop_rx = stop_signal.subscribe();
         let proc_map = self.procedure.as_ref();
-        match proc_map.get_task(protocol,
-                                request.get_mut().data.take().map(Vec::into_boxed_slice)).await {
-            Ok(mut task) => {
+        let input = request.get_mut().data.take().map(Vec::into_boxed_slice);
+
+        // StreamTask 로 등록된 경우 반환된 stream 을 그대로 전달하며 schedule 은 사용하지 않음.
+        // task 를 찾은 후에 등록하여 실패한 요청이 실행중인 stream 을 종료하지 않도록 함.
+        let frames : DataStream<Vec<u8>> = match proc_map.get_stream_task(protocol, input.clone()).await {
+            Ok(task) => {
+                self.replace_stream(key_name, stop_signal).await;
                 let ctx = self.context.clone();
-                let max_page_byte_size = self.config.max_page_byte_size;
-                Ok(Response::new(Box::pin(stream! {
-                    let mut is_closed = false;
+                let cancel_guard = call.cancel.clone().drop_guard();
+                let mut task = TaskGuard::new(task);
+                let abort = self.abort.clone();
+                Box::pin(stream! {
+                    match abort.run_until_cancelled(task.execute(ctx, &call)).await {
+                        None => {}
+                        Some(Ok(mut inner_stream)) => {
+                            loop {
+                                if *stop_rx.borrow() {
+                                    break;
+                                }
+                                let next = tokio::select! {
+                                    _ = abort.cancelled() => break,
+                                    next = inner_stream.next() => next,
+                                    res = stop_rx.changed() => {
+                                        if res.is_err() {
+                                            break;
+                                        }
+                                        continue;
+                                    }
+                                };
+                                match next {
+                                    Some(Ok(output)) => {
+                                        yield Ok(output);
+                                    }
+                                    Some(Err(e)) => {
+                                        yield Err(e);
+                                        break;
+                                    }
+                                    None => {
+                                        break;
+                                    }
+                                }
+                            }
+                            drop(inner_stream);
+                        }
+                        Some(Err(e)) => {
+                            yield Err(e);
+                        }
+                    }
+                    drop(cancel_guard);
+                    task.destroy().await;
+                    info!("Server Stream stopped");
+                })
+            }
+            Err(e) if e.code != CuteErrorCode::NotFound => {
+                return Err(convert_cute_error_to_status(e));
+            }
+            Err(_) => {
+                let task = proc_map.get_task(protocol, input).await
+                    .map_err(convert_cute_error_to_status)?;
+                self.replace_stream(key_name, stop_signal).await;
+                let ctx = self.context.clone();
+                let cancel_guard = call.cancel.clone().drop_guard();
+                let mut task = TaskGuard::new(task);
+                let mut ticker = StreamTicker::new(schedule, &self.context_watch);
+                let abort = self.abort.clone();
+                Box::pin(stream! {
                     loop {
                         if *stop_rx.borrow() {
-                            is_closed = true;
-                        }
-                        if is_closed {
                             break;
-                        } else {
-                            match task.execute(ctx.clone()).await {
-                                Ok(opt_output) => {
-                                    if let Some(output) = opt_output {
-                                        let output_len = output.len();
-                                        let chuck_size = output_len / max_page_byte_size + (output_len % max_page_byte_size != 0) as usize;
-                                        for (chuck_idx, chuck_item) in output.chunks(max_page_byte_size).enumerate() {
-                                            yield Ok( Output {
-                                                protocol,
-                                                page_size: chuck_size as u32,
-                                                page_idx: chuck_idx as u32,
-                                                data: chuck_item.to_vec(),
-                                            });
-                                        }
-                                    }
+                        }
+                        tokio::select! {
+                            _ = ticker.tick() => {}
+                            res = stop_rx.changed() => {
+                                if res.is_err() {
+                                    break;
+                                }
+                                continue;
+                            }
+                        }
+                        match abort.run_until_cancelled(task.execute(ctx.clone(), &call)).await {
+                            None => {
+                                break;
+                            }
+                            Some(Ok(opt_output)) => {
+                                let is_continue = ticker.is_continue(&opt_output);
+                                if let Some(output) = opt_output {
+                                    yield Ok(output);
                                 }
-                                Err(e) => {
-                                    yield Err(convert_cute_error_to_status(e));
+                                if !is_continue {
+                                    break;
                                 }
                             }
+                            Some(Err(e)) => {
+                                yield Err(e);
+                                break;
+                            }
                         }
                     }
+                    drop(cancel_guard);
+                    task.destroy().await;
                     info!("Server Stream stopped");
-                })))
+                })
             }
-            Err(e) => {
-                Err(convert_cute_error_to_status(e))
-            }
-        }
+        };
+        Ok(Response::new(self.paged_stream(protocol, permit.attach(frames))))
     }
 
     async fn server_stream_close(&self, request: Request<Input>) -> Result<Response<Empty>, Status> {
         let protocol = request.get_ref().protocol;
+        self.authenticate(&request, protocol).await?;
         let remote_addr = request
             .remote_addr()
             .map(|addr| addr.to_string())
@@ -182,15 +557,57 @@ where R : AsRef<P> + Send + Sync + 'static,
 
         let mut lock_peer_map = self.peer_map.lock().await;
         if let Some(sender) = lock_peer_map.remove(&key_name.clone().into_boxed_str()) {
-            let _ = sender.send(true).is_err();
+            let _ = sender.send(true);
         }
         drop(lock_peer_map);
 
         Ok(Response::new(Empty {}))
     }
 
-    async fn server_stream_all_close(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+    type ClientStreamStream = Pin<Box<dyn tokio_stream::Stream<Item = Result<Output, Status>> + Send>>;
 
+    async fn client_stream(&self, request: Request<Streaming<Input>>) -> Result<Response<Self::ClientStreamStream>, Status> {
+        let (protocol, mut output_stream) = self.open_bidi(request).await?;
+        let max_page_byte_size = self.config.max_page_byte_size;
+        Ok(Response::new(Box::pin(stream! {
+            // client streaming 은 마지막 결과를 응답으로 보냄.
+            let mut last_output = Ok(vec![]);
+            while let Some(res) = output_stream.next().await {
+                let is_err = res.is_err();
+                last_output = res;
+                if is_err {
+                    break;
+                }
+            }
+            drop(output_stream);
+            match last_output {
+                Ok(output) => {
+                    for paged_output in paged_outputs(protocol, output, max_page_byte_size) {
+                        yield Ok(paged_output);
+                    }
+                }
+                Err(e) => {
+                    yield Err(convert_cute_error_to_status(e));
+                }
+            }
+        })))
+    }
+
+    type BidiStreamStream = Pin<Box<dyn tokio_stream::Stream<Item = Result<Output, Status>> + Send>>;
+
+    async fn bidi_stream(&self, request: Request<Streaming<Input>>) -> Result<Response<Self::BidiStreamStream>, Status> {
+        let (protocol, output_stream) = self.open_bidi(request).await?;
+        let mut output_stream = self.paged_stream(protocol, output_stream);
+        Ok(Response::new(Box::pin(stream! {
+            while let Some(res) = output_stream.next().await {
+                yield res;
+            }
+            info!("Bidi Stream stopped");
+        })))
+    }
+
+    async fn server_stream_all_close(&self, request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        self.authenticate(&request, 0).await?;
         let remote_addr = request
             .remote_addr()
             .map(|addr| addr.to_string())
@@ -201,10 +618,22 @@ where R : AsRef<P> + Send + Sync + 'static,
         let key_to_remove: Vec<Box<str>> = lock_peer_map.keys().filter(|key| key.contains(remote_addr.as_str())).cloned().collect();
         for item in key_to_remove {
             if let Some(sender) = lock_peer_map.remove(&item) {
-                let _ = sender.send(true).is_err();
+                let _ = sender.send(true);
             }
         }
         drop(lock_peer_map);
         Ok(Response::new(Empty {}))
     }
 }
+
+/// 결과를 `max_page_byte_size` 크기의 Output 으로 나눔.
+fn paged_outputs(protocol : u32, output : Vec<u8>, max_page_byte_size : usize) -> Vec<Output> {
+    let output_len = output.len();
+    let chuck_size = output_len / max_page_byte_size + (output_len % max_page_byte_size != 0) as usize;
+    output.chunks(max_page_byte_size).enumerate().map(|(chuck_idx, chuck_item)| Output {
+        protocol,
+        page_size: chuck_size as u32,
+        page_idx: chuck_idx as u32,
+        data: chuck_item.to_vec(),
+    }).collect()
+}
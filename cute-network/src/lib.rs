@@ -2,21 +2,113 @@
 
 use std::net::SocketAddr;
 use std::sync::Arc;
-use cute_core::{CuteError, DataStream, Procedure};
+use tokio_util::sync::CancellationToken;
+use tokio_stream::StreamExt;
+use cute_core::{bin_deserialize, bin_serialize, ContextWatch, CuteError, CuteProtocol, DataStream, ProcEvent, Procedure, ProtocolInfo, StreamSchedule};
 use crate::grpc::GRPCClient;
 use crate::raw::{CutePacket, RawClient};
 
 mod grpc;
 mod raw;
+mod tls;
+mod auth;
+mod reconnect;
+mod retry;
+mod pool;
+mod rate_limit;
+mod backpressure;
 
-#[derive(Debug, Clone, Copy)]
+pub use self::auth::{AuthConfig, Authenticator, AuthorizationPolicy, Credential, ProtocolAcl, TokenAuthenticator};
+pub use self::reconnect::{ConnectionEvent, ReconnectPolicy};
+pub use self::retry::{RetryBudget, RetryPolicy};
+pub use self::pool::{ClientPool, EndpointResolver, EndpointStatus, LoadBalance, PoolConfig};
+pub use self::rate_limit::{RateLimit, RateLimitConfig};
+pub use self::backpressure::{BackpressureConfig, BackpressurePolicy, FrameCounters};
+
+#[derive(Debug, Clone)]
 pub struct NetworkConfig {
     pub max_page_byte_size: usize,
+    /// stream 의 결과 및 입력을 쌓아두는 수. 넘는 경우 입력은 대기하며 결과는 `backpressure` 에 따른다.
     pub max_channel_size : usize,
+    /// raw server 가 나뉘어 받는 요청 하나의 최대 크기 (byte). 0 은 제한하지 않음.
+    ///
+    /// 넘는 요청을 보낸 연결은 `ResourceExhausted` 를 보낸 후 종료한다.
+    pub max_message_size : usize,
+    /// raw server 가 연결마다 동시에 합치는 나뉜 요청의 수. 0 은 제한하지 않음.
+    ///
+    /// 넘는 요청을 보낸 연결은 `ResourceExhausted` 를 보낸 후 종료한다.
+    pub max_pending_messages : usize,
+    /// server 가 peer 및 protocol 마다 요청 하나를 받는 간격 (ms). 0 은 제한하지 않음.
+    ///
+    /// 넘는 요청은 `ResourceExhausted` 및 기다려야 하는 시간 (`CuteError::retry_after`) 으로 거부한다.
     pub request_limit_milli_second : usize,
     pub host_address: SocketAddr,
+    /// unary 요청 제한 시간 (초). 0 은 제한하지 않음.
+    ///
+    /// client 는 제한 시간을 지정하지 않은 요청에 사용하며 server 는 client 가 보낸 제한 시간과 비교해 짧은 것을 사용한다.
     pub time_out : u64,
     pub keep_alive_time_out : u64,
+    /// 종료 요청 후 처리중인 요청을 기다리는 시간 (초). 지나면 남은 작업을 중단한다.
+    pub shutdown_time_out : u64,
+    /// payload 압축 방식. 받는 쪽은 packet 마다 기록된 방식으로 해제하므로 서로 다른 설정을 사용해도 된다.
+    pub compress_type : CompressType,
+    /// 해당 크기 (byte) 이상의 payload 만 압축함.
+    pub compress_threshold : usize,
+    /// `None` 인 경우 TLS 를 사용하지 않음.
+    pub tls : Option<TlsConfig>,
+    /// 인증 및 권한 설정.
+    pub auth : AuthConfig,
+    /// client 의 연결이 끊긴 경우 다시 연결하는 방식. `None` 인 경우 다시 연결하지 않음.
+    pub reconnect : Option<ReconnectPolicy>,
+    /// server 가 idempotent 로 알린 protocol 의 unary 요청이 실패한 경우 다시 보내는 방식. `None` 인 경우 다시 보내지 않음.
+    pub retry : Option<RetryPolicy>,
+    /// 요청 수 제한의 burst 및 protocol 별 제한.
+    pub rate_limit : RateLimitConfig,
+    /// stream 의 결과를 처리하지 못한 경우의 동작 및 server 의 동시 stream 수 제한.
+    pub backpressure : BackpressureConfig,
+}
+
+/// gRPC 및 Raw 에서 사용하는 TLS 설정. 인증서 및 key 는 PEM 파일 경로를 사용한다.
+///
+/// + Server : `cert_path`, `key_path` 필수. `ca_path` 를 지정하면 해당 CA 로 client 인증서를 검증한다.
+/// + Client : `ca_path` 로 server 인증서를 검증하며 `cert_path`, `key_path` 를 지정하면 mutual TLS 로 동작한다.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    pub cert_path : Option<std::path::PathBuf>,
+    pub key_path : Option<std::path::PathBuf>,
+    pub ca_path : Option<std::path::PathBuf>,
+    /// Server 에서 client 인증서가 없는 연결을 거부함. `ca_path` 가 필요하다.
+    pub client_auth_required : bool,
+    /// Client 가 server 인증서를 검증할 때 사용하는 이름.
+    pub domain_name : String,
+}
+
+/// payload 압축 방식.
+///
+/// gRPC 는 tonic 이 지원하는 gzip 만 사용 가능하므로 `None` 이 아닌 경우 gzip 으로 압축한다.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompressType {
+    #[default]
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl NetworkConfig {
+    /// `time_out` 을 `Duration` 으로 변환. 0 인 경우 `None`.
+    pub(crate) fn unary_timeout(&self) -> Option<std::time::Duration> {
+        (self.time_out != 0).then(|| std::time::Duration::from_secs(self.time_out))
+    }
+
+    /// client 가 보낸 제한 시간 및 `time_out` 중 짧은 것으로 요청의 deadline 을 정함.
+    pub(crate) fn call_deadline(&self, timeout : Option<std::time::Duration>) -> Option<tokio::time::Instant> {
+        let limit = self.unary_timeout();
+        let timeout = match (timeout, limit) {
+            (Some(timeout), Some(limit)) => Some(timeout.min(limit)),
+            (timeout, limit) => timeout.or(limit),
+        };
+        timeout.map(|timeout| tokio::time::Instant::now() + timeout)
+    }
 }
 
 impl Default for NetworkConfig {
@@ -24,10 +116,21 @@ impl Default for NetworkConfig {
         Self {
             max_page_byte_size: 262_144,
             max_channel_size: 128,
+            max_message_size: 67_108_864,
+            max_pending_messages: 16,
             request_limit_milli_second: 0,
             host_address: SocketAddr::from(([0,0,0,0], 7777)),
             time_out: 30,
             keep_alive_time_out: 60,
+            shutdown_time_out: 10,
+            compress_type: CompressType::None,
+            compress_threshold: 1024,
+            tls: None,
+            auth: AuthConfig::default(),
+            reconnect: None,
+            retry: None,
+            rate_limit: RateLimitConfig::default(),
+            backpressure: BackpressureConfig::default(),
         }
     }
 }
@@ -44,21 +147,117 @@ impl Server {
 
     pub fn create_raw(config : NetworkConfig) -> Self { Server::Raw(config) }
 
-    pub async fn start_server<R, P, C>(&self, procedure : R, context : Arc<tokio::sync::RwLock<C>>) -> Result<(),std::io::Error>
+    /// Server 를 시작하고 `ServerHandle` 을 반환함.
+    ///
+    /// bind 는 반환 전에 수행되므로 반환된 시점부터 연결을 받을 수 있다.
+    pub async fn start_server<R, P, C>(&self, procedure : R, context : Arc<tokio::sync::RwLock<C>>) -> Result<ServerHandle,std::io::Error>
     where R : AsRef<P> + Send + Sync + 'static,
           P : Procedure<C> + Send + Sync + 'static,
           C : Default + Clone + Send + Sync + 'static,
     {
-        match self {
+        let shutdown = CancellationToken::new();
+        let context_watch = ContextWatch::new();
+        let frame_counters = FrameCounters::default();
+        let (local_addr, join) = match self {
             Server::GRPC(config) => {
-                grpc::GRPCServer::start(procedure, *config,context).await
+                let listener = tokio::net::TcpListener::bind(config.host_address).await?;
+                let local_addr = listener.local_addr()?;
+                (local_addr, tokio::spawn(grpc::GRPCServer::start(procedure, config.clone(), context, listener, shutdown.clone(), context_watch.clone(), frame_counters.clone())))
             }
             Server::Raw(config) => {
-                raw::CuteRawServer::<R,P,C,CutePacket>::start(procedure, *config,context).await
+                let listener = tokio::net::TcpListener::bind(config.host_address).await?;
+                let local_addr = listener.local_addr()?;
+                (local_addr, tokio::spawn(raw::CuteRawServer::<R,P,C,CutePacket>::start(procedure, config.clone(), context, listener, shutdown.clone(), context_watch.clone(), frame_counters.clone())))
             }
+        };
+
+        Ok(ServerHandle {
+            local_addr,
+            shutdown,
+            context_watch,
+            frame_counters,
+            join,
+        })
+    }
+}
+
+/// # Comment
+/// 실행중인 Server 의 handle.
+///
+/// `shutdown` 시 아래 순서로 종료한다.
+/// 1. 새로운 연결을 받지 않음.
+/// 2. 실행중인 모든 stream 을 종료하고 `Task::destroy` 를 호출.
+/// 3. 처리중인 unary 요청을 `NetworkConfig::shutdown_time_out` 동안 기다림. 지나면 중단한다.
+#[derive(Debug)]
+pub struct ServerHandle {
+    local_addr : SocketAddr,
+    shutdown : CancellationToken,
+    context_watch : ContextWatch,
+    frame_counters : FrameCounters,
+    join : tokio::task::JoinHandle<Result<(), std::io::Error>>,
+}
+
+impl ServerHandle {
+    /// 실제로 bind 된 주소. port 0 으로 시작한 경우 사용.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 다른 곳에서 종료를 요청하기 위한 token. `cancel` 시 `shutdown` 과 동일하게 종료한다.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Context 변경 알림. Server 외부에서 Context 를 변경한 경우 `notify` 를 호출한다.
+    pub fn context_watch(&self) -> ContextWatch {
+        self.context_watch.clone()
+    }
+
+    /// client 가 처리하지 못해 버린 stream 결과의 수. `BackpressurePolicy::Block` 인 protocol 은 버리지 않는다.
+    pub fn frame_counters(&self) -> FrameCounters {
+        self.frame_counters.clone()
+    }
+
+    /// 종료를 요청하고 종료될때까지 대기함.
+    pub async fn shutdown(self) -> Result<(), std::io::Error> {
+        self.shutdown.cancel();
+        self.wait().await
+    }
+
+    /// 종료를 요청하지 않고 Server 가 종료될때까지 대기함.
+    pub async fn wait(self) -> Result<(), std::io::Error> {
+        self.join.await.map_err(std::io::Error::other)?
+    }
+}
+
+/// # Comment
+/// bidi streaming 의 입력을 보내기 위한 handle.
+///
+/// drop 또는 `close` 시 server 에 입력 종료를 알린다. 결과 stream 은 server 의 `BidiTask` 가 끝날때까지 유지된다.
+#[derive(Debug, Clone)]
+pub struct BidiSender {
+    input_tx : tokio::sync::mpsc::Sender<Vec<u8>>,
+}
+
+impl BidiSender {
+    pub(crate) fn new(input_tx : tokio::sync::mpsc::Sender<Vec<u8>>) -> Self {
+        Self {
+            input_tx,
         }
     }
+
+    /// 입력을 보냄. 보내지 못한 입력이 `max_channel_size` 만큼 쌓인 경우 대기한다.
+    pub async fn send(&self, input : Vec<u8>) -> Result<(), CuteError> {
+        self.input_tx.send(input).await
+            .map_err(|_| CuteError::cancelled("bidi stream input closed"))
+    }
+
+    /// 입력 종료. clone 된 sender 가 있다면 모두 drop 되어야 종료된다.
+    pub fn close(self) {
+        drop(self);
+    }
 }
+
 pub enum Client<C>
 where C : Default + Clone + Send + Sync + 'static,
 {
@@ -77,41 +276,225 @@ where C : Default + Clone + Send + Sync + 'static
         Ok(Client::Raw(RawClient::new(config,context).await?))
     }
 
-    pub async fn get_service_names(&mut self) -> Result<Vec<u8>, CuteError>
+    /// server 에 등록된 protocol 목록.
+    pub async fn get_service_names(&mut self) -> Result<Vec<u32>, CuteError>
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.get_service_names().await
+            }
+            Client::Raw(client) => {
+                client.get_service_names().await
+            }
+        }
+    }
+
+    /// server 에 등록된 protocol 의 종류, 이름, 설명 및 schema.
+    pub async fn describe_services(&mut self) -> Result<Vec<ProtocolInfo>, CuteError>
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.describe_services().await
+            }
+            Client::Raw(client) => {
+                client.describe_services().await
+            }
+        }
+    }
+
+    /// server 의 protocol 등록, 교체 및 삭제 알림.
+    pub async fn watch_services(&mut self) -> Result<DataStream<ProcEvent>, CuteError>
     {
         match self {
             Client::GRPC(client) => {
-                client.get_service_names().await;
-                Ok(vec![])
+                client.watch_services().await
             }
             Client::Raw(client) => {
-                Ok(vec![])
+                client.watch_services().await
             }
         }
     }
 
+    /// 연결이 끊기고 다시 연결되는 과정. `NetworkConfig::reconnect` 가 `None` 이면 아무것도 전달되지 않는다.
+    pub fn watch_connection(&self) -> DataStream<ConnectionEvent>
+    {
+        let events = match self {
+            Client::GRPC(client) => {
+                client.watch_connection()
+            }
+            Client::Raw(client) => {
+                client.watch_connection()
+            }
+        };
+        // 처리가 늦어 밀린 알림은 건너뜀.
+        Box::pin(tokio_stream::wrappers::BroadcastStream::new(events).filter_map(|event| event.ok().map(Ok)))
+    }
+
+    /// 결과를 처리하지 못해 버린 stream 결과의 수. `BackpressurePolicy::Block` 인 protocol 은 버리지 않는다.
+    pub fn frame_counters(&self) -> FrameCounters
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.frame_counters()
+            }
+            Client::Raw(client) => {
+                client.frame_counters()
+            }
+        }
+    }
+
+    /// 연결이 끊겼으며 다시 연결하지 않는 경우. `ClientPool` 은 새 연결로 교체한다.
+    pub(crate) fn is_closed(&self) -> bool
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.is_closed()
+            }
+            Client::Raw(client) => {
+                client.is_closed()
+            }
+        }
+    }
+
+    /// 이름으로 protocol 을 찾음. 처음 찾거나 모르는 이름인 경우 `describe_services` 를 요청한다.
+    pub async fn get_protocol(&mut self, name : &str) -> Result<u32, CuteError>
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.get_protocol(name).await
+            }
+            Client::Raw(client) => {
+                client.get_protocol(name).await
+            }
+        }
+    }
+
+    /// 이름으로 unary 요청.
+    pub async fn get_unary_by_name(&mut self, name : &str, parameter : Option<Vec<u8>>) -> Result<Vec<u8>, CuteError>
+    {
+        let key = self.get_protocol(name).await?;
+        self.get_unary(key, parameter).await
+    }
+
+    /// 이름으로 stream 요청.
+    pub async fn get_stream_by_name(&mut self, name : &str, parameter : Option<Vec<u8>>, schedule : StreamSchedule) -> Result<DataStream<Vec<u8>>, CuteError>
+    {
+        let key = self.get_protocol(name).await?;
+        self.get_stream_with_schedule(key, parameter, schedule).await
+    }
 
     //task_constructor : Box<dyn TaskConstructor<T,C>>
+    /// 재시도 방식이 있는 protocol 은 실패시 다시 요청하며 `NetworkConfig::time_out` 은 모든 시도를 포함한다.
     pub async fn get_unary(&mut self, key :u32,parameter : Option<Vec<u8>>) -> Result<Vec<u8>, CuteError>
     {
         match self {
             Client::GRPC(client) => {
-                client.get_unary_data(key,parameter).await
+                client.get_unary_data(key,parameter,None).await
+            }
+            Client::Raw(client) => {
+                client.get_unary_data(key,parameter,None).await
+            }
+        }
+    }
+
+    /// protocol 의 재시도 방식을 지정함. server 의 idempotent 여부보다 우선하며 `None` 이면 다시 보내지 않는다.
+    pub fn set_retry_policy(&mut self, key : u32, policy : Option<RetryPolicy>)
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.set_retry_policy(key, policy)
+            }
+            Client::Raw(client) => {
+                client.set_retry_policy(key, policy)
+            }
+        }
+    }
+
+    /// 제한 시간을 지정한 unary 요청. `get_unary` 는 `NetworkConfig::time_out` 을 사용한다.
+    ///
+    /// server 는 `CallInfo::deadline` 으로 받으며 지나면 `DeadlineExceeded` 를 반환한다.
+    pub async fn get_unary_with_timeout(&mut self, key : u32, parameter : Option<Vec<u8>>, timeout : std::time::Duration) -> Result<Vec<u8>, CuteError>
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.get_unary_data(key,parameter,Some(timeout)).await
             }
             Client::Raw(client) => {
-                client.get_unary_data(key,parameter).await
+                client.get_unary_data(key,parameter,Some(timeout)).await
             }
         }
     }
 
+    /// `NetworkConfig::reconnect` 가 있는 경우 연결이 끊겨 끝난 stream 은 다시 연결한 후 같은 parameter 로 다시 요청된다.
+    /// `Task` 가 반환한 `Unavailable` 은 그대로 전달되며 stream 이 끝난다.
     pub async fn get_stream(&mut self, key : u32,parameter : Option<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError>
     {
+        self.get_stream_with_schedule(key, parameter, StreamSchedule::Continuous).await
+    }
+
+    /// server 에서 `schedule` 에 맞춰 `Task::execute` 를 호출하는 stream 을 요청함.
+    pub async fn get_stream_with_schedule(&mut self, key : u32, parameter : Option<Vec<u8>>, schedule : StreamSchedule) -> Result<DataStream<Vec<u8>>, CuteError>
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.get_stream_data(key,parameter,schedule).await
+            }
+            Client::Raw(client) => {
+                client.get_stream_data(key,parameter,schedule).await
+            }
+        }
+    }
+
+    /// `CuteProtocol` 의 type 으로 unary 요청. 응답이 비어있으면 (`execute` 가 `None` 을 반환) `None` 이다.
+    pub async fn get_typed_unary<P : CuteProtocol>(&mut self, input : &P::Input) -> Result<Option<P::Output>, CuteError>
+    {
+        let output = self.get_unary(P::ID, Some(bin_serialize(input)?)).await?;
+        Self::typed_output::<P>(output)
+    }
+
+    /// `get_typed_unary` 에 제한 시간을 지정함.
+    pub async fn get_typed_unary_with_timeout<P : CuteProtocol>(&mut self, input : &P::Input, timeout : std::time::Duration) -> Result<Option<P::Output>, CuteError>
+    {
+        let output = self.get_unary_with_timeout(P::ID, Some(bin_serialize(input)?), timeout).await?;
+        Self::typed_output::<P>(output)
+    }
+
+    fn typed_output<P : CuteProtocol>(output : Vec<u8>) -> Result<Option<P::Output>, CuteError> {
+        if output.is_empty() {
+            return Ok(None);
+        }
+        bin_deserialize::<P::Output>(&output).map(Some)
+    }
+
+    /// `CuteProtocol` 의 type 으로 stream 요청. 변환에 실패한 결과는 `DeSerializeInvalid` 로 전달된다.
+    pub async fn get_typed_stream<P : CuteProtocol>(&mut self, input : &P::Input, schedule : StreamSchedule) -> Result<DataStream<P::Output>, CuteError>
+    {
+        let stream = self.get_stream_with_schedule(P::ID, Some(bin_serialize(input)?), schedule).await?;
+        Ok(Box::pin(stream.map(|res| res.and_then(|output| bin_deserialize::<P::Output>(&output)))))
+    }
+
+    /// client streaming 요청. `input` 을 모두 보낸 후 server 의 응답 하나를 반환한다.
+    pub async fn send_stream<S>(&mut self, key : u32, parameter : Option<Vec<u8>>, input : S) -> Result<Vec<u8>, CuteError>
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        match self {
+            Client::GRPC(client) => {
+                client.send_stream_data(key,parameter,input).await
+            }
+            Client::Raw(client) => {
+                client.send_stream_data(key,parameter,input).await
+            }
+        }
+    }
+
+    /// bidi streaming 요청. 입력을 보내는 `BidiSender` 와 결과 stream 을 반환한다.
+    pub async fn open_bidi(&mut self, key : u32, parameter : Option<Vec<u8>>) -> Result<(BidiSender, DataStream<Vec<u8>>), CuteError> {
         match self {
             Client::GRPC(client) => {
-                client.get_stream_data(key,parameter).await
+                client.open_bidi(key,parameter).await
             }
             Client::Raw(client) => {
-                client.get_stream_data(key,parameter).await
+                client.open_bidi(key,parameter).await
             }
         }
     }
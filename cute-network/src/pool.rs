@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::Duration;
+use async_stream::stream;
+use log::warn;
+use tokio::sync::OwnedMutexGuard;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+use cute_core::{CuteError, CuteErrorCode, CuteProtocol, DataStream, ProtocolInfo, StreamSchedule};
+use crate::{BidiSender, Client, NetworkConfig, RetryPolicy};
+
+/// # Comment
+/// `ClientPool` 이 연결할 server 목록을 제공함.
+///
+/// `Vec<SocketAddr>` (고정 목록) 및 `Fn() -> Future<Output = Result<Vec<SocketAddr>, CuteError>>` 는 이미 구현되어 있다.
+#[async_trait::async_trait]
+pub trait EndpointResolver : Send + Sync {
+    async fn resolve(&self) -> Result<Vec<SocketAddr>, CuteError>;
+}
+
+#[async_trait::async_trait]
+impl EndpointResolver for Vec<SocketAddr> {
+    async fn resolve(&self) -> Result<Vec<SocketAddr>, CuteError> {
+        Ok(self.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> EndpointResolver for F
+where F : Fn() -> Fut + Send + Sync,
+      Fut : Future<Output = Result<Vec<SocketAddr>, CuteError>> + Send,
+{
+    async fn resolve(&self) -> Result<Vec<SocketAddr>, CuteError> {
+        self().await
+    }
+}
+
+/// unary 요청을 보낼 server 를 고르는 방식.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalance {
+    /// 순서대로 돌아가며 보냄.
+    #[default]
+    RoundRobin,
+    /// 처리중이거나 대기중인 요청이 가장 적은 server 로 보냄.
+    LeastOutstanding,
+}
+
+/// # Comment
+/// `ClientPool` 설정. 각 연결은 `NetworkConfig` 의 `host_address` 만 바꿔 생성한다.
+#[derive(Clone)]
+pub struct PoolConfig {
+    pub resolver : Arc<dyn EndpointResolver>,
+    /// server 목록을 다시 받는 간격. 0 은 생성시 한번만 받음.
+    pub resolve_interval : Duration,
+    pub balance : LoadBalance,
+    /// server 마다 만드는 최대 연결 수. 연결마다 한번에 하나의 요청만 보내므로 server 마다 동시에 보낼 요청 수와 같다.
+    pub connections_per_endpoint : usize,
+    /// 연속으로 `Unavailable` 을 받은 횟수가 해당 값 이상이면 `eject_time` 동안 제외함. 연결에 실패하면 바로 제외한다.
+    pub unhealthy_threshold : u32,
+    pub eject_time : Duration,
+}
+
+impl std::fmt::Debug for PoolConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolConfig")
+            .field("resolve_interval", &self.resolve_interval)
+            .field("balance", &self.balance)
+            .field("connections_per_endpoint", &self.connections_per_endpoint)
+            .field("unhealthy_threshold", &self.unhealthy_threshold)
+            .field("eject_time", &self.eject_time)
+            .finish()
+    }
+}
+
+impl PoolConfig {
+    pub fn new(resolver : impl EndpointResolver + 'static) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            resolve_interval: Duration::ZERO,
+            balance: LoadBalance::RoundRobin,
+            connections_per_endpoint: 4,
+            unhealthy_threshold: 3,
+            eject_time: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_balance(mut self, balance : LoadBalance) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    pub fn with_resolve_interval(mut self, resolve_interval : Duration) -> Self {
+        self.resolve_interval = resolve_interval;
+        self
+    }
+}
+
+/// `ClientPool::endpoints` 로 받는 server 별 상태.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    pub address : SocketAddr,
+    pub healthy : bool,
+    /// 처리중이거나 연결을 기다리는 요청 수.
+    pub outstanding : usize,
+    /// 현재 연결된 수.
+    pub connections : usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Grpc,
+    Raw,
+}
+
+/// server 하나의 연결 및 상태.
+struct Endpoint<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    address : SocketAddr,
+    connections : Vec<Arc<tokio::sync::Mutex<Option<Client<C>>>>>,
+    next_connection : AtomicUsize,
+    outstanding : AtomicUsize,
+    failures : AtomicU32,
+    ejected_until : std::sync::Mutex<Option<Instant>>,
+}
+
+impl<C> Endpoint<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    fn new(address : SocketAddr, connection_count : usize) -> Self {
+        Self {
+            address,
+            connections: (0..connection_count.max(1)).map(|_| Arc::new(tokio::sync::Mutex::new(None))).collect(),
+            next_connection: AtomicUsize::new(0),
+            outstanding: AtomicUsize::new(0),
+            failures: AtomicU32::new(0),
+            ejected_until: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn is_healthy(&self, now : Instant) -> bool {
+        self.ejected_until.lock().unwrap_or_else(|e| e.into_inner())
+            .is_none_or(|until| until <= now)
+    }
+
+    /// 결과를 상태에 반영함. `Unavailable` 만 실패로 보며 다른 결과는 server 가 응답한 것이다.
+    fn record<T>(&self, result : &Result<T, CuteError>, config : &PoolConfig) {
+        match result {
+            Err(e) if e.code == CuteErrorCode::Unavailable => {
+                let failures = self.failures.fetch_add(1, Ordering::AcqRel) + 1;
+                if failures >= config.unhealthy_threshold {
+                    self.eject(config.eject_time);
+                }
+            }
+            _ => {
+                self.failures.store(0, Ordering::Release);
+                *self.ejected_until.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            }
+        }
+    }
+
+    fn eject(&self, eject_time : Duration) {
+        *self.ejected_until.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now() + eject_time);
+    }
+
+    /// 사용중이지 않은 연결. 모두 사용중이면 돌아가며 하나를 기다린다.
+    async fn lock_connection(&self) -> OwnedMutexGuard<Option<Client<C>>> {
+        for connection in &self.connections {
+            if let Ok(guard) = connection.clone().try_lock_owned() {
+                return guard;
+            }
+        }
+        let idx = self.next_connection.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        self.connections[idx].clone().lock_owned().await
+    }
+
+    fn status(&self, now : Instant) -> EndpointStatus {
+        EndpointStatus {
+            address: self.address,
+            healthy: self.is_healthy(now),
+            outstanding: self.outstanding.load(Ordering::Acquire),
+            connections: self.connections.iter()
+                .filter(|connection| connection.try_lock().map_or(true, |guard| guard.is_some()))
+                .count(),
+        }
+    }
+}
+
+/// 요청을 보내는 동안 사용하는 연결. drop 시 처리중인 요청 수를 줄인다.
+struct Lease<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    endpoint : Arc<Endpoint<C>>,
+    guard : OwnedMutexGuard<Option<Client<C>>>,
+}
+
+impl<C> Lease<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    fn client(&mut self) -> &mut Client<C> {
+        self.guard.as_mut().expect("leased connection is connected")
+    }
+
+    /// 결과를 반영하고 다시 연결하지 않는 끊긴 연결은 다음 요청에서 새로 연결하도록 버림.
+    fn finish<T>(mut self, result : Result<T, CuteError>, config : &PoolConfig) -> Result<T, CuteError> {
+        self.endpoint.record(&result, config);
+        if self.guard.as_ref().is_some_and(|client| client.is_closed()) {
+            *self.guard = None;
+        }
+        result
+    }
+}
+
+impl<C> Drop for Lease<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        self.endpoint.outstanding.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// # Comment
+/// 여러 server 에 요청을 나누어 보내는 client.
+///
+/// + unary 요청은 `PoolConfig::balance` 에 따라 server 를 고르며 `Unavailable` 이 이어지는 server 는 잠시 제외한다.
+/// + stream 은 요청한 server 의 연결에서 끝날때까지 유지되며 server 목록에서 빠져도 끊지 않는다.
+/// + 연결은 처음 사용할 때 생성한다. 연결에 실패하면 다른 server 로 보낸다.
+///
+/// 모든 요청은 `&self` 로 보내므로 `Arc` 로 공유하여 동시에 사용할 수 있다.
+pub struct ClientPool<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    transport : Transport,
+    config : NetworkConfig,
+    pool_config : PoolConfig,
+    context : Arc<tokio::sync::RwLock<C>>,
+    endpoints : std::sync::RwLock<Vec<Arc<Endpoint<C>>>>,
+    resolved_at : tokio::sync::Mutex<Instant>,
+    next_endpoint : AtomicUsize,
+    retry_policies : std::sync::Mutex<HashMap<u32, Option<RetryPolicy>>>,
+}
+
+impl<C> ClientPool<C>
+where C : Default + Clone + Send + Sync + 'static,
+{
+    pub async fn create_grpc(config : NetworkConfig, pool_config : PoolConfig, context : Arc<tokio::sync::RwLock<C>>) -> Result<Self, CuteError> {
+        Self::new(Transport::Grpc, config, pool_config, context).await
+    }
+
+    pub async fn create_raw(config : NetworkConfig, pool_config : PoolConfig, context : Arc<tokio::sync::RwLock<C>>) -> Result<Self, CuteError> {
+        Self::new(Transport::Raw, config, pool_config, context).await
+    }
+
+    async fn new(transport : Transport, config : NetworkConfig, pool_config : PoolConfig, context : Arc<tokio::sync::RwLock<C>>) -> Result<Self, CuteError> {
+        let pool = Self {
+            transport,
+            config,
+            pool_config,
+            context,
+            endpoints: std::sync::RwLock::new(Vec::new()),
+            resolved_at: tokio::sync::Mutex::new(Instant::now()),
+            next_endpoint: AtomicUsize::new(0),
+            retry_policies: std::sync::Mutex::new(HashMap::new()),
+        };
+        pool.refresh().await?;
+        Ok(pool)
+    }
+
+    /// server 목록을 다시 받음. 남아있는 server 의 연결 및 상태는 유지한다.
+    pub async fn refresh(&self) -> Result<(), CuteError> {
+        let addresses = self.pool_config.resolver.resolve().await?;
+        let mut endpoints = self.endpoints.write().unwrap_or_else(|e| e.into_inner());
+        let mut previous : HashMap<SocketAddr, Arc<Endpoint<C>>> = endpoints.drain(..)
+            .map(|endpoint| (endpoint.address, endpoint))
+            .collect();
+        for address in addresses {
+            if endpoints.iter().any(|endpoint| endpoint.address == address) {
+                continue;
+            }
+            let endpoint = previous.remove(&address)
+                .unwrap_or_else(|| Arc::new(Endpoint::new(address, self.pool_config.connections_per_endpoint)));
+            endpoints.push(endpoint);
+        }
+        Ok(())
+    }
+
+    /// `resolve_interval` 이 지난 경우 server 목록을 다시 받음. 이미 다른 요청이 받는 중이면 기다리지 않는다.
+    async fn refresh_if_needed(&self) {
+        if self.pool_config.resolve_interval.is_zero() {
+            return;
+        }
+        let Ok(mut resolved_at) = self.resolved_at.try_lock() else {
+            return;
+        };
+        if resolved_at.elapsed() < self.pool_config.resolve_interval {
+            return;
+        }
+        if let Err(e) = self.refresh().await {
+            warn!("resolve endpoints failed : {}", e);
+        }
+        *resolved_at = Instant::now();
+    }
+
+    /// 현재 server 목록 및 상태.
+    pub fn endpoints(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.endpoints.read().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|endpoint| endpoint.status(now))
+            .collect()
+    }
+
+    /// 요청을 보낼 server. 모든 server 가 제외된 경우 전체에서 고른다.
+    fn select(&self) -> Result<Arc<Endpoint<C>>, CuteError> {
+        let endpoints = self.endpoints.read().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let mut candidates : Vec<_> = endpoints.iter().filter(|endpoint| endpoint.is_healthy(now)).collect();
+        if candidates.is_empty() {
+            candidates = endpoints.iter().collect();
+        }
+        if candidates.is_empty() {
+            return Err(CuteError::unavailable("no endpoint"));
+        }
+
+        let start = self.next_endpoint.fetch_add(1, Ordering::Relaxed);
+        let count = candidates.len();
+        let endpoint = match self.pool_config.balance {
+            LoadBalance::RoundRobin => candidates[start % count],
+            // 같은 수인 경우 돌아가며 고름.
+            LoadBalance::LeastOutstanding => (0..count)
+                .map(|i| candidates[(start + i) % count])
+                .min_by_key(|endpoint| endpoint.outstanding.load(Ordering::Acquire))
+                .expect("candidates is not empty"),
+        };
+        Ok(endpoint.clone())
+    }
+
+    /// server 를 골라 연결을 빌림. 연결에 실패한 server 는 제외하고 server 수 만큼 다시 고른다.
+    async fn acquire(&self) -> Result<Lease<C>, CuteError> {
+        self.refresh_if_needed().await;
+        let attempts = self.endpoints.read().unwrap_or_else(|e| e.into_inner()).len().max(1);
+        let mut last_error = None;
+        for _ in 0..attempts {
+            let endpoint = self.select()?;
+            endpoint.outstanding.fetch_add(1, Ordering::AcqRel);
+            let guard = endpoint.lock_connection().await;
+            let mut lease = Lease { endpoint, guard };
+            if lease.guard.is_some() {
+                return Ok(lease);
+            }
+            match self.connect(lease.endpoint.address).await {
+                Ok(client) => {
+                    *lease.guard = Some(client);
+                    return Ok(lease);
+                }
+                Err(e) => {
+                    warn!("connect to {} failed : {}", lease.endpoint.address, e);
+                    lease.endpoint.eject(self.pool_config.eject_time);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| CuteError::unavailable("no endpoint")))
+    }
+
+    async fn connect(&self, address : SocketAddr) -> Result<Client<C>, CuteError> {
+        let mut config = self.config.clone();
+        config.host_address = address;
+        let mut client = match self.transport {
+            Transport::Grpc => Client::create_grpc(config, self.context.clone()).await?,
+            Transport::Raw => Client::create_raw(config, self.context.clone()).await?,
+        };
+        for (key, policy) in self.retry_policies.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            client.set_retry_policy(*key, policy.clone());
+        }
+        Ok(client)
+    }
+
+    /// 연결된 모든 연결에 `f` 를 수행함. 하나라도 실패하면 마지막 오류를 반환한다.
+    ///
+    /// 끊긴 연결의 stream 은 이미 끝났으므로 `Unavailable` 은 무시한다.
+    async fn for_each_connection<F>(&self, mut f : F) -> Result<(), CuteError>
+    where F : for<'a> FnMut(&'a mut Client<C>) -> std::pin::Pin<Box<dyn Future<Output = Result<(), CuteError>> + Send + 'a>>,
+    {
+        let endpoints = self.endpoints.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let mut result = Ok(());
+        for endpoint in endpoints {
+            for connection in &endpoint.connections {
+                if let Some(client) = connection.lock().await.as_mut() {
+                    match f(client).await {
+                        Err(e) if e.code != CuteErrorCode::Unavailable => result = Err(e),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// stream 이 끝날때까지 server 를 유지하며 `Unavailable` 로 끝나면 server 의 실패로 기록함.
+    fn pin_stream<T : Send + 'static>(&self, endpoint : Arc<Endpoint<C>>, mut stream : DataStream<T>) -> DataStream<T> {
+        let pool_config = self.pool_config.clone();
+        Box::pin(stream! {
+            while let Some(item) = stream.next().await {
+                if item.is_err() {
+                    endpoint.record(&item, &pool_config);
+                }
+                yield item;
+            }
+        })
+    }
+
+    /// server 하나에 등록된 protocol 목록.
+    pub async fn get_service_names(&self) -> Result<Vec<u32>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().get_service_names().await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    /// server 하나에 등록된 protocol 의 설명. 모든 server 가 같은 protocol 을 등록한 것으로 본다.
+    pub async fn describe_services(&self) -> Result<Vec<ProtocolInfo>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().describe_services().await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    pub async fn get_unary(&self, key : u32, parameter : Option<Vec<u8>>) -> Result<Vec<u8>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().get_unary(key, parameter).await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    pub async fn get_unary_with_timeout(&self, key : u32, parameter : Option<Vec<u8>>, timeout : Duration) -> Result<Vec<u8>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().get_unary_with_timeout(key, parameter, timeout).await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    /// 이름으로 unary 요청. 이름은 요청을 보내는 연결에서 찾는다.
+    pub async fn get_unary_by_name(&self, name : &str, parameter : Option<Vec<u8>>) -> Result<Vec<u8>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().get_unary_by_name(name, parameter).await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    pub async fn get_typed_unary<P : CuteProtocol>(&self, input : &P::Input) -> Result<Option<P::Output>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().get_typed_unary::<P>(input).await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    /// 모든 연결의 protocol 재시도 방식을 지정함. 이후 생성되는 연결에도 적용된다.
+    pub async fn set_retry_policy(&self, key : u32, policy : Option<RetryPolicy>) {
+        self.retry_policies.lock().unwrap_or_else(|e| e.into_inner()).insert(key, policy.clone());
+        let _ = self.for_each_connection(|client| {
+            client.set_retry_policy(key, policy.clone());
+            Box::pin(async { Ok(()) })
+        }).await;
+    }
+
+    pub async fn get_stream(&self, key : u32, parameter : Option<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError> {
+        self.get_stream_with_schedule(key, parameter, StreamSchedule::Continuous).await
+    }
+
+    pub async fn get_stream_with_schedule(&self, key : u32, parameter : Option<Vec<u8>>, schedule : StreamSchedule) -> Result<DataStream<Vec<u8>>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let endpoint = lease.endpoint.clone();
+        let result = lease.client().get_stream_with_schedule(key, parameter, schedule).await;
+        Ok(self.pin_stream(endpoint, lease.finish(result, &self.pool_config)?))
+    }
+
+    pub async fn get_typed_stream<P : CuteProtocol>(&self, input : &P::Input, schedule : StreamSchedule) -> Result<DataStream<P::Output>, CuteError> {
+        let mut lease = self.acquire().await?;
+        let endpoint = lease.endpoint.clone();
+        let result = lease.client().get_typed_stream::<P>(input, schedule).await;
+        Ok(self.pin_stream(endpoint, lease.finish(result, &self.pool_config)?))
+    }
+
+    pub async fn send_stream<S>(&self, key : u32, parameter : Option<Vec<u8>>, input : S) -> Result<Vec<u8>, CuteError>
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        let mut lease = self.acquire().await?;
+        let result = lease.client().send_stream(key, parameter, input).await;
+        lease.finish(result, &self.pool_config)
+    }
+
+    pub async fn open_bidi(&self, key : u32, parameter : Option<Vec<u8>>) -> Result<(BidiSender, DataStream<Vec<u8>>), CuteError> {
+        let mut lease = self.acquire().await?;
+        let endpoint = lease.endpoint.clone();
+        let result = lease.client().open_bidi(key, parameter).await;
+        let (sender, stream) = lease.finish(result, &self.pool_config)?;
+        Ok((sender, self.pin_stream(endpoint, stream)))
+    }
+
+    /// 모든 연결의 `key` stream 을 종료함.
+    pub async fn close_stream(&self, key : u32) -> Result<(), CuteError> {
+        self.for_each_connection(|client| Box::pin(client.close_stream(key))).await
+    }
+
+    pub async fn close_stream_all(&self) -> Result<(), CuteError> {
+        self.for_each_connection(|client| Box::pin(client.close_stream_all())).await
+    }
+}
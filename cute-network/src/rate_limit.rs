@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::time::Instant;
+use cute_core::{CallInfo, CuteError};
+
+/// 남아있는 bucket 이 해당 수를 넘으면 가득 찬 bucket 을 정리함.
+const MAX_IDLE_BUCKETS : usize = 1024;
+
+/// # Comment
+/// token bucket 으로 요청 수를 제한함.
+///
+/// `interval` 마다 token 이 하나 채워지며 최대 `burst` 개까지 모인다. 요청마다 token 하나를 사용한다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// 0 은 제한하지 않음.
+    pub interval : Duration,
+    /// 0 은 1 로 본다.
+    pub burst : u32,
+}
+
+impl RateLimit {
+    pub fn new(interval : Duration, burst : u32) -> Self {
+        Self {
+            interval,
+            burst,
+        }
+    }
+
+    /// 제한하지 않음. 기본 제한을 사용하지 않을 protocol 에 지정한다.
+    pub fn unlimited() -> Self {
+        Self::new(Duration::ZERO, 0)
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.interval.is_zero()
+    }
+
+    fn capacity(&self) -> f64 {
+        self.burst.max(1) as f64
+    }
+}
+
+/// # Comment
+/// server 의 요청 수 제한 설정. peer (ip) 및 protocol 마다 따로 제한한다.
+///
+/// 기본 제한은 `NetworkConfig::request_limit_milli_second` 를 `interval` 로, `burst` 를 사용하며 `protocols` 에 지정한 protocol 은 해당 제한을 사용한다.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub burst : u32,
+    pub protocols : HashMap<u32, RateLimit>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            burst: 1,
+            protocols: HashMap::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn with_burst(mut self, burst : u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn with_protocol(mut self, protocol : u32, limit : RateLimit) -> Self {
+        self.protocols.insert(protocol, limit);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens : f64,
+    updated : Instant,
+}
+
+impl Bucket {
+    /// 지난 시간만큼 token 을 채움.
+    fn refill(&mut self, limit : &RateLimit, now : Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed / limit.interval.as_secs_f64()).min(limit.capacity());
+        self.updated = now;
+    }
+}
+
+/// server 의 peer 및 protocol 별 token bucket.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    default : Option<RateLimit>,
+    protocols : HashMap<u32, RateLimit>,
+    buckets : std::sync::Mutex<HashMap<(Option<IpAddr>, u32), Bucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(request_limit_milli_second : usize, config : &RateLimitConfig) -> Self {
+        let default = (request_limit_milli_second != 0)
+            .then(|| RateLimit::new(Duration::from_millis(request_limit_milli_second as u64), config.burst));
+        Self {
+            default,
+            protocols: config.protocols.clone(),
+            buckets: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn limit(&self, protocol : u32) -> Option<RateLimit> {
+        self.protocols.get(&protocol).copied()
+            .or(self.default)
+            .filter(|limit| !limit.is_unlimited())
+    }
+
+    /// 요청 하나의 token 을 사용함. 부족한 경우 token 이 채워질 때까지의 시간과 함께 `ResourceExhausted` 를 반환한다.
+    pub(crate) fn acquire(&self, call : &CallInfo) -> Result<(), CuteError> {
+        let Some(limit) = self.limit(call.protocol) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|(_, protocol), bucket| match self.limit(*protocol) {
+                Some(limit) => {
+                    bucket.refill(&limit, now);
+                    bucket.tokens < limit.capacity()
+                }
+                None => false,
+            });
+        }
+
+        let peer = call.peer_addr.map(|addr| addr.ip());
+        let bucket = buckets.entry((peer, call.protocol))
+            .or_insert_with(|| Bucket { tokens: limit.capacity(), updated: now });
+        bucket.refill(&limit, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after = limit.interval.mul_f64(1.0 - bucket.tokens);
+        Err(CuteError::resource_exhausted(format!("protocol {} rate limit exceeded", call.protocol))
+            .with_retry_after(retry_after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use cute_core::CuteErrorCode;
+    use super::*;
+
+    fn call(peer : [u8; 4], protocol : u32) -> CallInfo {
+        CallInfo::new(protocol, Some(SocketAddr::from((peer, 10000))), None)
+    }
+
+    fn retry_after(limiter : &RateLimiter, call : &CallInfo) -> Duration {
+        let err = limiter.acquire(call).unwrap_err();
+        assert_eq!(err.code, CuteErrorCode::ResourceExhausted);
+        err.retry_after.unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tokens_refill_per_interval() {
+        let limiter = RateLimiter::new(100, &RateLimitConfig::default().with_burst(2));
+        let call = call([127, 0, 0, 1], 1);
+        limiter.acquire(&call).unwrap();
+        limiter.acquire(&call).unwrap();
+        assert_eq!(retry_after(&limiter, &call), Duration::from_millis(100));
+
+        tokio::time::advance(Duration::from_millis(40)).await;
+        assert_eq!(retry_after(&limiter, &call), Duration::from_millis(60));
+        tokio::time::advance(Duration::from_millis(60)).await;
+        limiter.acquire(&call).unwrap();
+        assert_eq!(retry_after(&limiter, &call), Duration::from_millis(100));
+
+        // 오래 기다려도 burst 이상 모이지 않음.
+        tokio::time::advance(Duration::from_secs(10)).await;
+        limiter.acquire(&call).unwrap();
+        limiter.acquire(&call).unwrap();
+        assert!(limiter.acquire(&call).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn buckets_are_per_peer_and_protocol() {
+        let config = RateLimitConfig::default()
+            .with_protocol(2, RateLimit::new(Duration::from_millis(500), 1))
+            .with_protocol(3, RateLimit::unlimited());
+        let limiter = RateLimiter::new(100, &config);
+        limiter.acquire(&call([127, 0, 0, 1], 1)).unwrap();
+        assert!(limiter.acquire(&call([127, 0, 0, 1], 1)).is_err());
+
+        // 다른 peer 및 protocol 은 따로 센다.
+        limiter.acquire(&call([127, 0, 0, 2], 1)).unwrap();
+        limiter.acquire(&call([127, 0, 0, 1], 2)).unwrap();
+        assert_eq!(retry_after(&limiter, &call([127, 0, 0, 1], 2)), Duration::from_millis(500));
+        for _ in 0..3 {
+            limiter.acquire(&call([127, 0, 0, 1], 3)).unwrap();
+        }
+
+        tokio::time::advance(Duration::from_millis(100)).await;
+        limiter.acquire(&call([127, 0, 0, 1], 1)).unwrap();
+        assert_eq!(retry_after(&limiter, &call([127, 0, 0, 1], 2)), Duration::from_millis(400));
+    }
+}
@@ -1,8 +1,13 @@
 use std::sync::Arc;
 use async_stream::stream;
+use log::warn;
 use tokio_stream::StreamExt;
-use cute_core::{CuteError, DataStream};
-use crate::NetworkConfig;
+use cute_core::{CuteError, DataStream, ProcEvent, ProtocolInfo, StreamSchedule};
+use crate::{BidiSender, ConnectionEvent, FrameCounters, NetworkConfig};
+use crate::backpressure::apply_backpressure;
+use crate::reconnect::{resubscribe, Reconnector};
+use crate::retry::{Retrier, RetryPolicy};
+use crate::tls::create_connector;
 use crate::raw::CutePacketTrait;
 use crate::raw::stub::CuteRawServiceClient;
 
@@ -12,9 +17,11 @@ where C : Send + Sync + 'static,
     P : CutePacketTrait + Send
 {
     config : NetworkConfig,
-    client : CuteRawServiceClient<P>,
+    connection : Arc<Reconnector<CuteRawServiceClient<P>>>,
     context : Arc<tokio::sync::RwLock<C>>,
-    protocol_name_map : std::collections::HashMap<Box<str>, u32>
+    protocol_name_map : std::collections::HashMap<Box<str>, u32>,
+    retry : Retrier,
+    frame_counters : FrameCounters,
 }
 
 impl<C,P> RawClient<C,P>
@@ -22,63 +29,196 @@ where C : Clone + Send + Sync + 'static,
       P : CutePacketTrait + Send
 {
     pub async fn new(config : NetworkConfig, context : Arc<tokio::sync::RwLock<C>>) -> Result<Self,CuteError> {
-        let client = CuteRawServiceClient::connect(config.host_address).await?;
+        let client = Self::connect(&config).await?;
+        let connection = Arc::new(Reconnector::new(config.reconnect.clone(), client));
         let protocol_name_map = std::collections::HashMap::new();
+        let retry = Retrier::new(config.retry.clone());
 
         Ok(Self {
             config,
-            client,
+            connection,
             context,
             protocol_name_map,
+            retry,
+            frame_counters: FrameCounters::default(),
         })
     }
 
-    pub async fn get_unary_data(&mut self, key: u32, parameter: Option<Vec<u8>>) -> Result<Vec<u8>, CuteError> {
-        match self.client.client_unary(key,parameter).await {
-            Ok(res) => {
-                Ok(res.get_payload())
+    /// 연결 후 인증 token 이 있으면 인증함.
+    async fn connect(config : &NetworkConfig) -> Result<CuteRawServiceClient<P>, CuteError> {
+        let tls = config.tls.as_ref().map(create_connector).transpose()?;
+        let client = CuteRawServiceClient::connect(config.host_address, tls, config.compress_type, config.compress_threshold).await?;
+        if let Some(token) = &config.auth.token {
+            client.client_auth(token.clone()).await?;
+        }
+        Ok(client)
+    }
+
+    /// 현재 연결 및 세대. 끊긴 경우 `NetworkConfig::reconnect` 에 따라 다시 연결한다.
+    async fn client(&self) -> Result<(u64, Arc<CuteRawServiceClient<P>>), CuteError> {
+        let (generation, client) = self.connection.current();
+        if !client.is_closed() || !self.connection.is_enabled() {
+            return Ok((generation, client));
+        }
+        self.connection.reconnect(generation, || Self::connect(&self.config)).await
+    }
+
+    pub fn watch_connection(&self) -> tokio::sync::broadcast::Receiver<ConnectionEvent> {
+        self.connection.subscribe()
+    }
+
+    pub fn frame_counters(&self) -> FrameCounters {
+        self.frame_counters.clone()
+    }
+
+    /// 처리하지 못한 결과는 protocol 의 backpressure 에 따라 대기하거나 버림.
+    ///
+    /// `Block` 인 경우 받은 결과는 stream 마다 쌓이며 같은 연결의 다른 요청은 대기하지 않는다.
+    fn apply_backpressure(&self, key : u32, stream : DataStream<Vec<u8>>) -> DataStream<Vec<u8>> {
+        apply_backpressure(stream,
+                           self.config.backpressure.policy(key),
+                           self.config.max_channel_size,
+                           self.frame_counters.clone(),
+                           key)
+    }
+
+    /// 연결이 끊겼으며 다시 연결하지 않는 경우.
+    pub(crate) fn is_closed(&self) -> bool {
+        !self.connection.is_enabled() && self.connection.current().1.is_closed()
+    }
+
+    pub async fn get_service_names(&mut self) -> Result<Vec<u32>, CuteError> {
+        self.client().await?.1.client_discovery().await
+    }
+
+    /// 등록된 protocol 의 설명을 받고 이름으로 찾을 수 있도록 기록함.
+    pub async fn describe_services(&mut self) -> Result<Vec<ProtocolInfo>, CuteError> {
+        let infos = self.client().await?.1.client_reflection().await?;
+        self.protocol_name_map = infos.iter()
+            .map(|info| (info.name.as_str().into(), info.protocol))
+            .collect();
+        self.retry.set_infos(&infos);
+        Ok(infos)
+    }
+
+    /// server 의 protocol 등록 변경 알림. 요청한 이후의 변경만 전달된다.
+    pub async fn watch_services(&mut self) -> Result<DataStream<ProcEvent>, CuteError> {
+        let (_, client) = self.client().await?;
+        let events = tokio_stream::wrappers::BroadcastStream::new(client.subscribe_changes()?);
+        // server 는 인증된 연결에만 알림을 보내므로 요청을 한번 보내 인증함.
+        client.client_discovery().await?;
+        // 처리가 늦어 밀린 알림은 건너뜀.
+        Ok(Box::pin(events.filter_map(|event| event.ok().map(Ok))))
+    }
+
+    /// 이름에 해당하는 protocol. 기록되지 않은 이름인 경우 server 에 다시 요청한다.
+    pub async fn get_protocol(&mut self, name : &str) -> Result<u32, CuteError> {
+        if let Some(protocol) = self.protocol_name_map.get(name) {
+            return Ok(*protocol);
+        }
+        self.describe_services().await?;
+        self.protocol_name_map.get(name).copied()
+            .ok_or_else(|| CuteError::not_found(format!("protocol \"{}\" not found", name)))
+    }
+
+    pub fn set_retry_policy(&mut self, key : u32, policy : Option<RetryPolicy>) {
+        self.retry.set_policy(key, policy);
+    }
+
+    /// protocol 의 재시도 방식. idempotent 여부를 모르는 경우 `describe_services` 를 요청한다.
+    async fn retry_policy(&mut self, key : u32) -> Option<RetryPolicy> {
+        if self.retry.needs_describe(key) {
+            if let Err(e) = self.describe_services().await {
+                warn!("describe services for retry failed : {}", e);
             }
-            Err(e) => {
-                Err(e)
+        }
+        self.retry.policy(key)
+    }
+
+    /// `timeout` 이 `None` 이면 `NetworkConfig::time_out` 을 사용. 다시 요청하는 경우 모든 시도를 포함한다.
+    pub async fn get_unary_data(&mut self, key: u32, parameter: Option<Vec<u8>>, timeout: Option<std::time::Duration>) -> Result<Vec<u8>, CuteError> {
+        let timeout = timeout.or_else(|| self.config.unary_timeout());
+        let Some(policy) = self.retry_policy(key).await else {
+            return self.client().await?.1.client_unary(key,parameter,timeout).await;
+        };
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let this = &*self;
+        this.retry.run(key, &policy, deadline, |timeout| {
+            let parameter = parameter.clone();
+            async move {
+                this.client().await?.1.client_unary(key, parameter, timeout).await
             }
+        }).await
+    }
+
+    /// 재연결을 사용하는 경우 연결이 끊기면 다시 연결한 후 같은 요청으로 stream 을 이어간다.
+    pub async fn get_stream_data(&mut self, key: u32, parameter: Option<Vec<u8>>, schedule: StreamSchedule) -> Result<DataStream<Vec<u8>>, CuteError> {
+        let (generation, client) = self.client().await?;
+        let res_stream = Self::merge_chucks(client.client_stream(key,parameter.clone(),schedule).await?);
+        if !self.connection.is_enabled() {
+            return Ok(self.apply_backpressure(key, res_stream));
         }
+
+        let connection = self.connection.clone();
+        let config = self.config.clone();
+        let current = self.connection.clone();
+        // 다른 요청이 이미 다시 연결했거나 해당 연결이 닫힌 경우만 끊긴 것으로 봄.
+        let is_lost = move |generation| {
+            let (current_generation, client) = current.current();
+            current_generation != generation || client.is_closed()
+        };
+        let res_stream = resubscribe(generation, res_stream, is_lost, move |generation| {
+            let (connection, config, parameter) = (connection.clone(), config.clone(), parameter.clone());
+            async move {
+                let (generation, client) = connection.reconnect(generation, || Self::connect(&config)).await?;
+                let res_stream = client.client_stream(key, parameter, schedule).await?;
+                Ok((generation, Self::merge_chucks(res_stream)))
+            }
+        });
+        Ok(self.apply_backpressure(key, res_stream))
+    }
+
+    pub async fn send_stream_data<S>(&mut self, key: u32, parameter: Option<Vec<u8>>, input: S) -> Result<Vec<u8>, CuteError>
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        self.client().await?.1.client_send_stream(key,parameter,input).await
+    }
+
+    pub async fn open_bidi(&mut self, key: u32, parameter: Option<Vec<u8>>) -> Result<(BidiSender, DataStream<Vec<u8>>), CuteError> {
+        let (input_tx, input_rx) = tokio::sync::mpsc::channel(self.config.max_channel_size);
+        let res_stream = self.client().await?.1.client_bidi(key,parameter,input_rx).await?;
+        Ok((BidiSender::new(input_tx), self.apply_backpressure(key, Self::merge_chucks(res_stream))))
     }
 
-    pub async fn get_stream_data(&mut self, key: u32, parameter: Option<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError> {
-
-        match self.client.client_stream(key,parameter).await {
-            Ok(mut res_stream) => {
-                Ok(Box::pin(stream! {
-                    let mut flat_vec = Vec::new();
-                    while let Some(packet) = res_stream.next().await {
-                        match packet {
-                            Ok(value) => {
-                                if value.get_chuck_idx() == 0 {
-                                    flat_vec.clear()
-                                }
-                                flat_vec.extend_from_slice(&value.get_payload());
-                                if value.get_chuck_idx() + 1 == value.get_chuck_size() {
-                                    yield Ok(flat_vec.clone())
-                                }
-                            }
-                            Err(_) => {
-                                break;
-                            }
+    /// chuck 된 packet 들을 합쳐서 하나의 결과로 반환함.
+    fn merge_chucks(mut res_stream : DataStream<Box<P>>) -> DataStream<Vec<u8>> {
+        Box::pin(stream! {
+            let mut flat_vec = Vec::new();
+            while let Some(packet) = res_stream.next().await {
+                match packet {
+                    Ok(value) => {
+                        if value.get_chuck_idx() == 0 {
+                            flat_vec.clear()
+                        }
+                        flat_vec.extend_from_slice(&value.get_payload());
+                        if value.get_chuck_idx() + 1 == value.get_chuck_size() {
+                            yield Ok(flat_vec.clone())
                         }
                     }
-                }))
-            }
-            Err(e) => {
-                Err(e)
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
             }
-        }
+        })
     }
 
     pub async fn close_stream(&mut self, key: u32) -> Result<(), CuteError> {
-        self.client.close_stream(key).await
+        self.client().await?.1.close_stream(key).await
     }
 
     pub async fn close_stream_all(&mut self) -> Result<(), CuteError> {
-        self.client.close_stream_all().await
+        self.client().await?.1.close_stream_all().await
     }
 }
\ No newline at end of file
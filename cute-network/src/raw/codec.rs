@@ -0,0 +1,181 @@
+use std::marker::PhantomData;
+use bytes::{Buf, BytesMut};
+use log::warn;
+use tokio_util::codec::{Decoder, Encoder};
+use cute_core::CuteError;
+use crate::CompressType;
+use crate::raw::{CutePacketTrait, CutePacketValid};
+
+/// # Comment
+/// `CutePacketTrait` 을 만족하는 packet 을 `tokio_util::codec` 로 읽고 쓰기 위한 Codec.
+///
+/// `FramedRead` 와 함께 사용하면 `AsyncRead` 를 `Stream<Item = Result<Box<P>, CuteError>>` 로 사용할 수 있다.
+///
+/// 유효성 검사에 실패한 경우 버퍼 전체를 버리지 않고 다음 packet 의 시작 위치를 찾아 이어서 읽는다.
+///
+/// 압축은 write 시에만 적용되며 read 는 packet 에 기록된 방식으로 해제한다.
+#[derive(Debug)]
+pub struct CutePacketCodec<P : CutePacketTrait> {
+    compress_type : CompressType,
+    compress_threshold : usize,
+    _phantom_p: PhantomData<fn() -> P>,
+}
+
+impl<P : CutePacketTrait> CutePacketCodec<P> {
+    pub fn new() -> Self {
+        Self {
+            compress_type: CompressType::None,
+            compress_threshold: 0,
+            _phantom_p: Default::default(),
+        }
+    }
+
+    /// write 하는 payload 중 `compress_threshold` 이상인 경우 `compress_type` 으로 압축함.
+    pub fn with_compression(mut self, compress_type : CompressType, compress_threshold : usize) -> Self {
+        self.compress_type = compress_type;
+        self.compress_threshold = compress_threshold;
+        self
+    }
+
+    /// `ValidFailed` 이후 버릴 길이.
+    ///
+    /// 첫 byte 는 무조건 버리며 그 이후에서 다음 packet 의 시작 위치를 찾는다.
+    fn resync_size(src : &[u8]) -> usize {
+        if let Some(pos) = P::find_packet_start(&src[1..]) {
+            return 1 + pos;
+        }
+        match P::get_drain_size() {
+            0 => src.len(),
+            drain_size => drain_size.min(src.len()),
+        }
+    }
+}
+
+impl<P : CutePacketTrait> Default for CutePacketCodec<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P : CutePacketTrait> Decoder for CutePacketCodec<P> {
+    type Item = Box<P>;
+    type Error = CuteError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if src.is_empty() {
+                return Ok(None);
+            }
+            match P::is_valid(src) {
+                CutePacketValid::ValidOK(packet_len) => {
+                    let res_packet = P::recv_create_packet(&src[0..packet_len]);
+                    src.advance(packet_len);
+                    return res_packet.map(Some);
+                }
+                CutePacketValid::DataShort => {
+                    src.reserve(P::get_header_size() + P::get_tail_size());
+                    return Ok(None);
+                }
+                CutePacketValid::ValidFailed(check) => {
+                    warn!("invalid packet, resynchronize : {}", check);
+                    let resync_size = Self::resync_size(src);
+                    src.advance(resync_size);
+                }
+            }
+        }
+    }
+}
+
+impl<P : CutePacketTrait> Encoder<Box<P>> for CutePacketCodec<P> {
+    type Error = CuteError;
+
+    /// payload 가 큰 경우 `chuck_create_packet` 을 통해 나누어 기록함.
+    fn encode(&mut self, item: Box<P>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        for chuck in P::chuck_create_packet(item.get_payload(), item.get_packet_protocol(), item.get_correlation_id(), item.get_packet_type(), self.compress_type, self.compress_threshold) {
+            dst.extend_from_slice(&chuck.serialize());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw::{CutePacket, CutePacketType};
+    use crate::raw::packet::CUTE_DELIMITER;
+    use super::*;
+
+    fn encoded(payload : &[u8], correlation_id : u32) -> BytesMut {
+        let mut dst = BytesMut::new();
+        CutePacketCodec::<CutePacket>::new()
+            .encode(CutePacket::send_create_packet(payload.to_vec(), 1, correlation_id, CutePacketType::Unary), &mut dst)
+            .unwrap();
+        dst
+    }
+
+    fn decode_all(src : &mut BytesMut) -> Vec<(u32, Vec<u8>)> {
+        let mut codec = CutePacketCodec::<CutePacket>::new();
+        let mut packets = Vec::new();
+        while let Some(packet) = codec.decode(src).unwrap() {
+            packets.push((packet.get_correlation_id(), packet.get_payload()));
+        }
+        packets
+    }
+
+    #[test]
+    fn garbage_before_packet_is_skipped() {
+        let mut src = BytesMut::from(&b"garbage"[..]);
+        // delimiter 와 일부만 같은 byte.
+        src.extend_from_slice(&CUTE_DELIMITER.to_le_bytes()[..3]);
+        src.extend_from_slice(&encoded(b"first", 1));
+        src.extend_from_slice(&encoded(b"second", 2));
+        assert_eq!(decode_all(&mut src), vec![(1, b"first".to_vec()), (2, b"second".to_vec())]);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn bad_header_is_skipped() {
+        let packet = encoded(b"valid", 3);
+
+        // version 이 다른 header.
+        let mut bad_version = encoded(b"old", 1);
+        bad_version[23] = 0;
+        // 최대 크기를 넘는 length 는 기다리지 않고 버림.
+        let mut bad_length = encoded(b"large", 2);
+        bad_length[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut src = bad_version;
+        src.extend_from_slice(&bad_length);
+        src.extend_from_slice(&packet);
+        assert_eq!(decode_all(&mut src), vec![(3, b"valid".to_vec())]);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn crc_mismatch_packet_is_dropped() {
+        let mut src = encoded(b"corrupted", 1);
+        let last = src.len() - 5;
+        src[last] ^= 0x01;
+        src.extend_from_slice(&encoded(b"valid", 2));
+        assert_eq!(decode_all(&mut src), vec![(2, b"valid".to_vec())]);
+    }
+
+    #[test]
+    fn short_data_waits_for_rest() {
+        let packet = encoded(b"split", 4);
+        let mut codec = CutePacketCodec::<CutePacket>::new();
+        let mut src = BytesMut::from(&packet[..10]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&packet[10..packet.len() - 1]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&packet[packet.len() - 1..]);
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap().get_payload(), b"split".to_vec());
+    }
+
+    #[test]
+    fn garbage_only_is_dropped() {
+        let mut src = BytesMut::from(&[0xAB; 64][..]);
+        assert!(decode_all(&mut src).is_empty());
+        // delimiter 가 없으면 쌓아두지 않음.
+        assert!(src.is_empty());
+    }
+}
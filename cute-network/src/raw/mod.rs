@@ -1,25 +1,82 @@
 #![allow(unused)]
 
 use cute_core::CuteError;
+use crate::CompressType;
 pub use self::server::CuteRawServer;
 pub use self::client::RawClient;
 pub use self::packet::CutePacket;
+pub use self::codec::CutePacketCodec;
 
 pub enum CutePacketValid {
     /// 아무 문제 없음. header + payload + tail 의 binary 길이를 반환.
     ValidOK(usize),
     /// Valid 체크를 하기에는 부족한 경우.
     DataShort,
-    /// 헤더 잘못되었을 경우.
-    ValidFailed(CuteError),
+    /// 헤더 잘못되었을 경우. 어떤 검사가 실패했는지 반환.
+    ValidFailed(CutePacketCheck),
 }
 
+/// `CutePacketValid::ValidFailed` 의 실패한 검사 항목.
+#[derive(Debug, Clone)]
+pub enum CutePacketCheck {
+    /// 시작 위치의 delimiter 가 일치하지 않음.
+    Delimiter,
+    /// 지원하지 않는 protocol version. 받은 version 을 반환.
+    Version(u8),
+    /// payload 길이가 최대 크기를 넘음. 받은 길이를 반환.
+    Length(usize),
+    /// tail 의 checksum 이 일치하지 않음.
+    Checksum { expected : u32, actual : u32 },
+    /// 그 외 구현체에서 정의한 검사.
+    Other(CuteError),
+}
+
+impl std::fmt::Display for CutePacketCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CutePacketCheck::Delimiter => write!(f, "Packet delimiter do not match."),
+            CutePacketCheck::Version(version) => write!(f, "Packet version {} is not supported.", version),
+            CutePacketCheck::Length(length) => write!(f, "Packet length {} is too long.", length),
+            CutePacketCheck::Checksum { expected, actual } => write!(f, "Packet checksum do not match. expected : {:#010x}, actual : {:#010x}", expected, actual),
+            CutePacketCheck::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<CutePacketCheck> for CuteError {
+    fn from(value: CutePacketCheck) -> Self {
+        match value {
+            CutePacketCheck::Other(e) => e,
+            check => CuteError::deserialize_invalid(check.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CutePacketType {
     Empty = 0,
     Unary = 1,
     Streaming = 2,
     StreamClose = 3,
     StreamAllClose = 4,
+    /// Server 에서 요청 처리 중 실패한 경우. payload 에는 `CuteErrorCode` 와 message 가 기록됨.
+    Error = 5,
+    /// 연결 후 Client 가 인증 token 을 보내는 경우 사용. Server 는 인증 성공시 빈 payload 로 응답한다.
+    Auth = 6,
+    /// client streaming 을 시작. payload 는 `BidiTask` 생성 input 이며 응답은 `Unary` packet 으로 받는다.
+    ClientStream = 7,
+    /// bidi streaming 을 시작. payload 는 `BidiTask` 생성 input 이며 결과는 `Streaming` packet 으로 받는다.
+    Bidi = 8,
+    /// client streaming 및 bidi streaming 의 입력. 시작한 packet 의 correlation id 를 사용한다.
+    StreamInput = 9,
+    /// client streaming 및 bidi streaming 의 입력 종료.
+    StreamInputClose = 10,
+    /// 등록된 protocol 의 `ProtocolInfo` 를 요청. 응답 payload 는 `bin_serialize` 된 `Vec<ProtocolInfo>` 이다.
+    Reflection = 11,
+    /// 등록된 protocol 목록을 요청. 응답 payload 는 protocol (u32, little endian) 의 나열이다.
+    Discovery = 12,
+    /// server 의 protocol 등록 변경 알림. payload 는 `ProcEvent::kind` (u8) 이며 correlation id 는 0 이다.
+    ServiceChanged = 13,
 }
 
 pub trait CutePacketTrait : Send + Sync + 'static {
@@ -27,7 +84,7 @@ pub trait CutePacketTrait : Send + Sync + 'static {
     fn get_header_size() -> usize;
     /// 해당 packet 의 tail size.
     fn get_tail_size() -> usize;
-    /// `CutePacketValid::ValidFailed(err)` 이 나오는 경우 어떻게 처리를 해줘야 하냐?
+    /// `CutePacketValid::ValidFailed(check)` 이 나오는 경우 어떻게 처리를 해줘야 하냐?
     ///
     /// 0 인 경우 clear 시킴.
     ///
@@ -38,12 +95,26 @@ pub trait CutePacketTrait : Send + Sync + 'static {
     /// 1. header 체크.
     /// 2. 데이터 가져옴.
     /// 3. tail 확인.
-    fn is_valid(store_data : &Vec<u8>) -> CutePacketValid;
+    fn is_valid(store_data : &[u8]) -> CutePacketValid;
+    /// virtual 함수임.
+    ///
+    /// `ValidFailed` 가 나온 경우 다음 packet 이 시작되는 위치를 찾음. delimiter 등을 사용하는 경우 구현.
+    ///
+    /// `None` 인 경우 `get_drain_size` 만큼 버린다.
+    fn find_packet_start(store_data : &[u8]) -> Option<usize> {
+        None
+    }
     /// read 의 binary 데이터를 통해 packet 을 생성함.
-    fn recv_create_packet(store_data : &[u8]) -> Box<Self>;
+    ///
+    /// 압축된 payload 는 여기서 해제하며 실패한 경우 error 를 반환한다.
+    fn recv_create_packet(store_data : &[u8]) -> Result<Box<Self>, CuteError>;
     /// chuck 된 packet 들을 만들어냄
-    fn chuck_create_packet(write_data : Vec<u8>, protocol : u32, protocol_type : CutePacketType) -> Vec<Box<Self>>;
-    fn send_create_packet(write_data : Vec<u8>, protocol : u32, protocol_type : CutePacketType) -> Box<Self>;
+    ///
+    /// `correlation_id` 는 요청과 응답을 짝지어 주기 위한 값으로 chuck 된 모든 packet 에 동일하게 기록된다.
+    ///
+    /// `compress_threshold` 이상인 chuck 은 `compress_type` 으로 압축하며 압축 결과가 더 큰 경우 그대로 보낸다.
+    fn chuck_create_packet(write_data : Vec<u8>, protocol : u32, correlation_id : u32, protocol_type : CutePacketType, compress_type : CompressType, compress_threshold : usize) -> Vec<Box<Self>>;
+    fn send_create_packet(write_data : Vec<u8>, protocol : u32, correlation_id : u32, protocol_type : CutePacketType) -> Box<Self>;
 
     /// virtual 함수임.
     ///
@@ -52,12 +123,26 @@ pub trait CutePacketTrait : Send + Sync + 'static {
     /// 만약 error 를 반환하고 싶지 않은 경우 해당 함수는 무조건 None 으로 반환시킴.
     ///
     /// 기본은 반환하지 않음.
-    fn error_create_packet(err : CuteError) -> Option<Box<Self>> {
+    fn error_create_packet(err : CuteError, protocol : u32, correlation_id : u32) -> Option<Box<Self>> {
+        None
+    }
+
+    /// virtual 함수임.
+    ///
+    /// `CutePacketType::Error` packet 을 받은 경우 payload 를 `CuteError` 로 변환함.
+    ///
+    /// `error_create_packet` 를 구현하지 않았다면 구현할 필요 없음.
+    fn get_packet_error(&self) -> Option<CuteError> {
         None
     }
 
     fn get_packet_protocol(&self) -> u32;
 
+    /// 요청 packet 에 기록된 correlation id.
+    ///
+    /// Server 는 응답시 요청에서 받은 값을 그대로 돌려주며 Client 는 해당 값으로 대기중인 요청을 찾는다.
+    fn get_correlation_id(&self) -> u32;
+
     fn get_packet_type(&self) -> CutePacketType;
     /// chuck 된 요소를 이용하는 경우 사용.
     ///
@@ -77,4 +162,5 @@ pub trait CutePacketTrait : Send + Sync + 'static {
 mod client;
 mod server;
 mod packet;
+mod codec;
 mod stub;
\ No newline at end of file
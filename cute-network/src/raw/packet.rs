@@ -1,11 +1,24 @@
 /// 해당 문서는 `CutePacketTrait` 을 사용.
 
 use std::fmt::Debug;
-use cute_core::CuteError;
-use crate::raw::{CutePacketTrait, CutePacketType, CutePacketValid};
+use cute_core::{CuteError, CuteErrorCode};
+use crate::CompressType;
+use crate::raw::{CutePacketCheck, CutePacketTrait, CutePacketType, CutePacketValid};
 
 pub const CUTE_DELIMITER : u32 = 0x12345678;
-pub const HEADER_SIZE: usize = 24;
+/// header 구성 및 tail 계산 방식이 바뀌는 경우 올림. 다른 version 의 packet 은 `ValidFailed` 로 처리한다.
+///
+/// 0 : header 의 합을 tail 로 사용하던 version.
+///
+/// 1 : CRC32C tail. stream 요청에 schedule 이 없던 version.
+///
+/// 2 : unary 요청에 제한 시간이 없던 version.
+///
+/// 3 : `Reflection` 응답의 `ProtocolInfo` 에 idempotent 가 없던 version.
+///
+/// 4 : `Error` packet 에 retry after 가 없던 version.
+pub const CUTE_PROTOCOL_VERSION : u8 = 5;
+pub const HEADER_SIZE: usize = 28;
 pub const TAIL_SIZE: usize = 4;
 pub const MAX_PAYLOAD_SIZE: usize = 65536 - HEADER_SIZE- TAIL_SIZE;
 
@@ -13,13 +26,116 @@ pub const MAX_PAYLOAD_SIZE: usize = 65536 - HEADER_SIZE- TAIL_SIZE;
 pub struct CutePacketHeader {
     delimiter : u32,
     protocol : u32,
+    correlation_id : u32,
+    /// 압축 전 payload 길이.
     length : u32,
+    /// 압축된 payload 길이. 압축하지 않은 경우 0.
     compress_length : u32,
-    protocol_type : u32,
+    protocol_type : u16,
+    compress_type : u8,
+    version : u8,
     idx: u16,
     count : u16,
 }
 
+impl CutePacketHeader {
+    /// binary 데이터에서 header 를 읽음. `HEADER_SIZE` 이상인지는 호출하는 쪽에서 확인해야 함.
+    fn from_bytes(store_data : &[u8]) -> Self {
+        let mut u32_bytes = [0u8; 4];
+        let mut u16_bytes = [0u8; 2];
+
+        u32_bytes.copy_from_slice(&store_data[0.. 4]);
+        let delimiter = u32::from_le_bytes(u32_bytes);
+        u32_bytes.copy_from_slice(&store_data[4..8]);
+        let protocol = u32::from_le_bytes(u32_bytes);
+        u32_bytes.copy_from_slice(&store_data[8..12]);
+        let correlation_id = u32::from_le_bytes(u32_bytes);
+        u32_bytes.copy_from_slice(&store_data[12..16]);
+        let length = u32::from_le_bytes(u32_bytes);
+        u32_bytes.copy_from_slice(&store_data[16..20]);
+        let compress_length = u32::from_le_bytes(u32_bytes);
+        u16_bytes.copy_from_slice(&store_data[20..22]);
+        let protocol_type = u16::from_le_bytes(u16_bytes);
+        let compress_type = store_data[22];
+        let version = store_data[23];
+        u16_bytes.copy_from_slice(&store_data[24..26]);
+        let idx = u16::from_le_bytes(u16_bytes);
+        u16_bytes.copy_from_slice(&store_data[26..28]);
+        let count = u16::from_le_bytes(u16_bytes);
+
+        Self {
+            delimiter,
+            protocol,
+            correlation_id,
+            length,
+            compress_length,
+            protocol_type,
+            compress_type,
+            version,
+            idx,
+            count,
+        }
+    }
+
+    /// 실제로 전송되는 payload 의 길이.
+    fn wire_length(&self) -> usize {
+        if self.compress_type == CompressType::None as u8 {
+            self.length as usize
+        } else {
+            self.compress_length as usize
+        }
+    }
+
+    fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut output = [0u8; HEADER_SIZE];
+        output[0..4].copy_from_slice(self.delimiter.to_le_bytes().as_ref());
+        output[4..8].copy_from_slice(self.protocol.to_le_bytes().as_ref());
+        output[8..12].copy_from_slice(self.correlation_id.to_le_bytes().as_ref());
+        output[12..16].copy_from_slice(self.length.to_le_bytes().as_ref());
+        output[16..20].copy_from_slice(self.compress_length.to_le_bytes().as_ref());
+        output[20..22].copy_from_slice(self.protocol_type.to_le_bytes().as_ref());
+        output[22] = self.compress_type;
+        output[23] = self.version;
+        output[24..26].copy_from_slice(self.idx.to_le_bytes().as_ref());
+        output[26..28].copy_from_slice(self.count.to_le_bytes().as_ref());
+        output
+    }
+}
+
+/// header 및 전송되는 payload 전체에 대한 CRC32C. tail 로 사용한다.
+fn checksum(header : &[u8], payload : &[u8]) -> u32 {
+    crc32c::crc32c_append(crc32c::crc32c(header), payload)
+}
+
+/// `compress_type` 으로 압축함. 압축 결과가 원본보다 작지 않으면 None.
+fn compress(data : &[u8], compress_type : CompressType) -> Option<Vec<u8>> {
+    let compressed = match compress_type {
+        CompressType::None => return None,
+        CompressType::Lz4 => lz4_flex::block::compress(data),
+        CompressType::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL).ok()?,
+    };
+    if compressed.len() < data.len() {
+        Some(compressed)
+    } else {
+        None
+    }
+}
+
+/// header 에 기록된 방식으로 압축을 해제함. 해제된 길이가 `length` 와 다르면 실패.
+fn decompress(data : &[u8], header : &CutePacketHeader) -> Result<Vec<u8>, CuteError> {
+    let length = header.length as usize;
+    let decompressed = match header.compress_type {
+        0 => return Ok(data.to_vec()),
+        1 => lz4_flex::block::decompress(data, length).map_err(|e| CuteError::deserialize_invalid(format!("lz4 decompress failed : {}", e)))?,
+        2 => zstd::bulk::decompress(data, length).map_err(|e| CuteError::deserialize_invalid(format!("zstd decompress failed : {}", e)))?,
+        unknown => return Err(CuteError::deserialize_invalid(format!("Unknown compress type : {}", unknown))),
+    };
+    if decompressed.len() != length {
+        return Err(CuteError::deserialize_invalid("Decompressed length do not match."));
+    }
+    Ok(decompressed)
+}
+
 #[derive(Debug)]
 pub struct CutePacket {
     header : CutePacketHeader,
@@ -49,40 +165,30 @@ impl CutePacketTrait for CutePacket {
         0
     }
 
-    fn is_valid(store_data: &Vec<u8>) -> CutePacketValid {
+    fn is_valid(store_data: &[u8]) -> CutePacketValid {
         if store_data.len() < HEADER_SIZE {
             CutePacketValid::DataShort
         } else {
-            let mut u32_bytes = [0u8; 4];
-            let mut u16_bytes = [0u8; 2];
-
-            u32_bytes.copy_from_slice(&store_data[0.. 4]);
-            let data_delimiter = u32::from_le_bytes(u32_bytes);
-            u32_bytes.copy_from_slice(&store_data[4..8]);
-            let data_protocol = u32::from_le_bytes(u32_bytes);
-            u32_bytes.copy_from_slice(&store_data[8..12]);
-            let data_len = u32::from_le_bytes(u32_bytes);
-            u32_bytes.copy_from_slice(&store_data[12..16]);
-            let data_comp_len = u32::from_le_bytes(u32_bytes);
-            u32_bytes.copy_from_slice(&store_data[16..20]);
-            let proc_type = u32::from_le_bytes(u32_bytes);
-            u16_bytes.copy_from_slice(&store_data[20..22]);
-            let idx = u16::from_le_bytes(u16_bytes);
-            u16_bytes.copy_from_slice(&store_data[22..24]);
-            let count = u16::from_le_bytes(u16_bytes);
-
-            if HEADER_SIZE + TAIL_SIZE + data_len as usize <= store_data.len() {
-                u32_bytes.copy_from_slice(&store_data[HEADER_SIZE + data_len as usize..HEADER_SIZE + TAIL_SIZE + data_len as usize]);
+            let header = CutePacketHeader::from_bytes(store_data);
+            let wire_len = header.wire_length();
+
+            // 잘못된 header 의 length 를 기다리지 않도록 delimiter 및 length 는 먼저 확인함.
+            if header.delimiter != CUTE_DELIMITER {
+                CutePacketValid::ValidFailed(CutePacketCheck::Delimiter)
+            } else if header.version != CUTE_PROTOCOL_VERSION {
+                CutePacketValid::ValidFailed(CutePacketCheck::Version(header.version))
+            } else if header.length as usize > MAX_PAYLOAD_SIZE || wire_len > MAX_PAYLOAD_SIZE {
+                CutePacketValid::ValidFailed(CutePacketCheck::Length(wire_len.max(header.length as usize)))
+            } else if HEADER_SIZE + TAIL_SIZE + wire_len <= store_data.len() {
+                let mut u32_bytes = [0u8; 4];
+                u32_bytes.copy_from_slice(&store_data[HEADER_SIZE + wire_len..HEADER_SIZE + TAIL_SIZE + wire_len]);
                 let tail = u32::from_le_bytes(u32_bytes);
+                let expected = checksum(&store_data[0..HEADER_SIZE], &store_data[HEADER_SIZE..HEADER_SIZE + wire_len]);
 
-                if data_delimiter != CUTE_DELIMITER {
-                    CutePacketValid::ValidFailed(CuteError::internal("Packet delimiter do not match."))
+                if tail == expected {
+                    CutePacketValid::ValidOK(HEADER_SIZE + wire_len + TAIL_SIZE)
                 } else {
-                    if tail == data_delimiter + data_protocol + data_len + data_comp_len + proc_type + idx as u32 + count as u32 {
-                        CutePacketValid::ValidOK(HEADER_SIZE + data_len as usize + TAIL_SIZE)
-                    } else {
-                        CutePacketValid::ValidFailed(CuteError::internal("Packet valid failed."))
-                    }
+                    CutePacketValid::ValidFailed(CutePacketCheck::Checksum { expected, actual: tail })
                 }
             } else {
                 CutePacketValid::DataShort
@@ -90,101 +196,129 @@ impl CutePacketTrait for CutePacket {
         }
     }
 
-    fn recv_create_packet(store_data: &[u8]) -> Box<Self> {
-        let mut u32_bytes = [0u8; 4];
-        let mut u16_bytes = [0u8; 2];
+    fn find_packet_start(store_data: &[u8]) -> Option<usize> {
+        let delimiter = CUTE_DELIMITER.to_le_bytes();
+        store_data.windows(delimiter.len()).position(|window| window == delimiter)
+    }
 
-        u32_bytes.copy_from_slice(&store_data[0.. 4]);
-        let data_delimiter = u32::from_le_bytes(u32_bytes);
-        u32_bytes.copy_from_slice(&store_data[4..8]);
-        let data_protocol = u32::from_le_bytes(u32_bytes);
-        u32_bytes.copy_from_slice(&store_data[8..12]);
-        let data_len = u32::from_le_bytes(u32_bytes);
-        u32_bytes.copy_from_slice(&store_data[12..16]);
-        let data_comp_len = u32::from_le_bytes(u32_bytes);
-        u32_bytes.copy_from_slice(&store_data[16..20]);
-        let proc_type = u32::from_le_bytes(u32_bytes);
-        u16_bytes.copy_from_slice(&store_data[20..22]);
-        let idx = u16::from_le_bytes(u16_bytes);
-        u16_bytes.copy_from_slice(&store_data[22..24]);
-        let count = u16::from_le_bytes(u16_bytes);
+    fn recv_create_packet(store_data: &[u8]) -> Result<Box<Self>, CuteError> {
+        let mut header = CutePacketHeader::from_bytes(store_data);
+        let wire_len = header.wire_length();
 
-        u32_bytes.copy_from_slice(&store_data[HEADER_SIZE + data_len as usize..HEADER_SIZE + TAIL_SIZE + data_len as usize]);
-        let tail = u32::from_le_bytes(u32_bytes);
+        let payload = decompress(&store_data[HEADER_SIZE..HEADER_SIZE + wire_len], &header)?;
 
-        Box::new(Self {
-            header: CutePacketHeader {
-                delimiter: data_delimiter,
-                protocol: data_protocol,
-                length: data_len,
-                compress_length: data_comp_len,
-                protocol_type: proc_type,
-                idx,
-                count,
-            },
-            payload: store_data[HEADER_SIZE..HEADER_SIZE + data_len as usize].to_vec(),
+        // 해제된 packet 은 압축되지 않은 packet 과 동일하게 취급하므로 tail 도 다시 계산함.
+        header.compress_length = 0;
+        header.compress_type = CompressType::None as u8;
+        let tail = checksum(&header.to_bytes(), &payload);
+        Ok(Box::new(Self {
+            header,
+            payload,
             tail,
-        })
+        }))
     }
 
-    fn chuck_create_packet(write_data: Vec<u8>, protocol: u32, protocol_type: CutePacketType) -> Vec<Box<Self>> {
+    fn chuck_create_packet(write_data: Vec<u8>, protocol: u32, correlation_id: u32, protocol_type: CutePacketType, compress_type: CompressType, compress_threshold: usize) -> Vec<Box<Self>> {
         let chuck_size = (write_data.len() / MAX_PAYLOAD_SIZE) + (write_data.len() % MAX_PAYLOAD_SIZE != 0) as usize;
         let mut result = vec![];
-        let proc_type = protocol_type as u32;
-
-        if write_data.len() > MAX_PAYLOAD_SIZE {
-            for (idx, item) in write_data.chunks(MAX_PAYLOAD_SIZE).enumerate() {
-                result.push(Box::new(Self {
-                    header: CutePacketHeader {
-                        delimiter: CUTE_DELIMITER,
-                        protocol,
-                        length: item.len() as u32,
-                        compress_length: 0,
-                        protocol_type: proc_type,
-                        idx: idx as u16,
-                        count: chuck_size as u16,
-                    },
-                    payload: item.to_vec(),
-                    tail: CUTE_DELIMITER + protocol + item.len() as u32 + 0 + proc_type + idx as u32 + chuck_size as u32,
-                }));
-            }
-        } else {
-            let write_len = write_data.len();
+        let proc_type = protocol_type as u16;
+
+        // 빈 payload 도 packet 하나는 보내야 하므로 chunks 를 사용하지 않음.
+        if write_data.is_empty() {
+            result.push(Self::send_create_packet(write_data, protocol, correlation_id, protocol_type));
+            return result;
+        }
+
+        for (idx, item) in write_data.chunks(MAX_PAYLOAD_SIZE).enumerate() {
+            let opt_compressed = if item.len() >= compress_threshold {
+                compress(item, compress_type)
+            } else {
+                None
+            };
+            let (payload, compress_length, packet_compress_type) = match opt_compressed {
+                Some(compressed) => {
+                    let compress_length = compressed.len() as u32;
+                    (compressed, compress_length, compress_type)
+                }
+                None => (item.to_vec(), 0, CompressType::None),
+            };
+            let header = CutePacketHeader {
+                delimiter: CUTE_DELIMITER,
+                protocol,
+                correlation_id,
+                length: item.len() as u32,
+                compress_length,
+                protocol_type: proc_type,
+                compress_type: packet_compress_type as u8,
+                version: CUTE_PROTOCOL_VERSION,
+                idx: idx as u16,
+                count: chuck_size as u16,
+            };
+            let tail = checksum(&header.to_bytes(), &payload);
             result.push(Box::new(Self {
-                header: CutePacketHeader {
-                    delimiter: CUTE_DELIMITER,
-                    protocol: protocol,
-                    length: write_len as u32,
-                    compress_length: 0,
-                    protocol_type: proc_type,
-                    idx: 0,
-                    count: 1,
-                },
-                payload: write_data,
-                tail:CUTE_DELIMITER + protocol + write_len as u32 + 0 + proc_type + 0 + 1,
+                header,
+                payload,
+                tail,
             }));
         }
         result
     }
 
-    fn send_create_packet(write_data: Vec<u8>, protocol: u32, protocol_type: CutePacketType) -> Box<Self> {
-        let write_len = write_data.len();
-        let proc_type = protocol_type as u32;
+    fn send_create_packet(write_data: Vec<u8>, protocol: u32, correlation_id: u32, protocol_type: CutePacketType) -> Box<Self> {
+        let header = CutePacketHeader {
+            delimiter: CUTE_DELIMITER,
+            protocol,
+            correlation_id,
+            length: write_data.len() as u32,
+            compress_length: 0,
+            protocol_type: protocol_type as u16,
+            compress_type: CompressType::None as u8,
+            version: CUTE_PROTOCOL_VERSION,
+            idx: 0,
+            count: 1,
+        };
+        let tail = checksum(&header.to_bytes(), &write_data);
         Box::new(Self {
-            header: CutePacketHeader {
-                delimiter: CUTE_DELIMITER,
-                protocol,
-                length: write_len as u32,
-                compress_length: 0,
-                protocol_type: proc_type,
-                idx: 0,
-                count: 1,
-            },
+            header,
             payload: write_data,
-            tail: CUTE_DELIMITER + protocol + write_len as u32 + 0 + proc_type + 0 + 1,
+            tail,
         })
     }
 
+    /// payload 는 `CuteErrorCode` (u32 little endian) + retry after (milli second, u32 little endian. 0 은 없음) + utf8 message 로 구성.
+    fn error_create_packet(err: CuteError, protocol: u32, correlation_id: u32) -> Option<Box<Self>> {
+        let retry_after = err.retry_after
+            .map(|retry_after| u32::try_from(retry_after.as_millis()).unwrap_or(u32::MAX).max(1))
+            .unwrap_or(0);
+        let mut write_data = (err.code as u32).to_le_bytes().to_vec();
+        write_data.extend_from_slice(&retry_after.to_le_bytes());
+        write_data.extend_from_slice(err.message.as_bytes());
+        write_data.truncate(MAX_PAYLOAD_SIZE);
+
+        Some(Self::send_create_packet(write_data, protocol, correlation_id, CutePacketType::Error))
+    }
+
+    fn get_packet_error(&self) -> Option<CuteError> {
+        if self.get_packet_type() != CutePacketType::Error {
+            return None;
+        }
+        if self.payload.len() < 8 {
+            return Some(CuteError::deserialize_invalid("Error packet payload is too short."));
+        }
+        let mut u32_bytes = [0u8; 4];
+        u32_bytes.copy_from_slice(&self.payload[0..4]);
+        let code = u32::from_le_bytes(u32_bytes);
+        u32_bytes.copy_from_slice(&self.payload[4..8]);
+        let retry_after = u32::from_le_bytes(u32_bytes);
+        let message = String::from_utf8_lossy(&self.payload[8..]).to_string();
+
+        match CuteErrorCode::try_from(code) {
+            Ok(code) if retry_after != 0 => Some(CuteError::new(code, message).with_retry_after(std::time::Duration::from_millis(retry_after as u64))),
+            Ok(code) => Some(CuteError::new(code, message)),
+            Err(e) => Some(e),
+        }
+    }
+
     fn get_packet_type(&self) -> CutePacketType {
         match self.header.protocol_type {
             1 => {
@@ -199,6 +333,33 @@ impl CutePacketTrait for CutePacket {
             4 => {
                 CutePacketType::StreamAllClose
             },
+            5 => {
+                CutePacketType::Error
+            },
+            6 => {
+                CutePacketType::Auth
+            },
+            7 => {
+                CutePacketType::ClientStream
+            },
+            8 => {
+                CutePacketType::Bidi
+            },
+            9 => {
+                CutePacketType::StreamInput
+            },
+            10 => {
+                CutePacketType::StreamInputClose
+            },
+            11 => {
+                CutePacketType::Reflection
+            },
+            12 => {
+                CutePacketType::Discovery
+            },
+            13 => {
+                CutePacketType::ServiceChanged
+            },
             _ => {
                 CutePacketType::Empty
             }
@@ -208,6 +369,11 @@ impl CutePacketTrait for CutePacket {
     fn get_packet_protocol(&self) -> u32 {
         self.header.protocol
     }
+
+    fn get_correlation_id(&self) -> u32 {
+        self.header.correlation_id
+    }
+
     fn get_chuck_idx(&self) -> usize {
         self.header.idx as usize
     }
@@ -224,16 +390,68 @@ impl CutePacketTrait for CutePacket {
         let mut create_output = [0u8; HEADER_SIZE + MAX_PAYLOAD_SIZE + TAIL_SIZE];
 
         let payload_len = self.payload.len();
-        create_output[0..4].copy_from_slice(self.header.delimiter.to_le_bytes().as_ref());
-        create_output[4..8].copy_from_slice(self.header.protocol.to_le_bytes().as_ref());
-        create_output[8..12].copy_from_slice(self.header.length.to_le_bytes().as_ref());
-        create_output[12..16].copy_from_slice(self.header.compress_length.to_le_bytes().as_ref());
-        create_output[16..20].copy_from_slice(self.header.protocol_type.to_le_bytes().as_ref());
-        create_output[20..22].copy_from_slice(self.header.idx.to_le_bytes().as_ref());
-        create_output[22..24].copy_from_slice(self.header.count.to_le_bytes().as_ref());
+        create_output[0..HEADER_SIZE].copy_from_slice(self.header.to_bytes().as_ref());
         create_output[HEADER_SIZE..HEADER_SIZE + payload_len].copy_from_slice(self.payload.as_ref());
         create_output[HEADER_SIZE + payload_len..HEADER_SIZE + payload_len + TAIL_SIZE].copy_from_slice(self.tail.to_le_bytes().as_ref());
 
         create_output[0..HEADER_SIZE + payload_len + TAIL_SIZE].to_vec()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::raw::CutePacket;
+    use super::*;
+
+    fn serialized(payload : &[u8]) -> Vec<u8> {
+        CutePacket::send_create_packet(payload.to_vec(), 1, 2, CutePacketType::Unary).serialize()
+    }
+
+    #[test]
+    fn valid_packet_passes_crc() {
+        let data = serialized(b"payload");
+        assert!(matches!(CutePacket::is_valid(&data), CutePacketValid::ValidOK(len) if len == data.len()));
+    }
+
+    #[test]
+    fn crc_mismatch_is_rejected() {
+        // payload, header 및 tail 중 어느 byte 가 바뀌어도 거부함.
+        let data = serialized(b"payload");
+        for position in [HEADER_SIZE + 3, 4, 26, data.len() - 1] {
+            let mut corrupted = data.clone();
+            corrupted[position] ^= 0x01;
+            assert!(matches!(CutePacket::is_valid(&corrupted), CutePacketValid::ValidFailed(CutePacketCheck::Checksum { expected, actual }) if expected != actual),
+                "byte {} must fail checksum", position);
+        }
+    }
+
+    #[test]
+    fn compressed_packet_crc_covers_wire_payload() {
+        let payload = vec![7u8; 4096];
+        let packets = CutePacket::chuck_create_packet(payload.clone(), 1, 2, CutePacketType::Unary, CompressType::Lz4, 0);
+        let mut data = packets[0].serialize();
+        assert!(data.len() < payload.len());
+        assert!(matches!(CutePacket::is_valid(&data), CutePacketValid::ValidOK(_)));
+        assert_eq!(CutePacket::recv_create_packet(&data).unwrap().get_payload(), payload);
+
+        data[HEADER_SIZE] ^= 0x01;
+        assert!(matches!(CutePacket::is_valid(&data), CutePacketValid::ValidFailed(CutePacketCheck::Checksum { .. })));
+    }
+
+    #[test]
+    fn error_packet_keeps_code_retry_after_and_message() {
+        let err = CuteError::resource_exhausted("protocol 3 rate limit exceeded").with_retry_after(Duration::from_millis(1500));
+        let packet = CutePacket::error_create_packet(err, 3, 7).unwrap();
+        let payload = packet.get_payload();
+        assert_eq!(payload[0..4], (CuteErrorCode::ResourceExhausted as u32).to_le_bytes());
+        assert_eq!(payload[4..8], 1500u32.to_le_bytes());
+        assert_eq!(&payload[8..], b"protocol 3 rate limit exceeded");
+
+        let err = packet.get_packet_error().unwrap();
+        assert_eq!(err.code, CuteErrorCode::ResourceExhausted);
+        assert_eq!(err.retry_after, Some(Duration::from_millis(1500)));
+        assert_eq!(err.message, "protocol 3 rate limit exceeded");
+        assert_eq!(packet.get_correlation_id(), 7);
+    }
+}
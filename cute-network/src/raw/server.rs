@@ -1,13 +1,22 @@
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use async_stream::stream;
-use tokio_stream::Stream;
-use cute_core::{CuteError, Procedure};
-use crate::NetworkConfig;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use cute_core::{CallInfo, ContextWatch, CuteError, CuteErrorCode, DataStream, Identity, ProcEvent, Procedure, ProtocolInfo, StreamSchedule, StreamTicker, TaskGuard};
+use crate::{FrameCounters, NetworkConfig};
+use crate::auth::Credential;
+use crate::backpressure::{apply_backpressure, StreamLimiter, StreamPermit};
+use crate::rate_limit::RateLimiter;
+use crate::tls::create_acceptor;
 use crate::raw::CutePacketTrait;
 use crate::raw::stub::{CuteRawService, CuteRawServiceServer};
 
+type CloseMap = Arc<tokio::sync::Mutex<std::collections::HashMap<u64, (u32, tokio::sync::watch::Sender<bool>)>>>;
+
 pub struct CuteRawServer<R, P, C, T>
 where R : AsRef<P>,
       P : Procedure<C>,
@@ -17,7 +26,15 @@ where R : AsRef<P>,
     config: NetworkConfig,
     procedure: R,
     context: Arc<tokio::sync::RwLock<C>>,
-    close_map : Arc<tokio::sync::Mutex<std::collections::HashMap<u32, tokio::sync::watch::Sender<bool>>>>,
+    /// 실행중인 stream 의 종료 signal. 여러 peer 가 같은 protocol 을 사용할 수 있으므로 stream 마다 key 를 발급함.
+    close_map : CloseMap,
+    next_stream_key : AtomicU64,
+    context_watch : ContextWatch,
+    rate_limiter : RateLimiter,
+    stream_limiter : StreamLimiter,
+    frame_counters : FrameCounters,
+    /// 중단된 요청의 destroy 를 종료시 기다리기 위해 stub 과 공유함.
+    tracker : TaskTracker,
     _phantom_p: PhantomData<fn() -> P>,
     _phantom_t : PhantomData<fn() -> T>,
 }
@@ -28,26 +45,71 @@ where R : AsRef<P> + Send + Sync + 'static,
       C : Clone + Send + Sync + 'static,
       T : CutePacketTrait + Send
 {
+    /// `shutdown` 이 cancel 되면 연결을 더 받지 않고 모든 stream 을 종료한다.
     pub async fn start(procedure : R,
                        config : NetworkConfig,
-                       ctx : Arc<tokio::sync::RwLock<C>>)-> Result<() , std::io::Error> {
+                       ctx : Arc<tokio::sync::RwLock<C>>,
+                       listener : tokio::net::TcpListener,
+                       shutdown : CancellationToken,
+                       context_watch : ContextWatch,
+                       frame_counters : FrameCounters)-> Result<() , std::io::Error> {
+        let acceptor = config.tls.as_ref().map(create_acceptor).transpose()?;
         let server = CuteRawServer::<R, P, C, T> {
-            config,
+            config : config.clone(),
             procedure,
             context : ctx,
             close_map: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+            next_stream_key: AtomicU64::new(0),
+            context_watch,
+            rate_limiter : RateLimiter::new(config.request_limit_milli_second, &config.rate_limit),
+            stream_limiter : StreamLimiter::new(&config.backpressure),
+            frame_counters,
+            tracker : TaskTracker::new(),
             _phantom_p: Default::default(),
             _phantom_t : Default::default(),
         };
 
-        match CuteRawServiceServer::new(server, config.host_address).start().await {
-            Ok(_) => {
-                Ok(())
-            }
-            Err(_) => {
-                Err(std::io::Error::new(std::io::ErrorKind::Other, ""))
-            }
+        // 등록 해제된 protocol 의 stream 은 종료 signal 을 보내 destroy 후 끝나도록 함.
+        if let Some(mut event_rx) = server.procedure.as_ref().subscribe_changes() {
+            let close_map = server.close_map.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                loop {
+                    let event = tokio::select! {
+                        _ = shutdown.cancelled() => break,
+                        event = event_rx.recv() => event,
+                    };
+                    match event {
+                        Ok(ProcEvent::Removed(protocol)) => {
+                            close_protocol_streams(&close_map, protocol).await;
+                        }
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
         }
+
+        let tracker = server.tracker.clone();
+        CuteRawServiceServer::new(server, config.host_address)
+            .task_tracker(tracker)
+            .max_channel_size(config.max_channel_size)
+            .max_message_size(config.max_message_size)
+            .max_pending_messages(config.max_pending_messages)
+            .compression(config.compress_type, config.compress_threshold)
+            .tls(acceptor)
+            .shutdown_timeout(std::time::Duration::from_secs(config.shutdown_time_out))
+            .serve(listener, shutdown).await
+            .map_err(std::io::Error::from)
+    }
+
+    /// stream 이 끝날 때까지 `permit` 을 유지하고 protocol 의 backpressure 를 적용함.
+    fn output_stream(&self, protocol : u32, permit : StreamPermit, stream : DataStream<Vec<u8>>) -> DataStream<Vec<u8>> {
+        apply_backpressure(permit.attach(stream),
+                           self.config.backpressure.policy(protocol),
+                           self.config.max_channel_size,
+                           self.frame_counters.clone(),
+                           protocol)
     }
 }
 
@@ -58,13 +120,29 @@ where R : AsRef<P> + Send + Sync + 'static,
       C : Clone + Send + Sync + 'static,
         T : CutePacketTrait + Send
 {
-    async fn server_unary(&self, protocol: u32, input: Box<[u8]>) -> Result<Vec<u8>, CuteError> {
+    async fn authenticate(&self, credential: Credential) -> Result<Option<Identity>, CuteError> {
+        self.config.auth.authenticate(&credential).await
+    }
+
+    async fn server_unary(&self, call: CallInfo, timeout: Option<std::time::Duration>, input: Box<[u8]>) -> Result<Vec<u8>, CuteError> {
         let proc_map = self.procedure.as_ref();
+        let call = call.with_context_watch(self.context_watch.clone())
+            .with_deadline(self.config.call_deadline(timeout));
+        self.config.auth.authorize(&call).await?;
+        self.rate_limiter.acquire(&call)?;
 
-        match proc_map.get_task(protocol,Some(input)).await {
-            Ok(mut task) => {
-                let opt_output = task.execute(self.context.clone()).await?;
-                match opt_output {
+        match proc_map.get_task(call.protocol,Some(input)).await {
+            Ok(task) => {
+                // 연결 해제 등으로 해당 future 가 drop 되면 guard 에서 cancel 및 destroy 를 수행함.
+                let _cancel_guard = call.cancel.clone().drop_guard();
+                let mut task = TaskGuard::with_tracker(task, self.tracker.clone());
+                // 연결이 끊긴 경우 gRPC 와 같이 실행을 중단함.
+                let result = tokio::select! {
+                    result = call.until_deadline(task.execute(self.context.clone(), &call)) => result,
+                    _ = call.cancel.cancelled() => Err(CuteError::cancelled(format!("protocol {} caller gone", call.protocol))),
+                };
+                task.destroy().await;
+                match result? {
                     None => {
                         Ok(vec![])
                     }
@@ -79,41 +157,114 @@ where R : AsRef<P> + Send + Sync + 'static,
         }
     }
 
-    async fn server_stream(&self, protocol: u32, input: Box<[u8]>) -> Result<Pin<Box<dyn Stream<Item=Result<Vec<u8>, CuteError>> + Send>>, CuteError> {
+    async fn server_stream(&self, call: CallInfo, schedule: StreamSchedule, input: Box<[u8]>) -> Result<Pin<Box<dyn Stream<Item=Result<Vec<u8>, CuteError>> + Send>>, CuteError> {
         let proc_map = self.procedure.as_ref();
+        let call = call.with_context_watch(self.context_watch.clone());
+        let protocol = call.protocol;
+        self.config.auth.authorize(&call).await?;
+        self.rate_limiter.acquire(&call)?;
+        // stub 은 연결마다 같은 protocol 의 stream 을 교체하므로 peer 별 제한에 걸린 경우에도 교체할 수 있음.
+        let replace_key = format!("{}_{}", call.peer_addr.map(|addr| addr.to_string()).unwrap_or_else(|| "unknown".to_string()), protocol);
+        let permit = self.stream_limiter.acquire_replacing(&call, replace_key)?;
         let (stop_signal,_) = tokio::sync::watch::channel(false);
-        let stop_rx = stop_signal.subscribe();
-        let mut lock_peer_map = self.close_map.lock().await;
-        if let Some(sender) = lock_peer_map.get(&protocol) {
-            let _ = sender.send(true).is_err();
+        let mut stop_rx = stop_signal.subscribe();
+        let stream_key = self.next_stream_key.fetch_add(1, Ordering::Relaxed);
+        let mut lock_close_map = self.close_map.lock().await;
+        lock_close_map.retain(|_, (_, sender)| !sender.is_closed());
+        lock_close_map.insert(stream_key, (protocol, stop_signal));
+        drop(lock_close_map);
+
+        // StreamTask 로 등록된 경우 반환된 stream 을 그대로 전달하며 schedule 은 사용하지 않음.
+        match proc_map.get_stream_task(protocol, Some(input.clone())).await {
+            Ok(task) => {
+                let ctx = self.context.clone();
+                // stream 이 끝나거나 drop 되면 cancel 됨.
+                let cancel_guard = call.cancel.clone().drop_guard();
+                let mut task = TaskGuard::with_tracker(task, self.tracker.clone());
+                return Ok(self.output_stream(protocol, permit, Box::pin(stream!{
+                    match task.execute(ctx, &call).await {
+                        Ok(mut inner_stream) => {
+                            loop {
+                                if *stop_rx.borrow() {
+                                    break;
+                                }
+                                let next = tokio::select! {
+                                    next = inner_stream.next() => next,
+                                    res = stop_rx.changed() => {
+                                        if res.is_err() {
+                                            break;
+                                        }
+                                        continue;
+                                    }
+                                };
+                                match next {
+                                    Some(Ok(output)) => {
+                                        yield Ok(output);
+                                    }
+                                    Some(Err(e)) => {
+                                        yield Err(e);
+                                        break;
+                                    }
+                                    None => {
+                                        break;
+                                    }
+                                }
+                            }
+                            drop(inner_stream);
+                        }
+                        Err(e) => {
+                            yield Err(e);
+                        }
+                    }
+                    drop(cancel_guard);
+                    task.destroy().await;
+                })));
+            }
+            Err(e) if e.code != CuteErrorCode::NotFound => {
+                return Err(e);
+            }
+            Err(_) => {}
         }
-        lock_peer_map.entry(protocol).or_insert(stop_signal);
-        drop(lock_peer_map);
 
         match proc_map.get_task(protocol,Some(input)).await {
-            Ok(mut task) => {
+            Ok(task) => {
                 let ctx = self.context.clone();
-                Ok(Box::pin(stream!{
-                    let mut is_closed = false;
+                let cancel_guard = call.cancel.clone().drop_guard();
+                let mut task = TaskGuard::with_tracker(task, self.tracker.clone());
+                let mut ticker = StreamTicker::new(schedule, &self.context_watch);
+                Ok(self.output_stream(protocol, permit, Box::pin(stream!{
                     loop {
                         if *stop_rx.borrow() {
-                            is_closed = true;
-                        }
-                        if is_closed {
                             break;
-                        } else {
-                            match task.execute(ctx.clone()).await {
-                                Ok(opt_output) => {
-                                    if let Some(output) = opt_output {
-                                        yield Ok(output)
-                                    }
+                        }
+                        tokio::select! {
+                            _ = ticker.tick() => {}
+                            res = stop_rx.changed() => {
+                                if res.is_err() {
+                                    break;
                                 }
-                                Err(_) => {}
+                                continue;
+                            }
+                        }
+                        match task.execute(ctx.clone(), &call).await {
+                            Ok(opt_output) => {
+                                let is_continue = ticker.is_continue(&opt_output);
+                                if let Some(output) = opt_output {
+                                    yield Ok(output)
+                                }
+                                if !is_continue {
+                                    break;
+                                }
+                            }
+                            Err(e) => {
+                                yield Err(e);
+                                break;
                             }
                         }
                     }
-                    yield Err(CuteError::internal("stream close"))
-                }))
+                    drop(cancel_guard);
+                    task.destroy().await;
+                })))
             }
             Err(e) => {
                 Err(e)
@@ -121,23 +272,81 @@ where R : AsRef<P> + Send + Sync + 'static,
         }
     }
 
-    async fn server_stream_close(&self, protocol: u32) -> Result<(), CuteError> {
-        let mut lock_close_map = self.close_map.lock().await;
-        if let Some(sender) = lock_close_map.remove(&protocol) {
-            let _ = sender.send(true).is_err();
-        }
-        drop(lock_close_map);
+    async fn server_bidi(&self, call: CallInfo, input: Box<[u8]>, input_stream: DataStream<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError> {
+        let proc_map = self.procedure.as_ref();
+        let call = call.with_context_watch(self.context_watch.clone());
+        self.config.auth.authorize(&call).await?;
+        self.rate_limiter.acquire(&call)?;
+        let permit = self.stream_limiter.acquire(&call)?;
+
+        let protocol = call.protocol;
+        let task = proc_map.get_bidi_task(protocol, Some(input)).await?;
+        let ctx = self.context.clone();
+        let cancel_guard = call.cancel.clone().drop_guard();
+        let mut task = TaskGuard::with_tracker(task, self.tracker.clone());
+        Ok(self.output_stream(protocol, permit, Box::pin(stream!{
+            match task.execute(ctx, &call, input_stream).await {
+                Ok(mut output_stream) => {
+                    while let Some(res) = output_stream.next().await {
+                        match res {
+                            Ok(output) => {
+                                yield Ok(output);
+                            }
+                            Err(e) => {
+                                yield Err(e);
+                                break;
+                            }
+                        }
+                    }
+                    drop(output_stream);
+                }
+                Err(e) => {
+                    yield Err(e);
+                }
+            }
+            drop(cancel_guard);
+            task.destroy().await;
+        })))
+    }
+
+    async fn server_protocols(&self) -> Result<Vec<u32>, CuteError> {
+        self.procedure.as_ref().get_service_protocols().await
+    }
+
+    async fn server_reflection(&self) -> Result<Vec<ProtocolInfo>, CuteError> {
+        self.procedure.as_ref().get_service_infos().await
+    }
 
+    async fn server_stream_close(&self, protocol: u32) -> Result<(), CuteError> {
+        close_protocol_streams(&self.close_map, protocol).await;
         Ok(())
     }
 
     async fn server_stream_all_close(&self) -> Result<(), CuteError> {
         let mut lock_close_map = self.close_map.lock().await;
-        for (_, sender) in lock_close_map.drain() {
+        for (_, (_, sender)) in lock_close_map.drain() {
             let _ = sender.send(true).is_err();
         }
         drop(lock_close_map);
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn subscribe_changes(&self) -> Option<tokio::sync::broadcast::Receiver<ProcEvent>> {
+        self.procedure.as_ref().subscribe_changes()
+    }
+}
+
+/// 해당 protocol 의 모든 stream 에 종료 signal 을 보냄.
+async fn close_protocol_streams(close_map : &CloseMap, protocol : u32) {
+    let mut lock_close_map = close_map.lock().await;
+    lock_close_map.retain(|_, (stream_protocol, sender)| {
+        if *stream_protocol == protocol {
+            let _ = sender.send(true).is_err();
+            false
+        } else {
+            true
+        }
+    });
+    drop(lock_close_map);
+}
@@ -1,228 +1,442 @@
 #![allow(unused)]
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU32, Ordering};
+use futures_util::SinkExt;
 use log::{info, warn};
-use tokio::io::AsyncWriteExt;
-use tokio::sync::mpsc::error::TryRecvError;
-use cute_core::{CuteError, DataStream};
-use crate::raw::{CutePacketTrait, CutePacketType, CutePacketValid};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{FramedRead, FramedWrite};
+use cute_core::{bin_deserialize, CuteError, DataStream, ProcEvent, ProtocolInfo, StreamSchedule};
+use crate::CompressType;
+use crate::tls::ClientTls;
+use crate::raw::{CutePacketCodec, CutePacketTrait, CutePacketType};
+use crate::raw::stub::{decode_protocols, encode_stream_request, encode_unary_request};
+
+type UnaryMap = Arc<tokio::sync::Mutex<HashMap<u32, tokio::sync::oneshot::Sender<Result<Vec<u8>, CuteError>>>>>;
+/// correlation id 마다 (protocol, bidi 여부, 수신 channel). bidi stream 은 `close_stream` 의 대상이 아님.
+type StreamMap<P> = Arc<tokio::sync::Mutex<HashMap<u32, (u32, bool, StreamTx<P>)>>>;
+/// stream 의 결과를 전달하는 task 의 입력. read task 가 대기하지 않도록 제한 없이 쌓는다.
+type StreamTx<P> = mpsc::UnboundedSender<Result<Box<P>,CuteError>>;
+/// 연결이 끊기면 read task 가 sender 를 drop 하여 `subscribe_changes` 의 stream 을 종료시킴. `None` 이면 끊긴 연결이다.
+type EventTx = Arc<std::sync::Mutex<Option<tokio::sync::broadcast::Sender<ProcEvent>>>>;
 
 #[derive(Debug)]
 pub struct CuteRawServiceClient<P : CutePacketTrait> {
-    stop_flag : Arc<tokio::sync::RwLock<bool>>,
-    send_tx : tokio::sync::mpsc::Sender<Result<Box<P>,CuteError>>,
-    unary_map : Arc<tokio::sync::Mutex<std::collections::HashMap<u32, Box<P>>>>,
-    stream_map : Arc<tokio::sync::Mutex<std::collections::HashMap<u32, tokio::sync::mpsc::Sender<Result<Box<P>,CuteError>>>>>,
+    send_tx : mpsc::Sender<Box<P>>,
+    read_handle : JoinHandle<()>,
+    next_correlation_id : AtomicU32,
+    unary_map : UnaryMap,
+    stream_map : StreamMap<P>,
+    event_tx : EventTx,
     _phantom_p: PhantomData<fn() -> P>
 }
 
 impl<P : CutePacketTrait> Drop for CuteRawServiceClient<P> {
+    /// write task 는 `send_tx` 가 drop 되면 종료되며 read task 는 여기서 종료시킨다.
     fn drop(&mut self) {
-        tokio::spawn({
-            let arc_stop_flag = self.stop_flag.clone();
-            async move {
-                let mut writer = arc_stop_flag.write().await;
-                *writer = true;
-                drop(writer);
-                drop(arc_stop_flag);
-            }
-        });
+        self.read_handle.abort();
     }
 }
 
 impl<P : CutePacketTrait> CuteRawServiceClient<P>  {
-    pub async fn connect(host_addr : SocketAddr) -> Result<Self,CuteError> {
-        let (send_tx, mut rx) = tokio::sync::mpsc::channel::<Result<Box<P>, CuteError>>(64);
-        let stop_flag = Arc::new(tokio::sync::RwLock::new(false));
-        let unary_map : Arc<tokio::sync::Mutex<std::collections::HashMap<u32, Box<P>>>>  = Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
-        let stream_map : Arc<tokio::sync::Mutex<std::collections::HashMap<u32, tokio::sync::mpsc::Sender<Result<Box<P>,CuteError>>>>> = Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
-        let mut tcp_stream = tokio::net::TcpStream::connect(host_addr).await.map_err(|e| CuteError::internal(format!("{:?}", e)))?;
-
-        tokio::spawn({
-            let arc_stop_flag = stop_flag.clone();
-            let arc_unary_map = unary_map.clone();
-            let arc_stream_map = stream_map.clone();
-            async move {
-                let local_addr = tcp_stream.local_addr().unwrap();
-                let drain_size = P::get_drain_size();
-
-                let mut read_buf = [0u8; 65536];
-                let mut store_buffer = vec![];
-                let mut delay = tokio::time::interval(Duration::from_micros(10));
-                loop {
-                    delay.tick().await;
-                    if *arc_stop_flag.read().await {
-                        break;
-                    } else {
-                        match tcp_stream.try_read(&mut read_buf) {
-                            Ok(0) => { continue; }
-                            Ok(n) => {
-                                info!("{} client read size : {}",local_addr,n);
-                                store_buffer.extend_from_slice(&read_buf[..n]);
-                                match P::is_valid(&store_buffer) {
-                                    CutePacketValid::ValidOK(payload_len) => {
-                                        let packet = P::recv_create_packet(&store_buffer[0..payload_len]);
-                                        store_buffer.drain(0..payload_len);
-
-                                        let protocol = packet.get_packet_protocol();
-                                        let protocol_type = packet.get_packet_type();
-
-                                        match protocol_type {
-                                            CutePacketType::Unary => {
-                                                let mut lock_unary_map = arc_unary_map.lock().await;
-                                                if !lock_unary_map.contains_key(&protocol) {
-                                                    let _ = lock_unary_map.insert(protocol, packet);
-                                                }
-                                                drop(lock_unary_map);
-                                            }
-                                            CutePacketType::Streaming => {
-                                                let mut lock_stream_map = arc_stream_map.lock().await;
-                                                if let Some(tx) = lock_stream_map.get_mut(&protocol) {
-                                                    if let Err(e) = tx.send(Ok(packet)).await {
-                                                        println!("error sending stream: {}", e);
-                                                    }
-                                                }
-                                                drop(lock_stream_map);
-                                            }
-                                            CutePacketType::StreamClose => {
-                                                let mut lock_stream_map = arc_stream_map.lock().await;
-                                                let _ = lock_stream_map.remove(&protocol);
-                                                drop(lock_stream_map);
-                                            }
-                                            CutePacketType::StreamAllClose => {
-                                                let mut lock_stream_map = arc_stream_map.lock().await;
-                                                let _ = lock_stream_map.clear();
-                                                drop(lock_stream_map);
-                                            }
-                                            _ => {}
-                                        }
-
-                                    }
-                                    CutePacketValid::DataShort => {
-                                        continue;
-                                    }
-                                    CutePacketValid::ValidFailed(_) => {
-                                        if drain_size > 0 {
-                                            store_buffer.drain(0..drain_size);
-                                        } else {
-                                            store_buffer.clear();
-                                        }
-                                        continue;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                if e.kind() != std::io::ErrorKind::WouldBlock {
-                                    println!("client Error reading from stream: {}", e);
-                                    break;
-                                }
-                            }
-                        }
-                        match rx.try_recv() {
-                            Ok(res) => {
-                                if let Ok(packet) = res {
-                                    let protocol = packet.get_packet_protocol();
-                                    let protocol_type = packet.get_packet_type();
-
-                                    for item in P::chuck_create_packet(packet.get_payload(), protocol, protocol_type) {
-                                        if let Err(e) = tcp_stream.write_all(&item.serialize()).await {
-                                            warn!("error sending packet: {}", e);
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                match e {
-                                    TryRecvError::Empty => {}
-                                    TryRecvError::Disconnected => {
-                                        warn!("{} disconnected channel.", host_addr);
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                drop(arc_stream_map);
-                drop(arc_unary_map);
-                drop(arc_stop_flag);
+    /// 요청 payload 는 `compress_type` 으로 압축하며 응답은 server 가 기록한 방식으로 해제한다.
+    ///
+    /// `tls` 가 있는 경우 TCP 연결 후 TLS handshake 를 수행한다.
+    pub async fn connect(host_addr : SocketAddr, tls : Option<ClientTls>, compress_type : CompressType, compress_threshold : usize) -> Result<Self,CuteError> {
+        let (send_tx, send_rx) = mpsc::channel::<Box<P>>(64);
+        let unary_map : UnaryMap = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let stream_map : StreamMap<P> = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let event_tx : EventTx = Arc::new(std::sync::Mutex::new(Some(tokio::sync::broadcast::channel(64).0)));
+        let tcp_stream = tokio::net::TcpStream::connect(host_addr).await?;
+        let write_codec = CutePacketCodec::<P>::new().with_compression(compress_type, compress_threshold);
 
-                let _ = tcp_stream.shutdown().await;
-                warn!("{} client read thread stopped!!!",host_addr);
+        let read_handle = match tls {
+            Some(tls) => {
+                let tls_stream = tls.connector.connect(tls.server_name, tcp_stream).await
+                    .map_err(|e| CuteError::unauthenticated(format!("tls handshake failed : {}", e)))?;
+                Self::spawn_io(tls_stream, write_codec, send_rx, unary_map.clone(), stream_map.clone(), event_tx.clone(), host_addr)
             }
-        });
+            None => Self::spawn_io(tcp_stream, write_codec, send_rx, unary_map.clone(), stream_map.clone(), event_tx.clone(), host_addr),
+        };
 
         Ok(Self {
-            stop_flag : stop_flag,
-            send_tx : send_tx.clone() ,
+            send_tx,
+            read_handle,
+            next_correlation_id : AtomicU32::new(1),
             unary_map,
             stream_map,
+            event_tx,
             _phantom_p: Default::default(),
         })
     }
 
-    pub async fn client_unary(&self, protocol : u32, parameter : Option<Vec<u8>>) -> Result<Box<P>,CuteError> {
-        match parameter {
-            Some(input) => {
-                let _ = self.send_tx.send(Ok(P::send_create_packet(input,protocol,CutePacketType::Unary))).await.
-                    map_err(|e| CuteError::internal(format!("{:?}", e)))?;
+    /// read / write task 를 생성하고 read task 의 handle 을 반환함.
+    fn spawn_io<S>(stream : S,
+                   write_codec : CutePacketCodec<P>,
+                   send_rx : mpsc::Receiver<Box<P>>,
+                   unary_map : UnaryMap,
+                   stream_map : StreamMap<P>,
+                   event_tx : EventTx,
+                   host_addr : SocketAddr) -> JoinHandle<()>
+    where S : AsyncRead + AsyncWrite + Send + 'static
+    {
+        let (read_half, write_half) = tokio::io::split(stream);
+        tokio::spawn(Self::write_loop(FramedWrite::new(write_half, write_codec), send_rx, host_addr));
+        tokio::spawn(Self::read_loop(FramedRead::new(read_half, CutePacketCodec::<P>::new()), unary_map, stream_map, event_tx, host_addr))
+    }
+
+    /// Server 에서 받은 packet 을 correlation id 를 통해 대기중인 unary 및 stream 으로 전달함.
+    ///
+    /// 연결이 끊기면 대기중인 요청들에 `Unavailable` 을 전달하여 종료시킨다.
+    async fn read_loop<R : AsyncRead + Unpin>(mut framed_read : FramedRead<R, CutePacketCodec<P>>, unary_map : UnaryMap, stream_map : StreamMap<P>, event_tx : EventTx, host_addr : SocketAddr) {
+        let mut chuck_map : HashMap<u32, Vec<u8>> = HashMap::new();
+
+        while let Some(res_packet) = framed_read.next().await {
+            let packet = match res_packet {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("client Error reading from stream: {}", e);
+                    break;
+                }
+            };
+            let correlation_id = packet.get_correlation_id();
+
+            match packet.get_packet_type() {
+                CutePacketType::Unary | CutePacketType::Auth | CutePacketType::Reflection | CutePacketType::Discovery => {
+                    let payload = chuck_map.entry(correlation_id).or_default();
+                    payload.extend_from_slice(&packet.get_payload());
+
+                    if packet.get_chuck_idx() + 1 == packet.get_chuck_size() {
+                        let payload = chuck_map.remove(&correlation_id).unwrap_or_default();
+                        if let Some(tx) = unary_map.lock().await.remove(&correlation_id) {
+                            let _ = tx.send(Ok(payload));
+                        }
+                    }
+                }
+                CutePacketType::Streaming => {
+                    let tx = stream_map.lock().await.get(&correlation_id).map(|(_, _, tx)| tx.clone());
+                    if let Some(tx) = tx {
+                        if let Err(e) = tx.send(Ok(packet)) {
+                            warn!("error sending stream: {}", e);
+                        }
+                    }
+                }
+                CutePacketType::Error => {
+                    let err = packet.get_packet_error().unwrap_or_else(|| CuteError::internal("unknown server error"));
+                    chuck_map.remove(&correlation_id);
+
+                    if let Some(tx) = unary_map.lock().await.remove(&correlation_id) {
+                        let _ = tx.send(Err(err.clone()));
+                    }
+                    let res_stream = stream_map.lock().await.remove(&correlation_id);
+                    if let Some((_, _, tx)) = res_stream {
+                        let _ = tx.send(Err(err));
+                    }
+                }
+                CutePacketType::StreamClose => {
+                    stream_map.lock().await.remove(&correlation_id);
+                }
+                CutePacketType::StreamAllClose => {
+                    stream_map.lock().await.retain(|_, (_, is_bidi, _)| *is_bidi);
+                }
+                CutePacketType::ServiceChanged => {
+                    let kind = packet.get_payload().first().copied().unwrap_or_default();
+                    match ProcEvent::from_parts(kind as u32, packet.get_packet_protocol()) {
+                        Ok(event) => {
+                            if let Some(tx) = event_tx.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+                                let _ = tx.send(event);
+                            }
+                        }
+                        Err(e) => {
+                            warn!("invalid service changed packet : {}", e);
+                        }
+                    }
+                }
+                CutePacketType::Empty
+                | CutePacketType::ClientStream
+                | CutePacketType::Bidi
+                | CutePacketType::StreamInput
+                | CutePacketType::StreamInputClose => {}
             }
-            None => {
-                let _ = self.send_tx.send(Ok(P::send_create_packet(vec![0,0,0,0],protocol,CutePacketType::Unary))).await.
-                    map_err(|e| CuteError::internal(format!("{:?}", e)))?;
+        }
+
+        // 이후의 요청이 map 에 기록되지 않도록 먼저 닫힘을 알림.
+        event_tx.lock().unwrap_or_else(|e| e.into_inner()).take();
+        for (_, tx) in unary_map.lock().await.drain() {
+            let _ = tx.send(Err(CuteError::unavailable(format!("{} connection closed", host_addr))));
+        }
+        for (_, (protocol, _, tx)) in stream_map.lock().await.drain() {
+            let _ = tx.send(Err(CuteError::unavailable(format!("stream {} connection closed", protocol))));
+        }
+        warn!("{} client read thread stopped!!!",host_addr);
+    }
+
+    async fn write_loop<W : AsyncWrite + Unpin>(mut framed_write : FramedWrite<W, CutePacketCodec<P>>, mut send_rx : mpsc::Receiver<Box<P>>, host_addr : SocketAddr) {
+        while let Some(packet) = send_rx.recv().await {
+            if let Err(e) = framed_write.send(packet).await {
+                warn!("error sending packet: {}", e);
+                break;
             }
         }
+        let _ = framed_write.get_mut().shutdown().await;
+        info!("{} client write thread stopped!!!", host_addr);
+    }
 
+    /// read task 가 종료되어 더 이상 응답을 받을 수 없는 경우.
+    pub fn is_closed(&self) -> bool {
+        self.event_tx.lock().unwrap_or_else(|e| e.into_inner()).is_none()
+    }
+
+    /// 요청마다 다른 correlation id 를 발급함. 0 은 사용하지 않는다.
+    fn create_correlation_id(&self) -> u32 {
         loop {
-            let mut lock_unary_map = self.unary_map.lock().await;
-            if lock_unary_map.contains_key(&protocol) {
-                return Ok(lock_unary_map.remove(&protocol).unwrap());
+            let correlation_id = self.next_correlation_id.fetch_add(1, Ordering::Relaxed);
+            if correlation_id != 0 {
+                return correlation_id;
             }
-            drop(lock_unary_map);
         }
     }
 
-    pub async fn client_stream(&self, protocol : u32, parameter : Option<Vec<u8>>) -> Result<DataStream<Box<P>>, CuteError> {
-        let lock_stream_map = self.stream_map.lock().await;
-        if lock_stream_map.contains_key(&protocol) {
-            return Err(CuteError::internal("terminate and run that stream first!!!"));
+    /// 연결이 끊겨 요청을 보내지 못한 경우.
+    fn closed_error(protocol_type : CutePacketType, protocol : u32) -> CuteError {
+        CuteError::unavailable(format!("{:?} {} connection closed", protocol_type, protocol))
+    }
+
+    /// 응답을 하나만 받는 요청. unary 및 인증에 사용한다.
+    async fn request(&self, protocol : u32, input : Vec<u8>, protocol_type : CutePacketType) -> Result<Vec<u8>,CuteError> {
+        let correlation_id = self.create_correlation_id();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut lock_unary_map = self.unary_map.lock().await;
+        lock_unary_map.insert(correlation_id, tx);
+        drop(lock_unary_map);
+
+        if self.is_closed() || self.send_tx.send(P::send_create_packet(input,protocol,correlation_id,protocol_type)).await.is_err() {
+            self.unary_map.lock().await.remove(&correlation_id);
+            return Err(Self::closed_error(protocol_type, protocol));
         }
-        drop(lock_stream_map);
 
-        let (tx,rx) = tokio::sync::mpsc::channel(64);
+        rx.await.map_err(|_| CuteError::cancelled(format!("{:?} {} response channel closed", protocol_type, protocol)))?
+    }
+
+    /// 인증 token 을 보냄. 실패한 경우 server 는 연결을 종료한다.
+    pub async fn client_auth(&self, token : String) -> Result<(),CuteError> {
+        self.request(0, token.into_bytes(), CutePacketType::Auth).await?;
+        Ok(())
+    }
+
+    /// server 의 protocol 등록 변경 알림을 받음.
+    pub fn subscribe_changes(&self) -> Result<tokio::sync::broadcast::Receiver<ProcEvent>, CuteError> {
+        self.event_tx.lock().unwrap_or_else(|e| e.into_inner()).as_ref()
+            .map(|tx| tx.subscribe())
+            .ok_or_else(|| CuteError::unavailable("connection closed"))
+    }
+
+    /// server 에 등록된 protocol 목록을 요청.
+    pub async fn client_discovery(&self) -> Result<Vec<u32>,CuteError> {
+        let output = self.request(0, vec![], CutePacketType::Discovery).await?;
+        decode_protocols(&output)
+    }
+
+    /// server 에 등록된 protocol 의 `ProtocolInfo` 를 요청.
+    pub async fn client_reflection(&self) -> Result<Vec<ProtocolInfo>,CuteError> {
+        let output = self.request(0, vec![], CutePacketType::Reflection).await?;
+        bin_deserialize(&output)
+    }
+
+    /// `timeout` 은 server 에 전달되며 지나면 `DeadlineExceeded` 를 반환한다.
+    pub async fn client_unary(&self, protocol : u32, parameter : Option<Vec<u8>>, timeout : Option<std::time::Duration>) -> Result<Vec<u8>,CuteError> {
+        let input = encode_unary_request(timeout, parameter.unwrap_or_else(|| vec![0,0,0,0]));
+        let request = self.request(protocol, input, CutePacketType::Unary);
+        match timeout {
+            None => request.await,
+            Some(timeout) => tokio::time::timeout(timeout, request).await
+                .map_err(|_| CuteError::deadline_exceeded(format!("Unary {} deadline exceeded", protocol)))?,
+        }
+    }
+
+    pub async fn client_stream(&self, protocol : u32, parameter : Option<Vec<u8>>, schedule : StreamSchedule) -> Result<DataStream<Box<P>>, CuteError> {
+        let correlation_id = self.create_correlation_id();
+        let (tx,rx) = Self::stream_channel();
+
         let mut lock_stream_map = self.stream_map.lock().await;
-        lock_stream_map.entry(protocol).or_insert(tx);
+        if lock_stream_map.values().any(|(stream_protocol, is_bidi, _)| !*is_bidi && *stream_protocol == protocol) {
+            return Err(CuteError::internal("terminate and run that stream first!!!"));
+        }
+        lock_stream_map.insert(correlation_id, (protocol, false, tx));
         drop(lock_stream_map);
 
-        match parameter {
-            Some(input) => {
-                let _ = self.send_tx.send(Ok(P::send_create_packet(input,protocol,CutePacketType::Streaming))).await.
-                    map_err(|e| CuteError::internal(format!("{:?}", e)))?;
-            }
-            None => {
-                let _ = self.send_tx.send(Ok(P::send_create_packet(vec![0,0,0,0],protocol,CutePacketType::Streaming))).await.
-                    map_err(|e| CuteError::internal(format!("{:?}", e)))?;
-            }
+        let input = encode_stream_request(schedule, parameter.unwrap_or_else(|| vec![0,0,0,0]));
+        if self.is_closed() || self.send_tx.send(P::send_create_packet(input,protocol,correlation_id,CutePacketType::Streaming)).await.is_err() {
+            self.stream_map.lock().await.remove(&correlation_id);
+            return Err(Self::closed_error(CutePacketType::Streaming, protocol));
+        }
+
+        Ok(rx)
+    }
+
+    /// client streaming. `input` 이 끝나면 입력 종료를 알리며 server 의 응답 하나를 반환한다.
+    pub async fn client_send_stream<S>(&self, protocol : u32, parameter : Option<Vec<u8>>, input : S) -> Result<Vec<u8>,CuteError>
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        let correlation_id = self.create_correlation_id();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.unary_map.lock().await.insert(correlation_id, tx);
+
+        let input_param = parameter.unwrap_or_else(|| vec![0,0,0,0]);
+        if self.is_closed() || self.send_tx.send(P::send_create_packet(input_param,protocol,correlation_id,CutePacketType::ClientStream)).await.is_err() {
+            self.unary_map.lock().await.remove(&correlation_id);
+            return Err(Self::closed_error(CutePacketType::ClientStream, protocol));
+        }
+        let forward_handle = self.forward_input(protocol, correlation_id, input);
+
+        let result = rx.await.map_err(|_| CuteError::cancelled(format!("ClientStream {} response channel closed", protocol)));
+        // 응답을 받은 후에는 입력을 더 보낼 필요가 없음.
+        forward_handle.abort();
+        result?
+    }
+
+    /// bidi streaming. `input` 으로 받은 값을 server 에 보내며 결과 stream 을 반환한다.
+    ///
+    /// `input` 의 sender 가 모두 drop 되면 입력 종료를 알린다.
+    pub async fn client_bidi(&self, protocol : u32, parameter : Option<Vec<u8>>, input : mpsc::Receiver<Vec<u8>>) -> Result<DataStream<Box<P>>, CuteError> {
+        let correlation_id = self.create_correlation_id();
+        let (tx,rx) = Self::stream_channel();
+        self.stream_map.lock().await.insert(correlation_id, (protocol, true, tx));
+
+        let input_param = parameter.unwrap_or_else(|| vec![0,0,0,0]);
+        if self.is_closed() || self.send_tx.send(P::send_create_packet(input_param,protocol,correlation_id,CutePacketType::Bidi)).await.is_err() {
+            self.stream_map.lock().await.remove(&correlation_id);
+            return Err(Self::closed_error(CutePacketType::Bidi, protocol));
         }
+        self.forward_input(protocol, correlation_id, tokio_stream::wrappers::ReceiverStream::new(input));
+
+        Ok(rx)
+    }
+
+    /// stream 마다 결과를 순서대로 전달하는 task 를 생성함.
+    ///
+    /// 받는 쪽이 처리하지 못한 결과는 task 에 쌓이므로 read task 및 같은 연결의 다른 요청은 대기하지 않는다.
+    /// 반환된 stream 이 drop 되거나 map 에서 제거되면 task 가 종료된다.
+    fn stream_channel() -> (StreamTx<P>, DataStream<Box<P>>) {
+        let (input_tx, mut input_rx) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            while let Some(item) = input_rx.recv().await {
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (input_tx, Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx)))
+    }
 
-        Ok(Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx)))
+    /// `input` 을 `StreamInput` packet 으로 보내며 끝나면 `StreamInputClose` 를 보냄.
+    fn forward_input<S>(&self, protocol : u32, correlation_id : u32, mut input : S) -> JoinHandle<()>
+    where S : tokio_stream::Stream<Item = Vec<u8>> + Send + Unpin + 'static
+    {
+        let send_tx = self.send_tx.clone();
+        tokio::spawn(async move {
+            while let Some(item) = input.next().await {
+                if send_tx.send(P::send_create_packet(item, protocol, correlation_id, CutePacketType::StreamInput)).await.is_err() {
+                    return;
+                }
+            }
+            let _ = send_tx.send(P::send_create_packet(vec![], protocol, correlation_id, CutePacketType::StreamInputClose)).await;
+        })
     }
 
     pub async fn close_stream(&self, protocol : u32) -> Result<(),CuteError> {
-        let _ = self.send_tx.send(Ok(P::send_create_packet(vec![0,0,0,0],protocol,CutePacketType::StreamClose))).await.
+        let mut lock_stream_map = self.stream_map.lock().await;
+        lock_stream_map.retain(|_, (stream_protocol, is_bidi, _)| *is_bidi || *stream_protocol != protocol);
+        drop(lock_stream_map);
+
+        self.send_tx.send(P::send_create_packet(vec![0,0,0,0],protocol,0,CutePacketType::StreamClose)).await.
             map_err(|e| CuteError::internal(format!("{:?}", e)))?;
 
         Ok(())
     }
 
     pub async fn close_stream_all(&self) -> Result<(),CuteError> {
-        let _ = self.send_tx.send(Ok(P::send_create_packet(vec![0,0,0,0],0x0FFFFFFF,CutePacketType::StreamAllClose))).await.
+        let mut lock_stream_map = self.stream_map.lock().await;
+        lock_stream_map.retain(|_, (_, is_bidi, _)| *is_bidi);
+        drop(lock_stream_map);
+
+        self.send_tx.send(P::send_create_packet(vec![0,0,0,0],0x0FFFFFFF,0,CutePacketType::StreamAllClose)).await.
             map_err(|e| CuteError::internal(format!("{:?}", e)))?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use crate::raw::CutePacket;
+    use crate::raw::packet::MAX_PAYLOAD_SIZE;
+    use super::*;
+
+    const STREAM_PROTOCOL : u32 = 100;
+
+    /// 응답의 payload. 짝수 protocol 은 나뉘어 전송되는 크기.
+    fn response(protocol : u32) -> Vec<u8> {
+        let size = if protocol % 2 == 0 { MAX_PAYLOAD_SIZE * 2 + 10 } else { 10 };
+        vec![protocol as u8; size]
+    }
+
+    /// 동시에 보낸 요청의 응답을 역순으로, 나뉜 packet 및 stream 결과와 섞어 보내도 correlation id 로 나누어 받는다.
+    #[tokio::test]
+    async fn interleaved_responses_are_demultiplexed() {
+        const REQUESTS : u32 = 8;
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let (read_half, mut write_half) = tokio::io::split(tcp_stream);
+            let mut framed_read = FramedRead::new(read_half, CutePacketCodec::<CutePacket>::new());
+            let mut stream_id = None;
+            let mut unary = Vec::new();
+            while unary.len() < REQUESTS as usize || stream_id.is_none() {
+                let packet = framed_read.next().await.unwrap().unwrap();
+                match packet.get_packet_type() {
+                    CutePacketType::Streaming => stream_id = Some(packet.get_correlation_id()),
+                    _ => unary.push((packet.get_packet_protocol(), packet.get_correlation_id())),
+                }
+            }
+            let stream_id = stream_id.unwrap();
+
+            // 응답마다 나뉜 packet 을 돌아가며 하나씩 보냄.
+            let mut queues : Vec<VecDeque<Box<CutePacket>>> = unary.iter().rev()
+                .map(|(protocol, correlation_id)| CutePacket::chuck_create_packet(response(*protocol), *protocol, *correlation_id, CutePacketType::Unary, CompressType::None, 0).into())
+                .collect();
+            let mut sequence = 0u8;
+            while queues.iter().any(|queue| !queue.is_empty()) {
+                for queue in queues.iter_mut() {
+                    if let Some(chuck) = queue.pop_front() {
+                        write_half.write_all(&chuck.serialize()).await.unwrap();
+                        let item = CutePacket::send_create_packet(vec![sequence], STREAM_PROTOCOL, stream_id, CutePacketType::Streaming);
+                        write_half.write_all(&item.serialize()).await.unwrap();
+                        sequence += 1;
+                    }
+                }
+            }
+            sequence
+        });
+
+        let client = CuteRawServiceClient::<CutePacket>::connect(addr, None, CompressType::None, 0).await.unwrap();
+        let mut stream = client.client_stream(STREAM_PROTOCOL, None, StreamSchedule::Continuous).await.unwrap();
+        let outputs = futures_util::future::join_all((0..REQUESTS).map(|protocol| client.client_unary(protocol, None, None))).await;
+        for (protocol, output) in outputs.into_iter().enumerate() {
+            assert_eq!(output.unwrap(), response(protocol as u32), "protocol {}", protocol);
+        }
+
+        let sent = server.await.unwrap();
+        for sequence in 0..sent {
+            assert_eq!(stream.next().await.unwrap().unwrap().get_payload(), vec![sequence]);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::pin::Pin;
+use std::time::Duration;
 
-use cute_core::{CuteError};
+use cute_core::{CallInfo, CuteError, DataStream, Identity, ProcEvent, ProtocolInfo, StreamSchedule};
+use crate::auth::Credential;
 use crate::raw::CutePacketTrait;
 
 pub use server::CuteRawServiceServer;
@@ -10,11 +12,87 @@ pub use client::CuteRawServiceClient;
 pub trait CuteRawService<P> : Send + Sync + 'static
 where P : CutePacketTrait + Send
 {
-    async fn server_unary(&self,protocol : u32, input: Box<[u8]>) -> Result<Vec<u8>, CuteError>;
-    async fn server_stream(&self,protocol : u32, input: Box<[u8]>) -> Result<Pin<Box<dyn tokio_stream::Stream<Item=Result<Vec<u8>, CuteError>> + Send>>, CuteError>;
+    /// 연결 후 처음 받은 packet 에서 호출됨. 인증을 사용하지 않는 경우 `Ok(None)`.
+    async fn authenticate(&self, credential : Credential) -> Result<Option<Identity>, CuteError>;
+    /// `timeout` 은 client 가 보낸 제한 시간.
+    async fn server_unary(&self, call : CallInfo, timeout : Option<Duration>, input: Box<[u8]>) -> Result<Vec<u8>, CuteError>;
+    async fn server_stream(&self, call : CallInfo, schedule : StreamSchedule, input: Box<[u8]>) -> Result<Pin<Box<dyn tokio_stream::Stream<Item=Result<Vec<u8>, CuteError>> + Send>>, CuteError>;
+    /// client streaming 및 bidi streaming. `input_stream` 은 client 의 `StreamInput` 을 순서대로 전달한다.
+    async fn server_bidi(&self, call : CallInfo, input: Box<[u8]>, input_stream : DataStream<Vec<u8>>) -> Result<DataStream<Vec<u8>>, CuteError>;
+    /// 등록된 protocol 목록.
+    async fn server_protocols(&self) -> Result<Vec<u32>, CuteError>;
+    /// 등록된 protocol 의 종류 및 설명.
+    async fn server_reflection(&self) -> Result<Vec<ProtocolInfo>, CuteError>;
     async fn server_stream_close(&self, protocol : u32) -> Result<(), CuteError>;
+    /// protocol 등록 변경 알림. 연결마다 구독하여 `ServiceChanged` packet 으로 전달한다.
+    fn subscribe_changes(&self) -> Option<tokio::sync::broadcast::Receiver<ProcEvent>>;
     async fn server_stream_all_close(&self) -> Result<(), CuteError>;
 }
 
+/// `Discovery` 응답 payload 에 protocol 목록을 기록함.
+pub(crate) fn encode_protocols(protocols : &[u32]) -> Vec<u8> {
+    protocols.iter().flat_map(|protocol| protocol.to_le_bytes()).collect()
+}
+
+/// `Discovery` 응답 payload 에서 protocol 목록을 읽음.
+pub(crate) fn decode_protocols(payload : &[u8]) -> Result<Vec<u32>, CuteError> {
+    if payload.len() % 4 != 0 {
+        return Err(CuteError::deserialize_invalid(format!("invalid discovery payload length : {}", payload.len())));
+    }
+    Ok(payload.chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+/// unary 요청 payload 앞에 붙는 제한 시간 (milli second, u64) 의 크기. 0 은 제한하지 않음.
+const UNARY_TIMEOUT_SIZE : usize = 8;
+
+/// unary 요청 payload 의 앞에 제한 시간을 기록함.
+pub(crate) fn encode_unary_request(timeout : Option<Duration>, input : Vec<u8>) -> Vec<u8> {
+    // 0 은 제한 없음으로 사용하므로 1ms 미만은 1ms 로 보냄.
+    let timeout = timeout.map(|timeout| (timeout.as_millis() as u64).max(1)).unwrap_or_default();
+    let mut output = Vec::with_capacity(UNARY_TIMEOUT_SIZE + input.len());
+    output.extend_from_slice(&timeout.to_le_bytes());
+    output.extend_from_slice(&input);
+    output
+}
+
+/// unary 요청 payload 에서 제한 시간과 Task input 을 분리함.
+pub(crate) fn decode_unary_request(payload : &[u8]) -> Result<(Option<Duration>, &[u8]), CuteError> {
+    if payload.len() < UNARY_TIMEOUT_SIZE {
+        return Err(CuteError::deserialize_invalid(format!("unary request too short : {}", payload.len())));
+    }
+    let mut timeout = [0u8; UNARY_TIMEOUT_SIZE];
+    timeout.copy_from_slice(&payload[..UNARY_TIMEOUT_SIZE]);
+    let timeout = match u64::from_le_bytes(timeout) {
+        0 => None,
+        timeout => Some(Duration::from_millis(timeout)),
+    };
+    Ok((timeout, &payload[UNARY_TIMEOUT_SIZE..]))
+}
+
+/// stream 요청 payload 앞에 붙는 schedule 의 크기. mode (u8) + value (u64).
+const STREAM_SCHEDULE_SIZE : usize = 9;
+
+/// stream 요청 payload 의 앞에 `StreamSchedule` 을 기록함.
+pub(crate) fn encode_stream_request(schedule : StreamSchedule, input : Vec<u8>) -> Vec<u8> {
+    let mut output = Vec::with_capacity(STREAM_SCHEDULE_SIZE + input.len());
+    output.push(schedule.mode() as u8);
+    output.extend_from_slice(&schedule.value().to_le_bytes());
+    output.extend_from_slice(&input);
+    output
+}
+
+/// stream 요청 payload 에서 `StreamSchedule` 과 Task input 을 분리함.
+pub(crate) fn decode_stream_request(payload : &[u8]) -> Result<(StreamSchedule, &[u8]), CuteError> {
+    if payload.len() < STREAM_SCHEDULE_SIZE {
+        return Err(CuteError::deserialize_invalid(format!("stream request too short : {}", payload.len())));
+    }
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&payload[1..STREAM_SCHEDULE_SIZE]);
+    let schedule = StreamSchedule::from_parts(payload[0] as u32, u64::from_le_bytes(value))?;
+    Ok((schedule, &payload[STREAM_SCHEDULE_SIZE..]))
+}
+
 mod server;
 mod client;
\ No newline at end of file
@@ -3,26 +3,94 @@
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
-use std::pin::Pin;
-use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use log::{info, warn};
-use tokio::io::AsyncWriteExt;
-use tokio::sync::mpsc::error::TryRecvError;
-use tokio_stream::{StreamExt, StreamMap};
-use cute_core::CuteError;
-use crate::raw::{CutePacketTrait, CutePacketType, CutePacketValid};
-use crate::raw::stub::{CuteRawService};
+use futures_util::SinkExt;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tokio_stream::StreamExt;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::codec::{FramedRead, FramedWrite};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use cute_core::{bin_serialize, CallInfo, CuteError, DataStream, ProcEvent};
+use crate::auth::Credential;
+use crate::CompressType;
+use crate::raw::{CutePacketCodec, CutePacketTrait, CutePacketType};
+use crate::raw::stub::{decode_stream_request, decode_unary_request, encode_protocols, CuteRawService};
 
 struct _Inner<T>(Arc<T>);
 
+/// 연결마다 동시에 합치는 요청의 기본 최대 수.
+const DEFAULT_MAX_PENDING_MESSAGES : usize = 16;
+
+/// 연결마다 실행중인 stream.
+#[derive(Default)]
+struct PeerStreams {
+    /// server stream. protocol 마다 하나만 실행됨.
+    server : HashMap<u32, AbortHandle>,
+    /// client streaming 및 bidi streaming. correlation id 마다 실행됨.
+    bidi : HashMap<u32, AbortHandle>,
+    /// client streaming 및 bidi streaming 의 입력 channel.
+    inputs : HashMap<u32, mpsc::Sender<Result<Vec<u8>, CuteError>>>,
+}
+
+/// 연결마다 공유하는 값.
+struct ServeContext<T> {
+    service : Arc<T>,
+    /// 연결 및 요청 task 를 추적하여 종료시 대기함.
+    tracker : TaskTracker,
+    /// 종료 요청. 연결은 더 이상 요청을 읽지 않는다.
+    shutdown : CancellationToken,
+    /// 종료 대기 시간이 지난 경우 남은 task 를 중단시킴.
+    abort : CancellationToken,
+    channel_size : usize,
+    max_message_size : usize,
+    max_pending_messages : usize,
+}
+
+impl<T> Clone for ServeContext<T> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            tracker: self.tracker.clone(),
+            shutdown: self.shutdown.clone(),
+            abort: self.abort.clone(),
+            channel_size: self.channel_size,
+            max_message_size: self.max_message_size,
+            max_pending_messages: self.max_pending_messages,
+        }
+    }
+}
+
+impl<T> ServeContext<T> {
+    /// 종료시 대기 및 중단될 수 있도록 task 를 생성함.
+    fn spawn<F>(&self, future : F) -> tokio::task::JoinHandle<()>
+    where F : std::future::Future<Output = ()> + Send + 'static
+    {
+        let abort = self.abort.clone();
+        self.tracker.spawn(async move {
+            let _ = abort.run_until_cancelled(future).await;
+        })
+    }
+}
+
 pub struct CuteRawServiceServer<P,T : CuteRawService<P>>
 where P : CutePacketTrait
 {
     inner : _Inner<T>,
     host_addr : SocketAddr,
     timeout : Option<Duration>,
+    channel_size : usize,
+    max_message_size : usize,
+    max_pending_messages : usize,
+    compress_type : CompressType,
+    compress_threshold : usize,
+    acceptor : Option<TlsAcceptor>,
+    shutdown_timeout : Duration,
+    tracker : TaskTracker,
     _phantom_p: PhantomData<fn() -> P>
 }
 
@@ -39,254 +107,584 @@ where P : CutePacketTrait
             inner,
             host_addr,
             timeout: None,
+            channel_size: 64,
+            max_message_size: 0,
+            max_pending_messages: DEFAULT_MAX_PENDING_MESSAGES,
+            compress_type: CompressType::None,
+            compress_threshold: 0,
+            acceptor: None,
+            shutdown_timeout: Duration::from_secs(10),
+            tracker: TaskTracker::new(),
             _phantom_p: Default::default(),
         }
     }
 
+    /// peer 마다 생성되는 write channel 의 크기.
+    ///
+    /// channel 이 가득 찬 경우 stream task 는 write 가 끝날때까지 대기한다.
+    pub fn max_channel_size(mut self, channel_size : usize) -> Self {
+        self.channel_size = channel_size.max(1);
+        self
+    }
+
+    /// 나뉘어 받는 요청 하나의 최대 크기 (byte). 0 은 제한하지 않음.
+    ///
+    /// 합치는 중인 요청들의 크기가 넘거나 `max_pending_messages` 보다 많으면 연결을 종료한다.
+    pub fn max_message_size(mut self, max_message_size : usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// 연결마다 동시에 합치는 요청의 최대 수. 0 은 제한하지 않음.
+    pub fn max_pending_messages(mut self, max_pending_messages : usize) -> Self {
+        self.max_pending_messages = max_pending_messages;
+        self
+    }
+
+    /// 응답 payload 압축 설정. 요청 packet 은 client 가 기록한 방식으로 해제된다.
+    pub fn compression(mut self, compress_type : CompressType, compress_threshold : usize) -> Self {
+        self.compress_type = compress_type;
+        self.compress_threshold = compress_threshold;
+        self
+    }
+
+    /// `Some` 인 경우 accept 한 연결마다 TLS handshake 를 수행하며 실패한 연결은 종료한다.
+    pub fn tls(mut self, acceptor : Option<TlsAcceptor>) -> Self {
+        self.acceptor = acceptor;
+        self
+    }
+
+    /// 종료 요청 후 처리중인 요청을 기다리는 시간. 지나면 남은 task 를 중단한다.
+    pub fn shutdown_timeout(mut self, shutdown_timeout : Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// 연결 및 요청 task 를 추적하는 tracker. 종료시 service 가 추가한 task 도 함께 기다린다.
+    pub fn task_tracker(mut self, tracker : TaskTracker) -> Self {
+        self.tracker = tracker;
+        self
+    }
+
+    /// `host_addr` 에 bind 후 종료 요청 없이 계속 실행함.
     pub async fn start(&self) -> Result<(), CuteError> {
         let listener = tokio::net::TcpListener::bind(self.host_addr)
             .await.map_err(|e| CuteError::internal(e.to_string()))?;
+        self.serve(listener, CancellationToken::new()).await
+    }
 
-        let stop_flag = Arc::new(tokio::sync::RwLock::new(false));
-        let (close_tx,mut close_rx) = tokio::sync::mpsc::channel(64);
-        let (send_tx,mut send_rx) = tokio::sync::mpsc::channel(64);
-        let peer_map: Arc<tokio::sync::Mutex<HashMap<SocketAddr, (tokio::net::TcpStream, Vec<u8>)>>> = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
-        let stream_map : Arc<tokio::sync::Mutex<StreamMap<String, Pin<Box<dyn tokio_stream::Stream<Item=Result<Vec<u8>, CuteError>> + Send>>>>> = Arc::new(tokio::sync::Mutex::new(StreamMap::new()));
-
-        //task stream execute
-        tokio::spawn({
-            let arc_stop_flag = stop_flag.clone();
-            let arc_send_tx = send_tx.clone();
-            let arc_stream_map = stream_map.clone();
-            async move {
-                let mut delay = tokio::time::interval(Duration::from_micros(10));
-                let mut is_close= false;
-                while !is_close {
-                    delay.tick().await;
-                    if *arc_stop_flag.read().await {
-                        is_close= true;
-                    } else {
-                        if let Some((key_name, res)) = arc_stream_map.lock().await.next().await {
-                            let parts: Vec<&str> = key_name.split('_').collect();
-                            let socket_addr = SocketAddr::from_str(parts[0]).unwrap();
-                            let protocol = parts[1].parse::<u32>().unwrap();
-
-                            match res {
-                                Ok(output) => {
-                                    let _ = arc_send_tx.send((socket_addr, P::send_create_packet(output, protocol, CutePacketType::Streaming))).await;
-                                }
-                                Err(err) => {
-                                    is_close = true;
-                                }
+    /// Accept 를 수행하며 연결된 peer 마다 read / write task 를 생성한다.
+    ///
+    /// peer 간에 공유하는 lock 은 없으며 read 및 write 는 데이터가 있을때만 깨어난다.
+    ///
+    /// `shutdown` 이 cancel 되면 accept 를 멈추고 모든 stream 을 종료한 후 처리중인 요청을 `shutdown_timeout` 동안 기다린다.
+    pub async fn serve(&self, listener : tokio::net::TcpListener, shutdown : CancellationToken) -> Result<(), CuteError> {
+        let serve_ctx = ServeContext {
+            service: self.inner.0.clone(),
+            tracker: self.tracker.clone(),
+            shutdown: shutdown.clone(),
+            abort: CancellationToken::new(),
+            channel_size: self.channel_size,
+            max_message_size: self.max_message_size,
+            max_pending_messages: self.max_pending_messages,
+        };
+
+        let accept_result = loop {
+            let (tcp_stream, remote_addr) = tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => break Ok(()),
+                res = listener.accept() => match res {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("accept failed: {}", e);
+                        break Err(CuteError::internal("Server Accept loop failed"));
+                    }
+                }
+            };
+            info!("{} - server connection accepted", remote_addr);
+            let write_codec = CutePacketCodec::<P>::new().with_compression(self.compress_type, self.compress_threshold);
+            let conn_ctx = serve_ctx.clone();
+
+            match self.acceptor.clone() {
+                Some(acceptor) => {
+                    // handshake 가 느린 peer 가 accept loop 를 막지 않도록 task 안에서 수행함.
+                    serve_ctx.spawn(async move {
+                        match acceptor.accept(tcp_stream).await {
+                            Ok(tls_stream) => {
+                                let peer_certificates = tls_stream.get_ref().1.peer_certificates()
+                                    .map(|certs| certs.iter().map(|cert| cert.0.clone()).collect())
+                                    .unwrap_or_default();
+                                Self::serve_connection(conn_ctx, tls_stream, peer_certificates, write_codec, remote_addr).await
                             }
+                            Err(e) => warn!("{} - tls handshake failed : {}", remote_addr, e),
+                        }
+                    });
+                }
+                None => {
+                    serve_ctx.spawn(Self::serve_connection(conn_ctx, tcp_stream, Vec::new(), write_codec, remote_addr));
+                }
+            }
+        };
+        drop(listener);
+
+        info!("raw server shutdown, closing all streams");
+        if let Err(e) = serve_ctx.service.server_stream_all_close().await {
+            warn!("raw server stream close failed : {}", e);
+        }
+        serve_ctx.tracker.close();
+        if tokio::time::timeout(self.shutdown_timeout, serve_ctx.tracker.wait()).await.is_err() {
+            warn!("raw server shutdown timed out after {:?}, abort {} tasks", self.shutdown_timeout, serve_ctx.tracker.len());
+            serve_ctx.abort.cancel();
+            serve_ctx.tracker.wait().await;
+        }
+
+        accept_result
+    }
+
+    /// 하나의 peer 에 대한 read loop.
+    ///
+    /// write 는 별도의 task 가 bounded channel 을 통해 받은 packet 을 순서대로 기록한다.
+    ///
+    /// 처음 받은 요청에서 인증을 수행하며 실패한 경우 error 를 보내고 연결을 종료한다.
+    async fn serve_connection<S>(serve_ctx : ServeContext<T>, stream : S, peer_certificates : Vec<Vec<u8>>, write_codec : CutePacketCodec<P>, remote_addr : SocketAddr)
+    where S : AsyncRead + AsyncWrite + Send + 'static
+    {
+        let service = &serve_ctx.service;
+        let (read_half, write_half) = tokio::io::split(stream);
+        let (write_tx, write_rx) = mpsc::channel::<Box<P>>(serve_ctx.channel_size);
+        let writer = tokio::spawn(Self::write_loop(FramedWrite::new(write_half, write_codec), write_rx, remote_addr));
+
+        let mut framed_read = FramedRead::new(read_half, CutePacketCodec::<P>::new());
+        let mut chuck_map : HashMap<u32, Vec<u8>> = HashMap::new();
+        // chuck_map 에 쌓인 payload 의 크기.
+        let mut pending_size = 0;
+        let mut streams = PeerStreams::default();
+        let mut session : Option<CallInfo> = None;
+        let mut event_rx = service.subscribe_changes();
+        // 연결이 끊기면 해당 연결의 요청들에 전달한 `CallInfo::cancel` 을 cancel 함.
+        let connection_cancel = CancellationToken::new();
+
+        loop {
+            let res_packet = tokio::select! {
+                _ = serve_ctx.shutdown.cancelled() => break,
+                event = recv_event(&mut event_rx) => {
+                    // 인증된 연결에만 알림.
+                    if session.is_some() {
+                        let event_packet = P::send_create_packet(vec![event.kind() as u8], event.protocol(), 0, CutePacketType::ServiceChanged);
+                        if write_tx.send(event_packet).await.is_err() {
+                            break;
                         }
                     }
+                    continue;
+                }
+                res_packet = framed_read.next() => match res_packet {
+                    Some(res_packet) => res_packet,
+                    None => break,
+                }
+            };
+            let packet = match res_packet {
+                Ok(packet) => packet,
+                Err(e) => {
+                    info!("{} - server connection closed!! error : {}", remote_addr, e);
+                    break;
                 }
-                drop(arc_send_tx);
-                drop(arc_stream_map);
+            };
+
+            let correlation_id = packet.get_correlation_id();
+            let chuck = packet.get_payload();
+            pending_size += chuck.len();
+            if let Err(err) = check_pending(&chuck_map, correlation_id, pending_size, serve_ctx.max_message_size, serve_ctx.max_pending_messages) {
+                warn!("{} - server pending message limit exceeded : {}", remote_addr, err);
+                if let Some(err_packet) = P::error_create_packet(err, packet.get_packet_protocol(), correlation_id) {
+                    let _ = write_tx.send(err_packet).await;
+                }
+                break;
             }
-        });
-
-        // tcp_read & close
-        tokio::spawn({
-            let arc_peer_map = peer_map.clone();
-            let arc_stream_map = stream_map.clone();
-            let arc_service = self.inner.0.clone();
-            let arc_send_tx = send_tx.clone();
-            let arc_close_tx = close_tx.clone();
-            let arc_stop_flag = stop_flag.clone();
-            async move {
-                let mut delay = tokio::time::interval(Duration::from_micros(10));
-                let drain_size = P::get_drain_size();
-                let mut is_close= false;
-                let mut chuck_protocol_map : HashMap<SocketAddr,HashMap<u32, Vec<Box<P>>>> = HashMap::new();
-                while !is_close {
-                    delay.tick().await;
-                    if *arc_stop_flag.read().await {
-                        is_close = true;
-                    } else {
-                        for (remote_addr, (tcp_stream, store_buffer)) in arc_peer_map.lock().await.iter_mut() {
-                            if !chuck_protocol_map.contains_key(remote_addr) {
-                                let inner_hash_map = HashMap::new();
-                                chuck_protocol_map.insert(*remote_addr, inner_hash_map);
-                            }
-                            let protocol_item = chuck_protocol_map.get_mut(remote_addr);
-                            let mut read_buf = [0u8; 65536];
+            chuck_map.entry(correlation_id).or_default().extend_from_slice(&chuck);
+            if packet.get_chuck_idx() + 1 != packet.get_chuck_size() {
+                continue;
+            }
+            let payload = chuck_map.remove(&correlation_id).unwrap_or_default();
+            pending_size -= payload.len();
 
-                            match tcp_stream.try_read(&mut read_buf) {
-                                Ok(0) => {
-                                    let _ = arc_close_tx.send(*remote_addr).await;
-                                }
-                                Ok(n) => {
-                                    info!("Server - to {} Read {} bytes",*remote_addr, n);
-                                    store_buffer.extend_from_slice(&read_buf[..n]);
-                                    match P::is_valid(&store_buffer) {
-                                        CutePacketValid::ValidOK(payload_len) => {
-                                            let packet = P::recv_create_packet(&store_buffer[0..payload_len]);
-                                            store_buffer.drain(0..payload_len);
-
-                                            let chuck_idx = packet.get_chuck_idx();
-                                            let chuck_size = packet.get_chuck_size();
-                                            let protocol = packet.get_packet_protocol();
-                                            let protocol_type = packet.get_packet_type();
-                                            let key_name = format!("{}_{}",remote_addr,protocol);
-
-                                            if let Some(packet_hash_map) = protocol_item {
-                                                if !packet_hash_map.contains_key(&protocol) {
-                                                    packet_hash_map.entry(protocol).or_insert_with(Vec::new).push(packet);
-                                                } else {
-                                                    packet_hash_map.get_mut(&protocol).unwrap().push(packet);
-                                                }
-
-                                                if chuck_size == chuck_idx + 1 {
-                                                    if let Some(inner_packets) = packet_hash_map.remove(&protocol) {
-                                                        let summation_payload: Vec<u8> = inner_packets.iter().
-                                                            flat_map(|x| x.get_payload()).
-                                                            collect();
-
-                                                        match protocol_type {
-                                                            CutePacketType::Empty => {}
-                                                            CutePacketType::Unary => {
-                                                                if let Ok(output) = arc_service.server_unary(protocol, summation_payload.into_boxed_slice()).await {
-                                                                    let _ = arc_send_tx.send((*remote_addr,P::send_create_packet(output,protocol,protocol_type))).await;
-                                                                }
-                                                            }
-                                                            CutePacketType::Streaming => {
-                                                                let mut lock_stream_map = arc_stream_map.lock().await;
-                                                                if lock_stream_map.contains_key(&key_name) {
-                                                                    let _ = lock_stream_map.remove(&key_name.clone());
-                                                                }
-                                                                if let Ok(inner_stream) = arc_service.server_stream(protocol, summation_payload.into_boxed_slice()).await {
-                                                                    let _ = lock_stream_map.insert(key_name.clone(), inner_stream);
-                                                                }
-                                                                drop(lock_stream_map);
-                                                            }
-                                                            CutePacketType::StreamClose => {
-                                                                let mut lock_stream_map = arc_stream_map.lock().await;
-                                                                if lock_stream_map.contains_key(&key_name) {
-                                                                    let _ = lock_stream_map.remove(&key_name.clone());
-                                                                }
-                                                                drop(lock_stream_map);
-                                                            }
-                                                            CutePacketType::StreamAllClose => {
-                                                                info!("{} server stream close all!!!",*remote_addr);
-                                                                let remote_addr_str= remote_addr.to_string();
-
-                                                                let mut lock_stream_map = arc_stream_map.lock().await;
-                                                                let key_to_remove: Vec<String> = lock_stream_map.keys().filter(|key| key.contains(remote_addr_str.as_str())).cloned().collect();
-                                                                for item in key_to_remove {
-                                                                    let _ = lock_stream_map.remove(&item);
-                                                                }
-                                                                drop(lock_stream_map);
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                        }
-                                        CutePacketValid::DataShort => {
-                                        }
-                                        CutePacketValid::ValidFailed(_) => {
-                                            if drain_size > 0 {
-                                                store_buffer.drain(0..drain_size);
-                                            } else {
-                                                store_buffer.clear();
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    if e.kind() != std::io::ErrorKind::WouldBlock {
-                                        let _ = arc_close_tx.send(*remote_addr).await;
-                                        info!("{} - server connection closed!! error : {}",*remote_addr,e);
+            if write_tx.is_closed() {
+                break;
+            }
+
+            // `Auth` packet 은 언제든 다시 인증할 수 있으며 그 외의 첫 요청은 token 없이 인증한다.
+            let is_auth = packet.get_packet_type() == CutePacketType::Auth;
+            if is_auth || session.is_none() {
+                let credential = Credential {
+                    peer_addr: Some(remote_addr),
+                    token: is_auth.then(|| String::from_utf8_lossy(&payload).to_string()),
+                    peer_certificates: peer_certificates.clone(),
+                };
+                match service.authenticate(credential).await {
+                    Ok(identity) => {
+                        session = Some(CallInfo::new(0, Some(remote_addr), identity));
+                    }
+                    Err(err) => {
+                        warn!("{} - server authenticate failed : {}", remote_addr, err);
+                        if let Some(err_packet) = P::error_create_packet(err, packet.get_packet_protocol(), correlation_id) {
+                            let _ = write_tx.send(err_packet).await;
+                        }
+                        break;
+                    }
+                }
+            }
+            if is_auth {
+                let _ = write_tx.send(P::send_create_packet(vec![], packet.get_packet_protocol(), correlation_id, CutePacketType::Auth)).await;
+                continue;
+            }
+
+            let mut call = session.clone().unwrap_or_default()
+                .with_cancel(connection_cancel.child_token());
+            call.protocol = packet.get_packet_protocol();
+            Self::dispatch(&serve_ctx, &write_tx, &mut streams, call, packet, payload, remote_addr).await;
+        }
+        info!("{} - server connection closed", remote_addr);
+
+        // 종료 요청인 경우 stream 은 종료 signal 및 입력 종료를 받아 스스로 끝나며 unary 는 `shutdown_time_out` 동안 기다리므로 cancel 및 중단하지 않음.
+        streams.inputs.clear();
+        if !serve_ctx.shutdown.is_cancelled() {
+            connection_cancel.cancel();
+            for (_, handle) in streams.server.drain().chain(streams.bidi.drain()) {
+                handle.abort();
+            }
+        }
+        drop(write_tx);
+        let _ = writer.await;
+    }
+
+    /// 완성된 요청 하나를 처리함.
+    ///
+    /// unary 는 요청마다 task 를 만들고, stream 은 protocol 마다 하나의 task 가 write channel 로 결과를 보낸다.
+    ///
+    /// client streaming 및 bidi streaming 은 correlation id 마다 task 를 만들며 `StreamInput` 을 입력 channel 로 전달한다.
+    async fn dispatch(serve_ctx : &ServeContext<T>,
+                      write_tx : &mpsc::Sender<Box<P>>,
+                      streams : &mut PeerStreams,
+                      call : CallInfo,
+                      packet : Box<P>,
+                      payload : Vec<u8>,
+                      remote_addr : SocketAddr) {
+        let service = &serve_ctx.service;
+        let protocol = packet.get_packet_protocol();
+        let correlation_id = packet.get_correlation_id();
+        let protocol_type = packet.get_packet_type();
+        streams.server.retain(|_, handle| !handle.is_finished());
+        streams.bidi.retain(|_, handle| !handle.is_finished());
+        streams.inputs.retain(|_, input_tx| !input_tx.is_closed());
+
+        match protocol_type {
+            CutePacketType::Empty | CutePacketType::Error | CutePacketType::Auth | CutePacketType::ServiceChanged => {}
+            CutePacketType::Unary => {
+                // 같은 protocol 의 unary 요청이 동시에 들어올 수 있으므로 요청마다 별도로 실행함.
+                serve_ctx.spawn({
+                    let service = service.clone();
+                    let write_tx = write_tx.clone();
+                    async move {
+                        let res_output = match decode_unary_request(&payload) {
+                            Ok((timeout, input)) => service.server_unary(call, timeout, input.into()).await,
+                            Err(err) => Err(err),
+                        };
+                        let res_packet = match res_output {
+                            Ok(output) => Some(P::send_create_packet(output, protocol, correlation_id, protocol_type)),
+                            Err(err) => P::error_create_packet(err, protocol, correlation_id),
+                        };
+                        if let Some(res_packet) = res_packet {
+                            let _ = write_tx.send(res_packet).await;
+                        }
+                    }
+                });
+            }
+            CutePacketType::Streaming => {
+                // 실행중인 stream 은 새 stream 이 생성된 후에 종료하여 실패한 요청이 종료시키지 않도록 함.
+                let res_stream = match decode_stream_request(&payload) {
+                    Ok((schedule, input)) => service.server_stream(call, schedule, input.into()).await,
+                    Err(err) => Err(err),
+                };
+                match res_stream {
+                    Ok(mut inner_stream) => {
+                        let write_tx = write_tx.clone();
+                        let handle = serve_ctx.spawn(async move {
+                            while let Some(res) = inner_stream.next().await {
+                                let res_packet = match res {
+                                    Ok(output) => Some(P::send_create_packet(output, protocol, correlation_id, CutePacketType::Streaming)),
+                                    Err(err) => P::error_create_packet(err, protocol, correlation_id),
+                                };
+                                if let Some(res_packet) = res_packet {
+                                    if write_tx.send(res_packet).await.is_err() {
+                                        return;
                                     }
                                 }
                             }
+                            // server 에서 stream 이 끝난 경우 client 의 stream 도 종료되도록 알림.
+                            let _ = write_tx.send(P::send_create_packet(vec![], protocol, correlation_id, CutePacketType::StreamClose)).await;
+                        });
+                        if let Some(handle) = streams.server.insert(protocol, handle.abort_handle()) {
+                            handle.abort();
                         }
-                        match close_rx.try_recv() {
-                            Ok(remote_addr) => {
-                                if let Some((mut tcp_stream,mut store_buffer)) = arc_peer_map.lock().await.remove(&remote_addr) {
-                                    store_buffer.clear();
-                                    let _ = tcp_stream.shutdown().await;
+                    }
+                    Err(err) => {
+                        if let Some(err_packet) = P::error_create_packet(err, protocol, correlation_id) {
+                            let _ = write_tx.send(err_packet).await;
+                        }
+                    }
+                }
+            }
+            CutePacketType::StreamClose => {
+                // 다른 peer 의 같은 protocol stream 은 유지되어야 하므로 해당 연결의 task 만 종료함.
+                if let Some(handle) = streams.server.remove(&protocol) {
+                    handle.abort();
+                }
+            }
+            CutePacketType::StreamAllClose => {
+                info!("{} server stream close all!!!", remote_addr);
+                for (_, handle) in streams.server.drain() {
+                    handle.abort();
+                }
+            }
+            CutePacketType::ClientStream | CutePacketType::Bidi => {
+                let (input_tx, input_rx) = mpsc::channel(serve_ctx.channel_size);
+                let input_stream : DataStream<Vec<u8>> = Box::pin(tokio_stream::wrappers::ReceiverStream::new(input_rx));
+                match service.server_bidi(call, payload.into_boxed_slice(), input_stream).await {
+                    Ok(mut output_stream) => {
+                        let write_tx = write_tx.clone();
+                        let handle = serve_ctx.spawn(async move {
+                            if protocol_type == CutePacketType::ClientStream {
+                                // client streaming 은 마지막 결과를 unary 응답으로 보냄.
+                                let mut last_output = Ok(vec![]);
+                                while let Some(res) = output_stream.next().await {
+                                    let is_err = res.is_err();
+                                    last_output = res;
+                                    if is_err {
+                                        break;
+                                    }
                                 }
+                                let res_packet = match last_output {
+                                    Ok(output) => Some(P::send_create_packet(output, protocol, correlation_id, CutePacketType::Unary)),
+                                    Err(err) => P::error_create_packet(err, protocol, correlation_id),
+                                };
+                                if let Some(res_packet) = res_packet {
+                                    let _ = write_tx.send(res_packet).await;
+                                }
+                                return;
                             }
-                            Err(e) => {
-                                match e {
-                                    TryRecvError::Empty => {}
-                                    TryRecvError::Disconnected => {
-                                        is_close = true;
+
+                            while let Some(res) = output_stream.next().await {
+                                let res_packet = match res {
+                                    Ok(output) => Some(P::send_create_packet(output, protocol, correlation_id, CutePacketType::Streaming)),
+                                    Err(err) => P::error_create_packet(err, protocol, correlation_id),
+                                };
+                                if let Some(res_packet) = res_packet {
+                                    if write_tx.send(res_packet).await.is_err() {
+                                        return;
                                     }
                                 }
                             }
+                            let _ = write_tx.send(P::send_create_packet(vec![], protocol, correlation_id, CutePacketType::StreamClose)).await;
+                        });
+                        streams.bidi.insert(correlation_id, handle.abort_handle());
+                        streams.inputs.insert(correlation_id, input_tx);
+                    }
+                    Err(err) => {
+                        if let Some(err_packet) = P::error_create_packet(err, protocol, correlation_id) {
+                            let _ = write_tx.send(err_packet).await;
                         }
                     }
                 }
-                drop(arc_close_tx);
-                drop(arc_send_tx);
-                drop(arc_service);
-                drop(arc_peer_map);
-                drop(arc_stream_map);
             }
-        });
-
-        // tcp_write
-        tokio::spawn({
-            let arc_stop_flag = stop_flag.clone();
-            let arc_close_tx = close_tx.clone();
-            let arc_peer_map = peer_map.clone();
-            async move {
-                let mut is_close = false;
-                let mut delay = tokio::time::interval(Duration::from_micros(10));
-                while !is_close {
-                    delay.tick().await;
-                    if *arc_stop_flag.read().await {
-                        is_close = true;
-                    } else {
-                        match send_rx.recv().await {
-                            None => {}
-                            Some((remote_addr, res_packet)) => {
-                                let mut lock_peer_map = arc_peer_map.lock().await;
-                                if let Some((tcp_stream, _)) = lock_peer_map.get_mut(&remote_addr) {
-                                    for item in P::chuck_create_packet(res_packet.get_payload(), res_packet.get_packet_protocol(), res_packet.get_packet_type()) {
-                                        match tcp_stream.write_all(&item.serialize()).await {
-                                            Ok(_) => {}
-                                            Err(_) => {
-                                                let _ = arc_close_tx.send(remote_addr).await;
-                                            }
-                                        }
-                                    }
-                                }
-                                drop(lock_peer_map);
-                            }
-                        }
+            CutePacketType::StreamInput => {
+                // task 가 입력을 처리하지 못하는 경우 해당 연결의 read 는 대기한다.
+                if let Some(input_tx) = streams.inputs.get(&correlation_id) {
+                    if input_tx.send(Ok(payload)).await.is_err() {
+                        streams.inputs.remove(&correlation_id);
                     }
                 }
-                drop(arc_peer_map);
-                drop(arc_close_tx);
-                drop(arc_stop_flag);
             }
-        });
-
-        loop {
-            match listener.accept().await.map_err(|e| CuteError::internal(e.to_string())) {
-                Ok((tcp_stream, remote_addr)) => {
-                    tcp_stream.set_linger(None).expect("set_linger call failed");
-
-                    let mut lock_peer_map = peer_map.lock().await;
-                    lock_peer_map.entry(remote_addr).or_insert((tcp_stream, vec![]));
-                    drop(lock_peer_map);
+            CutePacketType::StreamInputClose => {
+                streams.inputs.remove(&correlation_id);
+            }
+            CutePacketType::Discovery => {
+                let res_packet = match service.server_protocols().await {
+                    Ok(protocols) => Some(P::send_create_packet(encode_protocols(&protocols), protocol, correlation_id, protocol_type)),
+                    Err(err) => P::error_create_packet(err, protocol, correlation_id),
+                };
+                if let Some(res_packet) = res_packet {
+                    let _ = write_tx.send(res_packet).await;
                 }
-                Err(e) => {
-                    warn!("accept failed: {}", e);
-                    break;
+            }
+            CutePacketType::Reflection => {
+                let res_packet = match service.server_reflection().await.and_then(bin_serialize) {
+                    Ok(output) => Some(P::send_create_packet(output, protocol, correlation_id, protocol_type)),
+                    Err(err) => P::error_create_packet(err, protocol, correlation_id),
+                };
+                if let Some(res_packet) = res_packet {
+                    let _ = write_tx.send(res_packet).await;
                 }
             }
         }
-        let mut writer = stop_flag.write().await;
-        *writer = true;
-        drop(writer);
+    }
+
+    async fn write_loop<W : AsyncWrite + Unpin>(mut framed_write : FramedWrite<W, CutePacketCodec<P>>, mut write_rx : mpsc::Receiver<Box<P>>, remote_addr : SocketAddr) {
+        while let Some(res_packet) = write_rx.recv().await {
+            if let Err(e) = framed_write.send(res_packet).await {
+                warn!("{} - server write failed : {}", remote_addr, e);
+                return;
+            }
+        }
+        let _ = framed_write.get_mut().shutdown().await;
+    }
+}
+
+/// protocol 등록 변경 알림을 받음. 구독하지 않았거나 종료된 경우 계속 대기한다.
+async fn recv_event(event_rx : &mut Option<tokio::sync::broadcast::Receiver<ProcEvent>>) -> ProcEvent {
+    loop {
+        match event_rx {
+            Some(rx) => match rx.recv().await {
+                Ok(event) => return event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => *event_rx = None,
+            },
+            None => std::future::pending::<()>().await,
+        }
+    }
+}
+
+/// 합치는 중인 요청의 수 및 크기가 제한을 넘는 경우 `ResourceExhausted`.
+///
+/// client 는 나뉜 packet 을 연속으로 보내므로 정상적인 연결은 제한에 걸리지 않는다.
+fn check_pending(chuck_map : &HashMap<u32, Vec<u8>>, correlation_id : u32, pending_size : usize, max_message_size : usize, max_pending_messages : usize) -> Result<(), CuteError> {
+    if max_pending_messages != 0 && !chuck_map.contains_key(&correlation_id) && chuck_map.len() >= max_pending_messages {
+        return Err(CuteError::resource_exhausted(format!("too many pending messages : {}", chuck_map.len())));
+    }
+    if max_message_size != 0 && pending_size > max_message_size {
+        return Err(CuteError::resource_exhausted(format!("pending message size {} exceeds {}", pending_size, max_message_size)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cute_core::{create_task_constructor, ProcManager, StreamSchedule, Task, TaskConstructor};
+    use crate::{NetworkConfig, Server};
+    use crate::raw::CutePacket;
+    use crate::raw::stub::encode_stream_request;
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Ctx;
+
+    /// input 이 비어 있으면 생성에 실패함.
+    struct Tick;
+
+    #[async_trait::async_trait]
+    impl Task<Ctx> for Tick {
+        fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+            if input.unwrap_or_default().is_empty() {
+                return Err(CuteError::deserialize_invalid("empty input"));
+            }
+            Ok(Box::new(Tick))
+        }
+
+        async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(Some(vec![1]))
+        }
+
+        async fn destroy(&mut self) {}
+    }
+    create_task_constructor!(Tick, TickConstructor, Ctx);
+
+    /// 받은 packet 의 correlation id 및 종류.
+    async fn next_packet<R : AsyncRead + Unpin>(framed_read : &mut FramedRead<R, CutePacketCodec<CutePacket>>) -> (u32, CutePacketType) {
+        let packet = tokio::time::timeout(Duration::from_secs(1), framed_read.next()).await
+            .expect("running stream must not be stopped").unwrap().unwrap();
+        (packet.get_correlation_id(), packet.get_packet_type())
+    }
+
+    /// 실패한 `Streaming` 요청은 같은 protocol 의 실행중인 stream 을 종료하지 않는다.
+    #[tokio::test]
+    async fn failed_stream_keeps_running_stream() {
+        let procedure = ProcManager::new();
+        procedure.insert(0, Box::new(TickConstructor)).unwrap();
+        let config = NetworkConfig {
+            host_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+            ..Default::default()
+        };
+        let handle = Server::create_raw(config).start_server(Box::new(procedure), Arc::new(tokio::sync::RwLock::new(Ctx))).await.unwrap();
+        let (read_half, write_half) = tokio::net::TcpStream::connect(handle.local_addr()).await.unwrap().into_split();
+        let mut framed_read = FramedRead::new(read_half, CutePacketCodec::<CutePacket>::new());
+        let mut framed_write = FramedWrite::new(write_half, CutePacketCodec::<CutePacket>::new());
+
+        let request = |input : Vec<u8>, correlation_id| CutePacket::send_create_packet(input, 0, correlation_id, CutePacketType::Streaming);
+        framed_write.send(request(encode_stream_request(StreamSchedule::Continuous, vec![1]), 1)).await.unwrap();
+        assert_eq!(next_packet(&mut framed_read).await, (1, CutePacketType::Streaming));
+
+        // schedule 을 읽지 못하는 요청 및 Task 생성에 실패하는 요청.
+        framed_write.send(request(vec![0], 2)).await.unwrap();
+        framed_write.send(request(encode_stream_request(StreamSchedule::Continuous, vec![]), 3)).await.unwrap();
+        let mut errors = Vec::new();
+        while errors.len() < 2 {
+            match next_packet(&mut framed_read).await {
+                (1, CutePacketType::Streaming) => {}
+                (correlation_id, CutePacketType::Error) => errors.push(correlation_id),
+                other => panic!("unexpected packet {:?}", other),
+            }
+        }
+        assert_eq!(errors, vec![2, 3]);
+
+        for _ in 0..3 {
+            assert_eq!(next_packet(&mut framed_read).await, (1, CutePacketType::Streaming));
+        }
+        handle.shutdown().await.unwrap();
+    }
+
+    /// peer 별 stream 제한에 걸린 경우에도 같은 protocol 의 stream 은 교체할 수 있다.
+    #[tokio::test]
+    async fn stream_at_peer_limit_is_replaced() {
+        let procedure = ProcManager::new();
+        procedure.insert(0, Box::new(TickConstructor)).unwrap();
+        let config = NetworkConfig {
+            host_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+            backpressure: crate::BackpressureConfig::default().with_max_streams(0, 1),
+            ..Default::default()
+        };
+        let handle = Server::create_raw(config).start_server(Box::new(procedure), Arc::new(tokio::sync::RwLock::new(Ctx))).await.unwrap();
+        let (read_half, write_half) = tokio::net::TcpStream::connect(handle.local_addr()).await.unwrap().into_split();
+        let mut framed_read = FramedRead::new(read_half, CutePacketCodec::<CutePacket>::new());
+        let mut framed_write = FramedWrite::new(write_half, CutePacketCodec::<CutePacket>::new());
+
+        let request = |correlation_id| CutePacket::send_create_packet(encode_stream_request(StreamSchedule::Continuous, vec![1]), 0, correlation_id, CutePacketType::Streaming);
+        framed_write.send(request(1)).await.unwrap();
+        assert_eq!(next_packet(&mut framed_read).await, (1, CutePacketType::Streaming));
+
+        framed_write.send(request(2)).await.unwrap();
+        loop {
+            match next_packet(&mut framed_read).await {
+                (1, CutePacketType::Streaming) => {}
+                (2, CutePacketType::Streaming) => break,
+                other => panic!("unexpected packet {:?}", other),
+            }
+        }
+        // 교체된 stream 은 더 이상 결과를 보내지 않음.
+        for _ in 0..3 {
+            assert_eq!(next_packet(&mut framed_read).await, (2, CutePacketType::Streaming));
+        }
+        handle.shutdown().await.unwrap();
+    }
 
-        Err(CuteError::internal("Server Accept loop failed"))
+    #[test]
+    fn pending_messages_are_limited() {
+        let chuck_map : HashMap<u32, Vec<u8>> = (0..4).map(|id| (id, vec![0])).collect();
+        assert!(check_pending(&chuck_map, 0, 4, 0, 4).is_ok());
+        let err = check_pending(&chuck_map, 4, 5, 0, 4).unwrap_err();
+        assert_eq!(err.code, cute_core::CuteErrorCode::ResourceExhausted);
+        assert!(check_pending(&chuck_map, 4, 5, 0, 0).is_ok());
+
+        let chuck_map = HashMap::from([(1, vec![0; 10])]);
+        assert!(check_pending(&chuck_map, 1, 20, 20, 4).is_ok());
+        assert!(check_pending(&chuck_map, 1, 21, 20, 4).is_err());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,179 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use async_stream::stream;
+use log::warn;
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+use cute_core::{CuteError, CuteErrorCode, DataStream};
+
+/// # Comment
+/// 연결이 끊긴 경우 다시 연결하는 방식.
+///
+/// `attempt` 번째 시도 전에 `initial_backoff * 2^(attempt - 1)` 만큼 기다리며 `max_backoff` 를 넘지 않는다.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_backoff : Duration,
+    pub max_backoff : Duration,
+    /// 한번 끊겼을 때 시도하는 최대 횟수. 0 은 제한하지 않음.
+    pub max_attempts : u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// `attempt` (1 부터) 번째 시도 전에 기다리는 시간.
+    pub fn backoff(&self, attempt : u32) -> Duration {
+        exponential_backoff(self.initial_backoff, self.max_backoff, attempt)
+    }
+}
+
+/// `attempt` (1 부터) 마다 두배로 늘어나며 `max` 를 넘지 않는 대기 시간.
+pub(crate) fn exponential_backoff(initial : Duration, max : Duration, attempt : u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    initial.saturating_mul(factor).min(max)
+}
+
+/// 재연결 진행 상황. `Client::watch_connection` 으로 받는다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// 연결이 끊긴 것을 확인함.
+    Disconnected,
+    /// `backoff` 만큼 기다린 후 `attempt` 번째 연결을 시도함.
+    Reconnecting { attempt : u32, backoff : Duration },
+    /// 다시 연결됨. 이후 `get_stream` 으로 받은 stream 들을 다시 요청한다.
+    Reconnected,
+    /// `max_attempts` 만큼 실패함. 다음 요청에서 다시 시도한다.
+    GaveUp,
+}
+
+/// # Comment
+/// client 의 현재 연결 및 세대.
+///
+/// 재연결은 한번에 하나만 수행되며 그동안 끊긴 것을 확인한 요청들은 기다렸다가 결과를 같이 사용한다.
+#[derive(Debug)]
+pub(crate) struct Reconnector<T> {
+    policy : Option<ReconnectPolicy>,
+    current : std::sync::RwLock<(u64, Arc<T>)>,
+    reconnecting : tokio::sync::Mutex<()>,
+    /// 끝난 재연결 시도 횟수. 기다리는 동안 실패한 시도가 있었는지 확인한다.
+    rounds : AtomicU64,
+    event_tx : broadcast::Sender<ConnectionEvent>,
+}
+
+impl<T> Reconnector<T> {
+    pub(crate) fn new(policy : Option<ReconnectPolicy>, connection : T) -> Self {
+        Self {
+            policy,
+            current: std::sync::RwLock::new((0, Arc::new(connection))),
+            reconnecting: tokio::sync::Mutex::new(()),
+            rounds: AtomicU64::new(0),
+            event_tx: broadcast::channel(64).0,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.policy.is_some()
+    }
+
+    pub(crate) fn current(&self) -> (u64, Arc<T>) {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// `generation` 의 연결이 끊긴 경우 `connect` 로 다시 연결함. 이미 다른 요청이 다시 연결했다면 그 연결을 반환한다.
+    pub(crate) async fn reconnect<F, Fut>(&self, generation : u64, mut connect : F) -> Result<(u64, Arc<T>), CuteError>
+    where F : FnMut() -> Fut,
+          Fut : Future<Output = Result<T, CuteError>>,
+    {
+        let Some(policy) = &self.policy else {
+            return Err(CuteError::unavailable("connection closed"));
+        };
+        let rounds = self.rounds.load(Ordering::Acquire);
+        let _reconnecting = self.reconnecting.lock().await;
+        let current = self.current();
+        if current.0 != generation {
+            return Ok(current);
+        }
+        if self.rounds.load(Ordering::Acquire) != rounds {
+            return Err(CuteError::unavailable("reconnect failed"));
+        }
+
+        let _ = self.event_tx.send(ConnectionEvent::Disconnected);
+        let mut attempt = 0;
+        let result = loop {
+            attempt += 1;
+            if policy.max_attempts != 0 && attempt > policy.max_attempts {
+                let _ = self.event_tx.send(ConnectionEvent::GaveUp);
+                break Err(CuteError::unavailable(format!("reconnect failed after {} attempts", policy.max_attempts)));
+            }
+            let backoff = policy.backoff(attempt);
+            let _ = self.event_tx.send(ConnectionEvent::Reconnecting { attempt, backoff });
+            tokio::time::sleep(backoff).await;
+
+            match connect().await {
+                Ok(connection) => {
+                    let next = (generation + 1, Arc::new(connection));
+                    *self.current.write().unwrap_or_else(|e| e.into_inner()) = next.clone();
+                    let _ = self.event_tx.send(ConnectionEvent::Reconnected);
+                    break Ok(next);
+                }
+                Err(e) => {
+                    warn!("reconnect attempt {} failed : {}", attempt, e);
+                }
+            }
+        };
+        self.rounds.fetch_add(1, Ordering::Release);
+        result
+    }
+}
+
+/// 연결이 끊겨 `Unavailable` 로 끝난 stream 을 `reopen` 으로 다시 요청함.
+///
+/// `is_lost` 는 stream 의 세대를 받아 실제로 연결이 끊겼는지 확인하며 아닌 경우 (`Task` 가 반환한 `Unavailable` 등) 오류를 전달하고 종료한다.
+/// `reopen` 은 끊긴 연결의 세대를 받아 다시 연결한 후 새 stream 을 반환하며 실패하면 오류를 전달하고 종료한다.
+pub(crate) fn resubscribe<T, L, F, Fut>(mut generation : u64, mut stream : DataStream<T>, is_lost : L, reopen : F) -> DataStream<T>
+where T : Send + 'static,
+      L : Fn(u64) -> bool + Send + 'static,
+      F : Fn(u64) -> Fut + Send + 'static,
+      Fut : Future<Output = Result<(u64, DataStream<T>), CuteError>> + Send,
+{
+    Box::pin(stream! {
+        loop {
+            let mut disconnected = false;
+            while let Some(item) = stream.next().await {
+                if matches!(&item, Err(e) if e.code == CuteErrorCode::Unavailable) && is_lost(generation) {
+                    disconnected = true;
+                    break;
+                }
+                yield item;
+            }
+            if !disconnected {
+                break;
+            }
+
+            match reopen(generation).await {
+                Ok((next_generation, next_stream)) => {
+                    generation = next_generation;
+                    stream = next_stream;
+                }
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+    })
+}
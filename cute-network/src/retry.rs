@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use futures_util::stream::FuturesUnordered;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+use cute_core::{CuteError, CuteErrorCode, ProtocolInfo, TaskKind};
+use crate::reconnect::exponential_backoff;
+
+/// # Comment
+/// 실패한 unary 요청을 다시 보내는 방식.
+///
+/// `NetworkConfig::retry` 는 server 가 idempotent 로 알린 protocol 에만 사용되며 `Client::set_retry_policy` 로 protocol 마다 지정할 수 있다.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 처음 요청을 포함한 최대 시도 횟수.
+    pub max_attempts : u32,
+    /// `attempt` 번째 실패 후 `initial_backoff * 2^(attempt - 1)` 만큼 기다리며 `max_backoff` 를 넘지 않는다.
+    pub initial_backoff : Duration,
+    pub max_backoff : Duration,
+    /// 다시 요청하는 오류. 그 외의 오류는 바로 반환한다.
+    pub retryable_codes : Vec<CuteErrorCode>,
+    /// `Some` 인 경우 응답을 기다리지 않고 해당 시간마다 같은 요청을 더 보내며 (hedging) 먼저 성공한 응답을 사용한다.
+    pub hedging_delay : Option<Duration>,
+    /// `None` 인 경우 재시도를 제한하지 않음.
+    pub budget : Option<RetryBudget>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            retryable_codes: vec![CuteErrorCode::Unavailable],
+            hedging_delay: None,
+            budget: Some(RetryBudget::default()),
+        }
+    }
+}
+
+/// # Comment
+/// protocol 마다의 재시도 예산. (gRPC 의 retry throttling 과 같음)
+///
+/// 다시 요청할 수 있는 오류마다 token 이 1 줄고 성공마다 `token_ratio` 만큼 늘며 `max_tokens` 의 절반 이하인 동안 다시 요청하지 않는다.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    pub max_tokens : u32,
+    pub token_ratio : f32,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self {
+            max_tokens: 10,
+            token_ratio: 0.1,
+        }
+    }
+}
+
+/// client 의 protocol 별 재시도 방식 및 예산.
+#[derive(Debug)]
+pub(crate) struct Retrier {
+    default : Option<RetryPolicy>,
+    /// `Client::set_retry_policy` 로 지정한 방식. server 의 idempotent 여부보다 우선한다.
+    overrides : HashMap<u32, Option<RetryPolicy>>,
+    /// `describe_services` 로 받은 protocol 의 idempotent 여부.
+    idempotent : HashMap<u32, bool>,
+    tokens : std::sync::Mutex<HashMap<u32, f32>>,
+}
+
+impl Retrier {
+    pub(crate) fn new(default : Option<RetryPolicy>) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+            idempotent: HashMap::new(),
+            tokens: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn set_policy(&mut self, protocol : u32, policy : Option<RetryPolicy>) {
+        self.overrides.insert(protocol, policy);
+        self.tokens.lock().unwrap_or_else(|e| e.into_inner()).remove(&protocol);
+    }
+
+    pub(crate) fn set_infos(&mut self, infos : &[ProtocolInfo]) {
+        self.idempotent = infos.iter()
+            .map(|info| (info.protocol, info.kind == TaskKind::Task && info.idempotent))
+            .collect();
+    }
+
+    /// 기본 방식을 사용하는데 protocol 의 idempotent 여부를 모르는 경우. `describe_services` 후 다시 확인한다.
+    pub(crate) fn needs_describe(&self, protocol : u32) -> bool {
+        self.default.is_some() && !self.overrides.contains_key(&protocol) && !self.idempotent.contains_key(&protocol)
+    }
+
+    pub(crate) fn policy(&self, protocol : u32) -> Option<RetryPolicy> {
+        if let Some(policy) = self.overrides.get(&protocol) {
+            return policy.clone();
+        }
+        match self.idempotent.get(&protocol) {
+            Some(true) => self.default.clone(),
+            _ => None,
+        }
+    }
+
+    /// `call` 을 `policy` 에 따라 다시 요청함. `call` 은 남은 제한 시간을 받는다.
+    pub(crate) async fn run<F, Fut>(&self, protocol : u32, policy : &RetryPolicy, deadline : Option<Instant>, call : F) -> Result<Vec<u8>, CuteError>
+    where F : Fn(Option<Duration>) -> Fut,
+          Fut : Future<Output = Result<Vec<u8>, CuteError>>,
+    {
+        match policy.hedging_delay {
+            Some(delay) => self.hedge(protocol, policy, delay, deadline, call).await,
+            None => self.retry(protocol, policy, deadline, call).await,
+        }
+    }
+
+    async fn retry<F, Fut>(&self, protocol : u32, policy : &RetryPolicy, deadline : Option<Instant>, call : F) -> Result<Vec<u8>, CuteError>
+    where F : Fn(Option<Duration>) -> Fut,
+          Fut : Future<Output = Result<Vec<u8>, CuteError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let error = match self.record(protocol, policy, call(remaining(deadline)).await) {
+                Ok(output) => return Ok(output),
+                Err(e) => e,
+            };
+            if !policy.retryable_codes.contains(&error.code) || attempt >= policy.max_attempts || !self.can_retry(protocol, policy) {
+                return Err(error);
+            }
+            // server 가 기다릴 시간을 알려준 경우 그 이상 기다림.
+            let backoff = exponential_backoff(policy.initial_backoff, policy.max_backoff, attempt)
+                .max(error.retry_after.unwrap_or_default());
+            if deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+                return Err(error);
+            }
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// `delay` 마다 요청을 더 보내며 먼저 성공한 응답을 반환함. 남은 요청은 drop 되어 취소된다.
+    async fn hedge<F, Fut>(&self, protocol : u32, policy : &RetryPolicy, delay : Duration, deadline : Option<Instant>, call : F) -> Result<Vec<u8>, CuteError>
+    where F : Fn(Option<Duration>) -> Fut,
+          Fut : Future<Output = Result<Vec<u8>, CuteError>>,
+    {
+        let mut pending = FuturesUnordered::new();
+        pending.push(call(remaining(deadline)));
+        let mut sent = 1;
+        loop {
+            let can_send = sent < policy.max_attempts && self.can_retry(protocol, policy);
+            // 모든 요청이 실패한 경우 기다리지 않고 보냄.
+            let hedge_delay = if pending.is_empty() { Duration::ZERO } else { delay };
+            tokio::select! {
+                Some(result) = pending.next() => {
+                    let error = match self.record(protocol, policy, result) {
+                        Ok(output) => return Ok(output),
+                        Err(e) => e,
+                    };
+                    if !policy.retryable_codes.contains(&error.code) {
+                        return Err(error);
+                    }
+                    if pending.is_empty() && !(sent < policy.max_attempts && self.can_retry(protocol, policy)) {
+                        return Err(error);
+                    }
+                }
+                _ = tokio::time::sleep(hedge_delay), if can_send => {
+                    pending.push(call(remaining(deadline)));
+                    sent += 1;
+                }
+            }
+        }
+    }
+
+    /// 결과를 예산에 반영함.
+    fn record(&self, protocol : u32, policy : &RetryPolicy, result : Result<Vec<u8>, CuteError>) -> Result<Vec<u8>, CuteError> {
+        let Some(budget) = &policy.budget else {
+            return result;
+        };
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let max_tokens = budget.max_tokens as f32;
+        let tokens = tokens.entry(protocol).or_insert(max_tokens);
+        match &result {
+            Ok(_) => *tokens = (*tokens + budget.token_ratio).min(max_tokens),
+            Err(e) if policy.retryable_codes.contains(&e.code) => *tokens = (*tokens - 1.0).max(0.0),
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn can_retry(&self, protocol : u32, policy : &RetryPolicy) -> bool {
+        let Some(budget) = &policy.budget else {
+            return true;
+        };
+        let max_tokens = budget.max_tokens as f32;
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner())
+            .get(&protocol).copied()
+            .unwrap_or(max_tokens);
+        tokens > max_tokens / 2.0
+    }
+}
+
+/// deadline 까지 남은 시간. 1ms 미만은 1ms 로 보내 제한 없음 (0) 과 구분한다.
+fn remaining(deadline : Option<Instant>) -> Option<Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()).max(Duration::from_millis(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use cute_core::ProtocolMeta;
+    use super::*;
+
+    fn policy(max_attempts : u32, backoff : Duration) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: backoff,
+            max_backoff: backoff,
+            budget: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_policy_is_only_for_idempotent_tasks() {
+        let mut retrier = Retrier::new(Some(RetryPolicy::default()));
+        assert!(retrier.needs_describe(1));
+        retrier.set_infos(&[
+            ProtocolInfo::new(1, TaskKind::Task, ProtocolMeta::new("write")),
+            ProtocolInfo::new(2, TaskKind::Task, ProtocolMeta::new("read").with_idempotent(true)),
+            ProtocolInfo::new(3, TaskKind::Stream, ProtocolMeta::new("watch").with_idempotent(true)),
+        ]);
+        assert!(!retrier.needs_describe(1));
+        assert!(retrier.policy(1).is_none());
+        assert!(retrier.policy(2).is_some());
+        assert!(retrier.policy(3).is_none());
+        // 모르는 protocol 도 다시 요청하지 않음.
+        assert!(retrier.policy(4).is_none());
+
+        retrier.set_policy(1, Some(RetryPolicy::default()));
+        retrier.set_policy(2, None);
+        assert!(retrier.policy(1).is_some());
+        assert!(retrier.policy(2).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn deadline_covers_all_attempts() {
+        let retrier = Retrier::new(None);
+        let start = Instant::now();
+        let remains = Mutex::new(Vec::new());
+        let err = retrier.run(1, &policy(10, Duration::from_millis(100)), Some(start + Duration::from_millis(250)), |remain| {
+            remains.lock().unwrap().push(remain.unwrap());
+            async { Err(CuteError::unavailable("down")) }
+        }).await.unwrap_err();
+
+        assert_eq!(err.code, CuteErrorCode::Unavailable);
+        // 남은 시간이 backoff 보다 짧으면 다시 요청하지 않고 끝남.
+        assert_eq!(*remains.lock().unwrap(), vec![Duration::from_millis(250), Duration::from_millis(150), Duration::from_millis(50)]);
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backoff_waits_for_retry_after() {
+        let retrier = Retrier::new(None);
+        let start = Instant::now();
+        let attempts = Mutex::new(Vec::new());
+        let policy = RetryPolicy {
+            retryable_codes: vec![CuteErrorCode::ResourceExhausted],
+            ..policy(3, Duration::from_millis(10))
+        };
+        let output = retrier.run(1, &policy, None, |_| {
+            let mut attempts = attempts.lock().unwrap();
+            attempts.push(start.elapsed());
+            let result = match attempts.len() {
+                1 => Err(CuteError::resource_exhausted("limited").with_retry_after(Duration::from_secs(1))),
+                _ => Ok(vec![1]),
+            };
+            async move { result }
+        }).await;
+        assert_eq!(output.unwrap(), vec![1]);
+        assert_eq!(*attempts.lock().unwrap(), vec![Duration::ZERO, Duration::from_secs(1)]);
+
+        // retry_after 가 deadline 을 넘으면 기다리지 않음.
+        let start = Instant::now();
+        let err = retrier.run(1, &policy, Some(start + Duration::from_millis(500)), |_| async {
+            Err(CuteError::resource_exhausted("limited").with_retry_after(Duration::from_secs(1)))
+        }).await.unwrap_err();
+        assert_eq!(err.retry_after, Some(Duration::from_secs(1)));
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn other_errors_are_not_retried() {
+        let retrier = Retrier::new(None);
+        let attempts = Mutex::new(0);
+        let err = retrier.run(1, &policy(3, Duration::from_millis(10)), None, |_| {
+            *attempts.lock().unwrap() += 1;
+            async { Err(CuteError::internal("failed")) }
+        }).await.unwrap_err();
+        assert_eq!(err.code, CuteErrorCode::Internal);
+        assert_eq!(*attempts.lock().unwrap(), 1);
+    }
+}
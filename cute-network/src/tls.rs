@@ -0,0 +1,310 @@
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use cute_core::CuteError;
+use crate::TlsConfig;
+
+/// Raw client 의 TLS 연결 정보.
+#[derive(Clone)]
+pub struct ClientTls {
+    pub connector : TlsConnector,
+    pub server_name : rustls::ServerName,
+}
+
+impl std::fmt::Debug for ClientTls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientTls").field("server_name", &self.server_name).finish()
+    }
+}
+
+fn read_pem(path : &Option<std::path::PathBuf>, name : &str) -> Result<Vec<u8>, CuteError> {
+    let path = path.as_ref().ok_or_else(|| CuteError::internal(format!("tls {} path is not set", name)))?;
+    std::fs::read(path).map_err(|e| CuteError::internal(format!("tls {} read failed : {} ({})", name, e, path.display())))
+}
+
+fn load_certs(path : &Path) -> Result<Vec<rustls::Certificate>, CuteError> {
+    let file = std::fs::File::open(path).map_err(|e| CuteError::internal(format!("tls cert open failed : {} ({})", e, path.display())))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .map_err(|e| CuteError::internal(format!("tls cert parse failed : {} ({})", e, path.display())))?;
+    if certs.is_empty() {
+        return Err(CuteError::internal(format!("tls cert not found ({})", path.display())));
+    }
+    Ok(certs.into_iter().map(rustls::Certificate).collect())
+}
+
+/// PKCS8, RSA, EC 순으로 처음 발견한 key 를 사용함.
+fn load_key(path : &Path) -> Result<rustls::PrivateKey, CuteError> {
+    let file = std::fs::File::open(path).map_err(|e| CuteError::internal(format!("tls key open failed : {} ({})", e, path.display())))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .map_err(|e| CuteError::internal(format!("tls key parse failed : {} ({})", e, path.display())))?;
+    items.into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| CuteError::internal(format!("tls key not found ({})", path.display())))
+}
+
+fn load_roots(path : &Path) -> Result<rustls::RootCertStore, CuteError> {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(&cert).map_err(|e| CuteError::internal(format!("tls ca add failed : {} ({})", e, path.display())))?;
+    }
+    Ok(roots)
+}
+
+fn required_path<'a>(path : &'a Option<std::path::PathBuf>, name : &str) -> Result<&'a Path, CuteError> {
+    path.as_deref().ok_or_else(|| CuteError::internal(format!("tls {} path is not set", name)))
+}
+
+/// Raw server 의 `TlsAcceptor` 생성.
+pub(crate) fn create_acceptor(config : &TlsConfig) -> Result<TlsAcceptor, CuteError> {
+    let certs = load_certs(required_path(&config.cert_path, "cert")?)?;
+    let key = load_key(required_path(&config.key_path, "key")?)?;
+    let builder = rustls::ServerConfig::builder().with_safe_defaults();
+
+    let builder = match (&config.ca_path, config.client_auth_required) {
+        (Some(ca_path), true) => {
+            builder.with_client_cert_verifier(rustls::server::AllowAnyAuthenticatedClient::new(load_roots(ca_path)?).boxed())
+        }
+        (Some(ca_path), false) => {
+            builder.with_client_cert_verifier(rustls::server::AllowAnyAnonymousOrAuthenticatedClient::new(load_roots(ca_path)?).boxed())
+        }
+        (None, true) => {
+            return Err(CuteError::internal("tls client auth required but ca path is not set"));
+        }
+        (None, false) => builder.with_no_client_auth(),
+    };
+    let server_config = builder.with_single_cert(certs, key).map_err(|e| CuteError::internal(format!("tls server config failed : {}", e)))?;
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// Raw client 의 `TlsConnector` 생성. cert 및 key 가 모두 있는 경우 client 인증서를 보낸다.
+pub(crate) fn create_connector(config : &TlsConfig) -> Result<ClientTls, CuteError> {
+    let roots = load_roots(required_path(&config.ca_path, "ca")?)?;
+    let builder = rustls::ClientConfig::builder().with_safe_defaults().with_root_certificates(roots);
+
+    let client_config = match (&config.cert_path, &config.key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            builder.with_client_auth_cert(load_certs(cert_path)?, load_key(key_path)?)
+                .map_err(|e| CuteError::internal(format!("tls client config failed : {}", e)))?
+        }
+        _ => builder.with_no_client_auth(),
+    };
+    let server_name = rustls::ServerName::try_from(config.domain_name.as_str())
+        .map_err(|e| CuteError::internal(format!("tls invalid domain name : {} ({})", e, config.domain_name)))?;
+
+    Ok(ClientTls {
+        connector: TlsConnector::from(Arc::new(client_config)),
+        server_name,
+    })
+}
+
+/// gRPC server 의 TLS 설정. `client_auth_required` 가 아닌 경우 client 인증서는 선택 사항이다.
+pub(crate) fn create_grpc_server_tls(config : &TlsConfig) -> Result<tonic::transport::ServerTlsConfig, CuteError> {
+    let identity = tonic::transport::Identity::from_pem(read_pem(&config.cert_path, "cert")?, read_pem(&config.key_path, "key")?);
+    let mut tls_config = tonic::transport::ServerTlsConfig::new().identity(identity);
+
+    match &config.ca_path {
+        Some(_) => {
+            tls_config = tls_config
+                .client_ca_root(tonic::transport::Certificate::from_pem(read_pem(&config.ca_path, "ca")?))
+                .client_auth_optional(!config.client_auth_required);
+        }
+        None if config.client_auth_required => {
+            return Err(CuteError::internal("tls client auth required but ca path is not set"));
+        }
+        None => {}
+    }
+    Ok(tls_config)
+}
+
+/// gRPC client 의 TLS 설정. cert 및 key 가 모두 있는 경우 client 인증서를 보낸다.
+pub(crate) fn create_grpc_client_tls(config : &TlsConfig) -> Result<tonic::transport::ClientTlsConfig, CuteError> {
+    let mut tls_config = tonic::transport::ClientTlsConfig::new()
+        .ca_certificate(tonic::transport::Certificate::from_pem(read_pem(&config.ca_path, "ca")?))
+        .domain_name(config.domain_name.clone());
+
+    if config.cert_path.is_some() && config.key_path.is_some() {
+        tls_config = tls_config.identity(tonic::transport::Identity::from_pem(read_pem(&config.cert_path, "cert")?, read_pem(&config.key_path, "key")?));
+    }
+    Ok(tls_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use cute_core::{create_task_constructor, CallInfo, CuteError, ProcManager, Task, TaskConstructor};
+    use crate::{Client, NetworkConfig, Server, TlsConfig};
+    use super::*;
+
+    /// 테스트마다 생성한 CA 및 CA 로 서명한 server, client 인증서의 경로.
+    struct TestCerts {
+        dir : PathBuf,
+    }
+
+    impl TestCerts {
+        fn generate(name : &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("cute-tls-{}-{}", std::process::id(), name));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let mut ca_params = rcgen::CertificateParams::new(vec![]);
+            ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            let ca = rcgen::Certificate::from_params(ca_params).unwrap();
+            std::fs::write(dir.join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+
+            for (file, alt_name) in [("server", "localhost"), ("client", "client")] {
+                let cert = rcgen::generate_simple_self_signed(vec![alt_name.to_string()]).unwrap();
+                std::fs::write(dir.join(format!("{}.pem", file)), cert.serialize_pem_with_signer(&ca).unwrap()).unwrap();
+                std::fs::write(dir.join(format!("{}.key", file)), cert.serialize_private_key_pem()).unwrap();
+            }
+            Self { dir }
+        }
+
+        fn server(&self, client_auth_required : bool) -> TlsConfig {
+            TlsConfig {
+                cert_path: Some(self.dir.join("server.pem")),
+                key_path: Some(self.dir.join("server.key")),
+                ca_path: Some(self.dir.join("ca.pem")),
+                client_auth_required,
+                domain_name: String::new(),
+            }
+        }
+
+        fn client(&self, with_cert : bool) -> TlsConfig {
+            TlsConfig {
+                cert_path: with_cert.then(|| self.dir.join("client.pem")),
+                key_path: with_cert.then(|| self.dir.join("client.key")),
+                ca_path: Some(self.dir.join("ca.pem")),
+                client_auth_required: false,
+                domain_name: "localhost".to_string(),
+            }
+        }
+    }
+
+    impl Drop for TestCerts {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// raw server 와 같이 accept 후 받은 값을 그대로 돌려줌. handshake 결과를 반환한다.
+    async fn raw_round_trip(server : &TlsConfig, client : &TlsConfig) -> (Result<(), std::io::Error>, Result<Vec<u8>, std::io::Error>) {
+        let acceptor = create_acceptor(server).unwrap();
+        let connector = create_connector(client).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let accept = tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await?;
+            let mut tls = acceptor.accept(tcp).await?;
+            let mut buf = [0u8; 4];
+            tls.read_exact(&mut buf).await?;
+            tls.write_all(&buf).await?;
+            tls.flush().await
+        });
+        let connect = async {
+            let tcp = tokio::net::TcpStream::connect(addr).await?;
+            let mut tls = connector.connector.connect(connector.server_name.clone(), tcp).await?;
+            tls.write_all(b"cute").await?;
+            let mut buf = vec![0u8; 4];
+            tls.read_exact(&mut buf).await?;
+            Ok(buf)
+        };
+        let connected = connect.await;
+        (accept.await.unwrap(), connected)
+    }
+
+    #[tokio::test]
+    async fn raw_mutual_tls_round_trip() {
+        let certs = TestCerts::generate("raw-mutual");
+        let (accepted, received) = raw_round_trip(&certs.server(true), &certs.client(true)).await;
+        accepted.unwrap();
+        assert_eq!(received.unwrap(), b"cute");
+    }
+
+    #[tokio::test]
+    async fn raw_client_without_cert_is_rejected() {
+        let certs = TestCerts::generate("raw-reject");
+        let (accepted, received) = raw_round_trip(&certs.server(true), &certs.client(false)).await;
+        assert!(accepted.is_err());
+        assert!(received.is_err());
+
+        // 필수가 아닌 경우 인증서 없이 연결됨.
+        let (accepted, received) = raw_round_trip(&certs.server(false), &certs.client(false)).await;
+        accepted.unwrap();
+        assert_eq!(received.unwrap(), b"cute");
+    }
+
+    #[test]
+    fn client_auth_required_needs_ca() {
+        let certs = TestCerts::generate("no-ca");
+        let mut server = certs.server(true);
+        server.ca_path = None;
+        assert!(create_acceptor(&server).is_err());
+        assert!(create_grpc_server_tls(&server).is_err());
+    }
+
+    #[derive(Clone, Default)]
+    struct Ctx;
+
+    struct Echo {
+        input : Vec<u8>,
+    }
+
+    #[async_trait::async_trait]
+    impl Task<Ctx> for Echo {
+        fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+            Ok(Box::new(Echo { input: input.unwrap_or_default().into_vec() }))
+        }
+
+        async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+            Ok(Some(self.input.clone()))
+        }
+
+        async fn destroy(&mut self) {}
+    }
+    create_task_constructor!(Echo, EchoConstructor, Ctx);
+
+    /// `create_grpc_server_tls` 및 `create_grpc_client_tls` 를 사용하는 gRPC server 에 unary 요청.
+    async fn grpc_unary(server : TlsConfig, client : TlsConfig) -> Result<Vec<u8>, CuteError> {
+        let ctx = Arc::new(tokio::sync::RwLock::new(Ctx));
+        let procedure = ProcManager::new();
+        procedure.insert(0, Box::new(EchoConstructor)).unwrap();
+        let config = NetworkConfig {
+            host_address: std::net::SocketAddr::from(([127, 0, 0, 1], 0)),
+            tls: Some(server),
+            ..Default::default()
+        };
+        let handle = Server::create_grpc(config).start_server(Box::new(procedure), ctx.clone()).await.unwrap();
+        let config = NetworkConfig {
+            host_address: handle.local_addr(),
+            tls: Some(client),
+            ..Default::default()
+        };
+        let result = match Client::create_grpc(config, ctx).await {
+            Ok(mut client) => client.get_unary(0, Some(b"cute".to_vec())).await,
+            Err(e) => Err(e),
+        };
+        handle.shutdown().await.unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn grpc_mutual_tls_round_trip() {
+        let certs = TestCerts::generate("grpc-mutual");
+        assert_eq!(grpc_unary(certs.server(true), certs.client(true)).await.unwrap(), b"cute");
+    }
+
+    #[tokio::test]
+    async fn grpc_client_without_cert_is_rejected() {
+        let certs = TestCerts::generate("grpc-reject");
+        assert!(grpc_unary(certs.server(true), certs.client(false)).await.is_err());
+        assert_eq!(grpc_unary(certs.server(false), certs.client(false)).await.unwrap(), b"cute");
+    }
+}
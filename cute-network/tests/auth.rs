@@ -0,0 +1,34 @@
+//! gRPC 의 조회, 감시 및 stream 종료 요청도 인증을 거치는지 확인.
+
+mod common;
+
+use std::sync::Arc;
+use cute_core::{CuteErrorCode, Identity};
+use cute_network::{AuthConfig, NetworkConfig, TokenAuthenticator};
+use common::*;
+
+#[tokio::test]
+async fn grpc_control_calls_require_authentication() {
+    let (handle, config) = start(true, NetworkConfig {
+        auth: AuthConfig {
+            authenticator: Some(Arc::new(TokenAuthenticator::new().with_token("secret", Identity::new("tester")))),
+            ..Default::default()
+        },
+        ..local_config()
+    }).await;
+    let mut client = connect(true, &NetworkConfig { auth: AuthConfig::default(), ..config.clone() }).await;
+
+    assert_eq!(client.get_service_names().await.map_err(|e| e.code), Err(CuteErrorCode::Unauthenticated));
+    assert_eq!(client.describe_services().await.map(|_| ()).map_err(|e| e.code), Err(CuteErrorCode::Unauthenticated));
+    assert_eq!(client.watch_services().await.map(|_| ()).map_err(|e| e.code), Err(CuteErrorCode::Unauthenticated));
+    assert_eq!(client.close_stream(PROBE).await.map_err(|e| e.code), Err(CuteErrorCode::Unauthenticated));
+    assert_eq!(client.close_stream_all().await.map_err(|e| e.code), Err(CuteErrorCode::Unauthenticated));
+
+    let mut client = connect(true, &NetworkConfig {
+        auth: AuthConfig { token: Some("secret".to_string()), ..Default::default() },
+        ..config
+    }).await;
+    assert_eq!(client.get_service_names().await.unwrap(), vec![PROBE]);
+    client.close_stream_all().await.unwrap();
+    handle.shutdown().await.unwrap();
+}
@@ -0,0 +1,33 @@
+//! raw client 에서 처리가 늦은 stream 이 같은 연결의 다른 요청을 막지 않는지 확인.
+
+mod common;
+
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use common::*;
+
+#[tokio::test]
+async fn raw_slow_stream_does_not_block_connection() {
+    let (handle, config) = start(false, local_config()).await;
+    let mut client = connect(false, &config).await;
+
+    // 읽지 않는 stream. 수신 channel (64) 보다 많은 결과가 쌓일 때까지 기다림.
+    let mut stream = client.get_stream(PROBE, input(OK, 0, &[])).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1800)).await;
+
+    let output = tokio::time::timeout(Duration::from_secs(1), client.get_unary(PROBE, input(OK, 0, &[2]))).await
+        .expect("unary must not wait for the slow stream");
+    assert_eq!(output.unwrap(), vec![2]);
+    tokio::time::timeout(Duration::from_secs(1), client.close_stream(PROBE)).await
+        .expect("close_stream must not wait for the slow stream")
+        .unwrap();
+
+    // 쌓인 결과는 순서대로 모두 받은 후 끝남.
+    let mut count = 0;
+    while let Some(item) = tokio::time::timeout(Duration::from_secs(1), stream.next()).await.unwrap() {
+        assert_eq!(item.unwrap(), vec![1]);
+        count += 1;
+    }
+    assert!(count > 64, "{} results", count);
+    handle.shutdown().await.unwrap();
+}
@@ -0,0 +1,144 @@
+//! 통합 test 에서 공유하는 context, `Probe` task 및 server / client 생성.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use cute_core::*;
+use cute_network::{Client, NetworkConfig, Server, ServerHandle};
+
+/// `Probe` 가 등록되는 protocol.
+pub const PROBE : u32 = 0;
+
+/// payload 를 반환함. 비어 있으면 `[1]`.
+pub const OK : u8 = 0;
+/// 세번째 `execute` 에서 `Internal` 로 실패.
+pub const FAIL_LATER : u8 = 1;
+/// 처음 `execute` 에서 `Internal` 로 실패.
+pub const FAIL_NOW : u8 = 2;
+/// `execute` 는 30 초, `destroy` 는 200ms 걸림.
+pub const SLOW : u8 = 3;
+/// 두번째 `execute` 에서 `Unavailable` 을 반환.
+pub const UNAVAILABLE_LATER : u8 = 4;
+/// 생성에 실패함.
+pub const REJECT : u8 = 5;
+/// `execute` 가 300ms 걸림.
+pub const WAIT : u8 = 6;
+/// 항상 `Unavailable` 을 반환.
+pub const UNAVAILABLE : u8 = 7;
+
+/// test 마다 따로 세는 `Probe::destroy` 호출 횟수. input 의 index 를 사용한다.
+pub static DESTROYED : [AtomicUsize; 8] = [const { AtomicUsize::new(0) }; 8];
+
+#[derive(Clone, Default)]
+pub struct Ctx;
+
+/// # Comment
+/// input 의 `[mode, index, payload..]` 에 따라 동작하는 task.
+pub struct Probe {
+    mode : u8,
+    index : usize,
+    payload : Vec<u8>,
+    count : u32,
+}
+
+#[async_trait::async_trait]
+impl Task<Ctx> for Probe {
+    fn new(input : Option<Box<[u8]>>) -> Result<Box<dyn Task<Ctx> + Send>, CuteError> {
+        let input = input.unwrap_or_default();
+        let mode = input.first().copied().unwrap_or(OK);
+        if mode == REJECT {
+            return Err(CuteError::deserialize_invalid("rejected input"));
+        }
+        Ok(Box::new(Probe {
+            mode,
+            index: input.get(1).copied().unwrap_or_default() as usize,
+            payload: input.get(2..).filter(|payload| !payload.is_empty()).unwrap_or(&[1]).to_vec(),
+            count: 0,
+        }))
+    }
+
+    async fn execute(&mut self, _ctx : Arc<tokio::sync::RwLock<Ctx>>, _call : &CallInfo) -> Result<Option<Vec<u8>>, CuteError> {
+        let delay = match self.mode {
+            SLOW => Duration::from_secs(30),
+            WAIT => Duration::from_millis(300),
+            _ => Duration::from_millis(20),
+        };
+        tokio::time::sleep(delay).await;
+        self.count += 1;
+        match self.mode {
+            FAIL_NOW => Err(CuteError::internal("fail now")),
+            FAIL_LATER if self.count > 2 => Err(CuteError::internal("fail later")),
+            UNAVAILABLE_LATER if self.count > 1 => Err(CuteError::unavailable("backend unavailable")),
+            UNAVAILABLE => Err(CuteError::unavailable("backend unavailable")),
+            _ => Ok(Some(self.payload.clone())),
+        }
+    }
+
+    async fn destroy(&mut self) {
+        if self.mode == SLOW {
+            // 정리에 시간이 걸리는 task. 종료시 기다리는지 확인한다.
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        DESTROYED[self.index].fetch_add(1, Ordering::SeqCst);
+    }
+}
+create_task_constructor!(Probe, ProbeConstructor, Ctx);
+create_task_constructor!(Probe, IdempotentProbeConstructor, Ctx, "", idempotent);
+
+/// `Probe` 의 input.
+pub fn input(mode : u8, index : usize, payload : &[u8]) -> Option<Vec<u8>> {
+    let mut input = vec![mode, index as u8];
+    input.extend_from_slice(payload);
+    Some(input)
+}
+
+/// 비동기로 호출되는 destroy 를 기다린 후 호출 횟수를 반환하고 초기화함.
+pub async fn destroyed(index : usize) -> usize {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while DESTROYED[index].load(Ordering::SeqCst) == 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // 두번 호출되는지 확인하기 위해 조금 더 기다림.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    DESTROYED[index].swap(0, Ordering::SeqCst)
+}
+
+pub fn context() -> Arc<tokio::sync::RwLock<Ctx>> {
+    Arc::new(tokio::sync::RwLock::new(Ctx))
+}
+
+/// 사용하지 않는 local port 로 bind 하는 설정.
+pub fn local_config() -> NetworkConfig {
+    NetworkConfig {
+        host_address: std::net::SocketAddr::from(([127, 0, 0, 1], 0)),
+        ..Default::default()
+    }
+}
+
+/// `PROBE` 에 `Probe` 를 등록한 procedure.
+pub fn probe_procedure() -> ProcManager<Ctx> {
+    let procedure = ProcManager::new();
+    procedure.insert(PROBE, Box::new(ProbeConstructor)).unwrap();
+    procedure
+}
+
+/// `Probe` 를 등록한 server 를 시작하고 client 가 사용할 설정을 반환함.
+pub async fn start(grpc : bool, config : NetworkConfig) -> (ServerHandle, NetworkConfig) {
+    start_with(grpc, config, probe_procedure()).await
+}
+
+pub async fn start_with(grpc : bool, mut config : NetworkConfig, procedure : ProcManager<Ctx>) -> (ServerHandle, NetworkConfig) {
+    let server = if grpc { Server::create_grpc(config.clone()) } else { Server::create_raw(config.clone()) };
+    let handle = server.start_server(Box::new(procedure), context()).await.unwrap();
+    config.host_address = handle.local_addr();
+    (handle, config)
+}
+
+pub async fn connect(grpc : bool, config : &NetworkConfig) -> Client<Ctx> {
+    if grpc {
+        Client::create_grpc(config.clone(), context()).await.unwrap()
+    } else {
+        Client::create_raw(config.clone(), context()).await.unwrap()
+    }
+}
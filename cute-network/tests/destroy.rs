@@ -0,0 +1,91 @@
+//! 모든 종료 경로에서 `Task::destroy` 가 한번 호출되는지 확인.
+
+mod common;
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use cute_network::NetworkConfig;
+use tokio_stream::StreamExt;
+use common::*;
+
+async fn destroy_on_every_path(grpc : bool) {
+    let index = grpc as usize;
+    let input = |mode : u8| input(mode, index, &[]);
+    let (handle, config) = start(grpc, local_config()).await;
+    let mut client = connect(grpc, &config).await;
+
+    // 정상 종료
+    client.get_unary(PROBE, input(OK)).await.unwrap();
+    assert_eq!(destroyed(index).await, 1, "unary completion");
+
+    // execute 오류
+    assert!(client.get_unary(PROBE, input(FAIL_NOW)).await.is_err());
+    assert_eq!(destroyed(index).await, 1, "unary execute error");
+    let mut stream = client.get_stream(PROBE, input(FAIL_LATER)).await.unwrap();
+    while let Some(Ok(_)) = stream.next().await {}
+    drop(stream);
+    assert_eq!(destroyed(index).await, 1, "stream execute error");
+
+    // server_stream_close
+    let mut stream = client.get_stream(PROBE, input(OK)).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    client.close_stream(PROBE).await.unwrap();
+    assert_eq!(destroyed(index).await, 1, "stream close");
+    drop(stream);
+
+    // client 연결 해제
+    let mut other = connect(grpc, &config).await;
+    let mut stream = other.get_stream(PROBE, input(OK)).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    drop(stream);
+    drop(other);
+    assert_eq!(destroyed(index).await, 1, "client disconnect");
+
+    // server 종료
+    let mut stream = client.get_stream(PROBE, input(OK)).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    handle.shutdown().await.unwrap();
+    assert_eq!(destroyed(index).await, 1, "server shutdown");
+}
+
+#[tokio::test]
+async fn raw_destroy_on_every_path() {
+    destroy_on_every_path(false).await;
+}
+
+#[tokio::test]
+async fn grpc_destroy_on_every_path() {
+    destroy_on_every_path(true).await;
+}
+
+/// `shutdown_time_out` 이 지나 중단한 요청도 `shutdown` 이 반환되기 전에 destroy 된다.
+async fn destroy_before_shutdown_returns(grpc : bool) {
+    let index = 2 + grpc as usize;
+    let (handle, config) = start(grpc, NetworkConfig {
+        shutdown_time_out: 1,
+        ..local_config()
+    }).await;
+    let mut client = connect(grpc, &config).await;
+    let unary = {
+        let mut client = connect(grpc, &config).await;
+        tokio::spawn(async move {
+            client.get_unary(PROBE, input(SLOW, index, &[])).await
+        })
+    };
+    let _stream = client.get_stream(PROBE, input(SLOW, index, &[])).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    handle.shutdown().await.unwrap();
+    assert_eq!(DESTROYED[index].load(Ordering::SeqCst), 2);
+    assert!(unary.await.unwrap().is_err());
+}
+
+#[tokio::test]
+async fn raw_destroy_before_shutdown_returns() {
+    destroy_before_shutdown_returns(false).await;
+}
+
+#[tokio::test]
+async fn grpc_destroy_before_shutdown_returns() {
+    destroy_before_shutdown_returns(true).await;
+}
@@ -0,0 +1,27 @@
+//! raw server 가 `max_message_size` 를 넘는 요청을 받으면 연결을 종료하는지 확인.
+
+mod common;
+
+use cute_core::CuteErrorCode;
+use cute_network::NetworkConfig;
+use common::*;
+
+#[tokio::test]
+async fn raw_message_over_limit_closes_connection() {
+    let (handle, config) = start(false, NetworkConfig {
+        max_message_size: 200_000,
+        ..local_config()
+    }).await;
+
+    let mut client = connect(false, &config).await;
+    let output = client.get_unary(PROBE, input(OK, 0, &[7; 150_000])).await.unwrap();
+    assert_eq!(output.len(), 150_000);
+
+    let err = client.get_unary(PROBE, input(OK, 0, &[7; 250_000])).await.unwrap_err();
+    assert_eq!(err.code, CuteErrorCode::ResourceExhausted, "{:?}", err);
+    assert!(client.get_unary(PROBE, input(OK, 0, &[])).await.is_err(), "connection must be closed");
+
+    let mut client = connect(false, &config).await;
+    assert!(client.get_unary(PROBE, input(OK, 0, &[])).await.is_ok());
+    handle.shutdown().await.unwrap();
+}
@@ -0,0 +1,112 @@
+//! 실제 server 에 연결한 `ClientPool` 의 연결 관리 확인.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use cute_core::CuteErrorCode;
+use cute_network::{ClientPool, EndpointStatus, NetworkConfig, PoolConfig};
+use common::*;
+
+async fn pool(grpc : bool, config : &NetworkConfig, connections : usize) -> Arc<ClientPool<Ctx>> {
+    let pool_config = PoolConfig {
+        connections_per_endpoint: connections,
+        ..PoolConfig::new(vec![config.host_address])
+    };
+    let pool = if grpc {
+        ClientPool::create_grpc(config.clone(), pool_config, context()).await
+    } else {
+        ClientPool::create_raw(config.clone(), pool_config, context()).await
+    };
+    Arc::new(pool.unwrap())
+}
+
+fn status(pool : &ClientPool<Ctx>) -> (usize, usize) {
+    let endpoints : Vec<EndpointStatus> = pool.endpoints();
+    assert_eq!(endpoints.len(), 1);
+    (endpoints[0].connections, endpoints[0].outstanding)
+}
+
+async fn checkout_and_return(grpc : bool) {
+    let (handle, config) = start(grpc, local_config()).await;
+    let pool = pool(grpc, &config, 2).await;
+    // 연결은 처음 사용할 때 생성한다.
+    assert_eq!(status(&pool), (0, 0));
+
+    for payload in 1..=3u8 {
+        assert_eq!(pool.get_unary(PROBE, input(OK, 0, &[payload])).await.unwrap(), vec![payload]);
+        // 반환된 연결을 다시 사용함.
+        assert_eq!(status(&pool), (1, 0));
+    }
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn raw_checkout_and_return() {
+    checkout_and_return(false).await;
+}
+
+#[tokio::test]
+async fn grpc_checkout_and_return() {
+    checkout_and_return(true).await;
+}
+
+async fn connections_are_limited(grpc : bool) {
+    let (handle, config) = start(grpc, local_config()).await;
+    let pool = pool(grpc, &config, 2).await;
+
+    let requests : Vec<_> = (0..5).map(|_| {
+        let pool = pool.clone();
+        tokio::spawn(async move { pool.get_unary(PROBE, input(WAIT, 0, &[])).await })
+    }).collect();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    // 연결을 기다리는 요청도 처리중인 요청으로 센다.
+    assert_eq!(status(&pool), (2, 5));
+
+    for request in requests {
+        assert_eq!(request.await.unwrap().unwrap(), vec![1]);
+    }
+    assert_eq!(status(&pool), (2, 0));
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn raw_connections_are_limited() {
+    connections_are_limited(false).await;
+}
+
+#[tokio::test]
+async fn grpc_connections_are_limited() {
+    connections_are_limited(true).await;
+}
+
+/// server 가 종료되어 끊긴 연결은 버리고 다음 요청에서 다시 연결한다.
+///
+/// gRPC channel 은 스스로 다시 연결하므로 버리지 않고 그대로 사용한다.
+async fn closed_connection_is_evicted_and_reconnected(grpc : bool) {
+    let (handle, config) = start(grpc, local_config()).await;
+    let pool = pool(grpc, &config, 1).await;
+    pool.get_unary(PROBE, input(OK, 0, &[])).await.unwrap();
+    assert_eq!(status(&pool), (1, 0));
+
+    handle.shutdown().await.unwrap();
+    let err = pool.get_unary(PROBE, input(OK, 0, &[])).await.unwrap_err();
+    assert_eq!(err.code, CuteErrorCode::Unavailable, "{:?}", err);
+    assert_eq!(status(&pool), (if grpc { 1 } else { 0 }, 0));
+
+    // 같은 주소로 다시 시작한 server 에 새로 연결함.
+    let (handle, _) = start(grpc, config.clone()).await;
+    assert_eq!(pool.get_unary(PROBE, input(OK, 0, &[2])).await.unwrap(), vec![2]);
+    assert_eq!(status(&pool), (1, 0));
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn raw_closed_connection_is_evicted_and_reconnected() {
+    closed_connection_is_evicted_and_reconnected(false).await;
+}
+
+#[tokio::test]
+async fn grpc_closed_connection_is_evicted_and_reconnected() {
+    closed_connection_is_evicted_and_reconnected(true).await;
+}
@@ -0,0 +1,34 @@
+//! 요청 수 제한의 `retry_after` 가 client 까지 전달되는지 확인.
+
+mod common;
+
+use std::time::Duration;
+use cute_core::CuteErrorCode;
+use cute_network::NetworkConfig;
+use common::*;
+
+async fn retry_after_reaches_client(grpc : bool) {
+    let (handle, config) = start(grpc, NetworkConfig {
+        request_limit_milli_second: 5_000,
+        ..local_config()
+    }).await;
+    let mut client = connect(grpc, &config).await;
+    client.get_unary(PROBE, input(OK, 0, &[])).await.unwrap();
+
+    let err = client.get_unary(PROBE, input(OK, 0, &[])).await.unwrap_err();
+    assert_eq!(err.code, CuteErrorCode::ResourceExhausted, "{:?}", err);
+    assert_eq!(err.message, format!("protocol {} rate limit exceeded", PROBE));
+    let retry_after = err.retry_after.expect("retry_after must be sent");
+    assert!(retry_after > Duration::from_millis(4_000) && retry_after <= Duration::from_millis(5_000), "{:?}", retry_after);
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn raw_retry_after_reaches_client() {
+    retry_after_reaches_client(false).await;
+}
+
+#[tokio::test]
+async fn grpc_retry_after_reaches_client() {
+    retry_after_reaches_client(true).await;
+}
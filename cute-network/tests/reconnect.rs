@@ -0,0 +1,47 @@
+//! `Task` 가 반환한 `Unavailable` 은 연결이 끊긴 것으로 보지 않는지 확인.
+
+mod common;
+
+use std::time::Duration;
+use cute_core::CuteErrorCode;
+use cute_network::{NetworkConfig, ReconnectPolicy};
+use tokio_stream::StreamExt;
+use common::*;
+
+async fn task_unavailable_is_not_reconnected(grpc : bool) {
+    let (handle, config) = start(grpc, local_config()).await;
+    let mut client = connect(grpc, &NetworkConfig {
+        reconnect: Some(ReconnectPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        }),
+        ..config
+    }).await;
+    let mut events = client.watch_connection();
+
+    let mut stream = client.get_stream(PROBE, input(UNAVAILABLE_LATER, 0, &[])).await.unwrap();
+    let items = tokio::time::timeout(Duration::from_secs(3), async {
+        let mut items = Vec::new();
+        while let Some(item) = stream.next().await {
+            items.push(item.map_err(|e| e.code));
+        }
+        items
+    }).await.expect("stream must end after the task error");
+    assert_eq!(items, vec![Ok(vec![1]), Err(CuteErrorCode::Unavailable)]);
+    assert!(tokio::time::timeout(Duration::from_millis(100), events.next()).await.is_err(), "connection must not be reopened");
+
+    // 같은 연결을 계속 사용함.
+    client.get_unary(PROBE, input(OK, 0, &[])).await.unwrap();
+    assert!(tokio::time::timeout(Duration::from_millis(100), events.next()).await.is_err(), "connection must not be reopened");
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn raw_task_unavailable_is_not_reconnected() {
+    task_unavailable_is_not_reconnected(false).await;
+}
+
+#[tokio::test]
+async fn grpc_task_unavailable_is_not_reconnected() {
+    task_unavailable_is_not_reconnected(true).await;
+}
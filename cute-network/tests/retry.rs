@@ -0,0 +1,45 @@
+//! client 의 기본 `RetryPolicy` 는 idempotent 로 등록된 protocol 만 다시 요청하는지 확인.
+
+mod common;
+
+use std::time::Duration;
+use cute_core::{CuteErrorCode, ProcManager};
+use cute_network::{NetworkConfig, RetryPolicy};
+use common::*;
+
+const IDEMPOTENT_PROBE : u32 = 1;
+
+/// `Probe` 는 요청마다 생성 및 destroy 되므로 destroy 횟수가 시도 횟수이다.
+async fn only_idempotent_is_retried(grpc : bool, index : usize) {
+    let procedure = ProcManager::new();
+    procedure.insert(PROBE, Box::new(ProbeConstructor)).unwrap();
+    procedure.insert(IDEMPOTENT_PROBE, Box::new(IdempotentProbeConstructor)).unwrap();
+    let (handle, config) = start_with(grpc, local_config(), procedure).await;
+    let mut client = connect(grpc, &NetworkConfig {
+        retry: Some(RetryPolicy {
+            initial_backoff: Duration::from_millis(10),
+            budget: None,
+            ..Default::default()
+        }),
+        ..config
+    }).await;
+
+    let err = client.get_unary(PROBE, input(UNAVAILABLE, index, &[])).await.unwrap_err();
+    assert_eq!(err.code, CuteErrorCode::Unavailable);
+    assert_eq!(destroyed(index).await, 1);
+
+    let err = client.get_unary(IDEMPOTENT_PROBE, input(UNAVAILABLE, index, &[])).await.unwrap_err();
+    assert_eq!(err.code, CuteErrorCode::Unavailable);
+    assert_eq!(destroyed(index).await, 3);
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn raw_only_idempotent_is_retried() {
+    only_idempotent_is_retried(false, 0).await;
+}
+
+#[tokio::test]
+async fn grpc_only_idempotent_is_retried() {
+    only_idempotent_is_retried(true, 1).await;
+}
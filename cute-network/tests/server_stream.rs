@@ -0,0 +1,47 @@
+//! 같은 peer 및 protocol 의 stream 요청이 실패해도 실행중인 stream 은 유지되는지 확인.
+
+mod common;
+
+use std::time::Duration;
+use cute_core::CuteErrorCode;
+use cute_network::{BackpressureConfig, NetworkConfig};
+use tokio_stream::StreamExt;
+use common::*;
+
+#[tokio::test]
+async fn grpc_failed_stream_keeps_running_stream() {
+    let (handle, config) = start(true, local_config()).await;
+    let mut client = connect(true, &config).await;
+
+    let mut stream = client.get_stream(PROBE, input(OK, 0, &[])).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+
+    assert!(client.get_stream(PROBE, input(REJECT, 0, &[])).await.is_err());
+
+    for _ in 0..3 {
+        let item = tokio::time::timeout(Duration::from_secs(1), stream.next()).await
+            .expect("running stream must not be stopped");
+        assert_eq!(item.map(|item| item.map_err(|e| e.code)), Some(Ok(vec![1])));
+    }
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn grpc_stream_at_peer_limit_is_replaced() {
+    let (handle, config) = start(true, NetworkConfig {
+        backpressure: BackpressureConfig::default().with_max_streams(0, 1),
+        ..local_config()
+    }).await;
+    let mut client = connect(true, &config).await;
+
+    let mut stream = client.get_stream(PROBE, input(OK, 0, &[1])).await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    // 다른 protocol 의 stream 은 제한된다.
+    assert_eq!(client.get_stream(PROBE + 1, input(OK, 0, &[])).await.map(|_| ()).map_err(|e| e.code), Err(CuteErrorCode::ResourceExhausted));
+
+    let mut replaced = client.get_stream(PROBE, input(OK, 0, &[2])).await.unwrap();
+    let item = tokio::time::timeout(Duration::from_secs(1), replaced.next()).await
+        .expect("replacing stream must run");
+    assert_eq!(item.map(|item| item.map_err(|e| e.code)), Some(Ok(vec![2])));
+    handle.shutdown().await.unwrap();
+}